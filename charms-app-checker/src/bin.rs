@@ -0,0 +1,39 @@
+use charms_app_runner::AppRunner;
+use charms_client::{AppProverInput, app_checker_pv};
+
+pub fn main() {
+    // Read an input to the program.
+    let input_vec = sp1_zkvm::io::read_vec();
+    let input: AppProverInput = charms_data::util::read(input_vec.as_slice()).unwrap();
+
+    let output_vec = run(input);
+
+    // Commit to the public values of the program.
+    sp1_zkvm::io::commit_slice(output_vec.as_slice());
+}
+
+/// Run the app contract: panic unless it is satisfied. Return the public values to commit to.
+pub(crate) fn run(input: AppProverInput) -> Vec<u8> {
+    let AppProverInput {
+        app_binary,
+        app,
+        tx,
+        x,
+        w,
+    } = input;
+
+    // the runner checks `app.vk` is the hash of the binary
+    AppRunner::new(false)
+        .run(&app_binary, &app, &tx, &x, &w)
+        .expect("app contract should be satisfied");
+
+    eprintln!("App contract satisfied: {}", app);
+
+    app_checker_pv(&app, &tx, &x)
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn dummy() {}
+}
@@ -0,0 +1,2 @@
+#![no_main]
+sp1_zkvm::entrypoint!(charms_app_checker::bin::main);
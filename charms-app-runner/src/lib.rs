@@ -1,5 +1,6 @@
 use anyhow::{Result, bail, ensure};
 use charms_data::{App, B32, Data, Transaction, is_simple_transfer, util};
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::{
     collections::BTreeMap,
@@ -229,6 +230,24 @@ fn environ_get(caller: Caller<'_, HostState>, environ_ptr: i32, environ_buf_ptr:
 }
 const MAX_FUEL_PER_RUN: u64 = 1000000000;
 
+/// Result of running an app contract over a transaction.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AppVerdict {
+    pub app: App,
+    /// Is the app contract satisfied?
+    pub satisfied: bool,
+    /// Is the transaction a simple transfer w.r.t. the app? If so, the app contract was not run.
+    pub simple_transfer: bool,
+    /// Cycles (fuel) consumed. `0` unless cycle counting is enabled.
+    pub cycles: u64,
+    /// Why the app contract is not satisfied.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Output the app contract has written to stderr.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub log: String,
+}
+
 impl AppRunner {
     pub fn new(count_cycles: bool) -> Self {
         let mut config = Config::default();
@@ -253,6 +272,26 @@ impl AppRunner {
         tx: &Transaction,
         x: &Data,
         w: &Data,
+    ) -> Result<u64> {
+        self.run_with_stderr(
+            app_binary,
+            app,
+            tx,
+            x,
+            w,
+            Arc::new(Mutex::new(std::io::stderr())),
+        )
+    }
+
+    /// Same as [`AppRunner::run`], but the app's stderr output goes to `stderr`.
+    pub fn run_with_stderr(
+        &self,
+        app_binary: &[u8],
+        app: &App,
+        tx: &Transaction,
+        x: &Data,
+        w: &Data,
+        stderr: Arc<Mutex<dyn Write>>,
     ) -> Result<u64> {
         let vk = self.vk(app_binary);
         ensure!(app.vk == vk, "app.vk mismatch");
@@ -261,7 +300,7 @@ impl AppRunner {
 
         let state = HostState {
             stdin: Arc::new(Mutex::new(stdin_content)),
-            stderr: Arc::new(Mutex::new(std::io::stderr())),
+            stderr,
         };
 
         let mut store = Store::new(&self.engine, state.clone());
@@ -333,4 +372,96 @@ impl AppRunner {
 
         Ok(app_cycles)
     }
+
+    /// Run contracts of all apps of the transaction, like [`AppRunner::run_all`], but without
+    /// stopping at the first unsatisfied one: return a verdict for each app.
+    pub fn run_each(
+        &self,
+        app_binaries: &BTreeMap<B32, Vec<u8>>,
+        tx: &Transaction,
+        app_public_inputs: &BTreeMap<App, Data>,
+        app_private_inputs: &BTreeMap<App, Data>,
+    ) -> Vec<AppVerdict> {
+        let empty = Data::empty();
+        app_public_inputs
+            .iter()
+            .map(|(app, x)| {
+                if is_simple_transfer(app, tx) {
+                    return AppVerdict {
+                        app: app.clone(),
+                        satisfied: true,
+                        simple_transfer: true,
+                        cycles: 0,
+                        error: None,
+                        log: String::new(),
+                    };
+                }
+                let log = Arc::new(Mutex::new(Vec::new()));
+                let result = match app_binaries.get(&app.vk) {
+                    Some(app_binary) => {
+                        let w = app_private_inputs.get(app).unwrap_or(&empty);
+                        self.run_with_stderr(app_binary, app, tx, x, w, log.clone())
+                    }
+                    None => Err(anyhow::anyhow!("app binary not found: {}", app)),
+                };
+                let log = String::from_utf8_lossy(&log.lock().unwrap()).into_owned();
+                AppVerdict {
+                    app: app.clone(),
+                    satisfied: result.is_ok(),
+                    simple_transfer: false,
+                    cycles: *result.as_ref().unwrap_or(&0),
+                    error: result.err().map(|e| e.to_string()),
+                    log,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use charms_data::{NFT, TOKEN};
+
+    #[test]
+    fn run_each_reports_every_app() {
+        let token = App {
+            tag: TOKEN,
+            identity: B32([1; 32]),
+            vk: B32([2; 32]),
+        };
+        let nft = App {
+            tag: NFT,
+            identity: B32([3; 32]),
+            vk: B32([4; 32]),
+        };
+        // the token is not touched: simple transfer; the NFT is minted: needs its contract
+        let tx = Transaction {
+            ins: vec![],
+            refs: vec![],
+            outs: vec![BTreeMap::from([(nft.clone(), Data::from(&"art"))])],
+        };
+        let app_public_inputs =
+            BTreeMap::from([(token.clone(), Data::empty()), (nft.clone(), Data::empty())]);
+
+        let verdicts = AppRunner::new(false).run_each(
+            &BTreeMap::new(),
+            &tx,
+            &app_public_inputs,
+            &BTreeMap::new(),
+        );
+
+        assert_eq!(verdicts.len(), 2);
+        let token_verdict = verdicts.iter().find(|v| v.app == token).unwrap();
+        assert!(token_verdict.satisfied && token_verdict.simple_transfer);
+        let nft_verdict = verdicts.iter().find(|v| v.app == nft).unwrap();
+        assert!(!nft_verdict.satisfied);
+        assert!(
+            nft_verdict
+                .error
+                .as_ref()
+                .unwrap()
+                .contains("app binary not found")
+        );
+    }
 }
@@ -0,0 +1,25 @@
+#![no_main]
+
+use bitcoin::{ScriptBuf, TxIn, Witness};
+use charms_client::bitcoin_tx::parse_spell_and_proof;
+use libfuzzer_sys::fuzz_target;
+
+/// Control block of a single leaf Taproot tree (the internal key is the secp256k1 generator).
+const CONTROL_BLOCK: [u8; 33] = [
+    0xc0, 0x79, 0xbe, 0x66, 0x7e, 0xf9, 0xdc, 0xbb, 0xac, 0x55, 0xa0, 0x62, 0x95, 0xce, 0x87, 0x0b,
+    0x07, 0x02, 0x9b, 0xfc, 0xdb, 0x2d, 0xce, 0x28, 0xd9, 0x59, 0xf2, 0x81, 0x5b, 0x16, 0xf8, 0x17,
+    0x98,
+];
+
+// The input is the leaf script of the spell commitment input.
+fuzz_target!(|data: &[u8]| {
+    let mut witness = Witness::new();
+    witness.push([0u8; 64]);
+    witness.push(ScriptBuf::from_bytes(data.to_vec()));
+    witness.push(CONTROL_BLOCK);
+    let tx_in = TxIn {
+        witness,
+        ..Default::default()
+    };
+    let _ = parse_spell_and_proof(&tx_in);
+});
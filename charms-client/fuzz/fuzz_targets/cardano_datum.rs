@@ -0,0 +1,16 @@
+#![no_main]
+
+use charms_client::{
+    cardano_tx::CardanoTx,
+    read_spell_data,
+    tx::{EnchantedTx, Tx},
+};
+use libfuzzer_sys::fuzz_target;
+
+// The input is either the spell datum bytes or a whole Cardano transaction.
+fuzz_target!(|data: &[u8]| {
+    let _ = read_spell_data(data);
+    if let Ok(tx) = CardanoTx::from_hex(&hex::encode(data)) {
+        let _ = Tx::Cardano(tx).extract_and_verify_spell("", true);
+    }
+});
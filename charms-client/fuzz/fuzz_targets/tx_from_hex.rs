@@ -0,0 +1,16 @@
+#![no_main]
+
+use charms_client::tx::{EnchantedTx, Tx};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let hex = match std::str::from_utf8(data) {
+        Ok(s) => s.to_string(),
+        Err(_) => hex::encode(data),
+    };
+    if let Ok(tx) = Tx::from_hex(&hex) {
+        let _ = tx.extract_and_verify_spell("", true);
+        let _ = tx.tx_ins();
+        let _ = tx.hex();
+    }
+});
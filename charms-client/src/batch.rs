@@ -0,0 +1,122 @@
+use crate::{Error, NormalizedSpell, Proof, SPELL_DATA_LIMITS, tx::to_serialized_pv};
+use charms_data::{B32, util};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Prefix of batch proofs and of their public values.
+///
+/// Single-spell public values are CBOR arrays (starting with `0x82`), so they never start with
+/// this prefix.
+pub const BATCH_PROOF_PREFIX: &[u8] = b"charms-batch";
+
+/// Proof covering a batch of spells, shared by all transactions of the batch.
+///
+/// The proof wrapper verifies the spell checker proof for each spell of the batch and commits to
+/// [`batch_public_values`] of their [`spell_hash`]es.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchProof {
+    /// Hashes of the spell checker public values `(spell_vk, spell)` of all spells of the batch.
+    pub spell_hashes: Vec<B32>,
+    /// SNARK (Groth16 or PLONK) proof of the batch. PLONK proofs are tagged with
+    /// [`PLONK_PROOF_PREFIX`](crate::tx::PLONK_PROOF_PREFIX).
+    pub proof: Proof,
+}
+
+impl BatchProof {
+    /// Is this proof (as found in spell data) a batch proof?
+    pub fn is_batch(proof: &[u8]) -> bool {
+        proof.starts_with(BATCH_PROOF_PREFIX)
+    }
+
+    /// Encode as a [`Proof`] to be committed in spell data.
+    pub fn to_proof(&self) -> Proof {
+        let mut proof = BATCH_PROOF_PREFIX.to_vec();
+        proof.extend(util::write(self).expect("batch proof should serialize successfully"));
+        proof
+    }
+
+    /// Decode from a [`Proof`] found in spell data.
+    pub fn from_proof(proof: &[u8]) -> Result<Self, Error> {
+        let Some(data) = proof.strip_prefix(BATCH_PROOF_PREFIX) else {
+            return Err(Error::Decode("batch proof: missing prefix".to_string()));
+        };
+        util::read_bounded(data, &SPELL_DATA_LIMITS)
+            .map_err(|e| Error::Decode(format!("batch proof: {}", e)))
+    }
+
+    /// Public values the proof wrapper commits to for this batch.
+    pub fn public_values(&self) -> Vec<u8> {
+        batch_public_values(&self.spell_hashes)
+    }
+}
+
+/// Hash identifying a spell in a batch: SHA-256 of the spell checker public values
+/// `(spell_vk, spell)`.
+pub fn spell_hash(spell_vk: &str, spell: &NormalizedSpell) -> B32 {
+    let committed_data = to_serialized_pv(spell.version, &(spell_vk, spell));
+    committed_data_hash(&committed_data)
+}
+
+/// SHA-256 of the public values committed by the spell checker.
+pub fn committed_data_hash(committed_data: &[u8]) -> B32 {
+    B32(Sha256::digest(committed_data).into())
+}
+
+/// Public values committed by the proof wrapper for a batch of spells:
+/// [`BATCH_PROOF_PREFIX`] followed by the spell hashes.
+pub fn batch_public_values(spell_hashes: &[B32]) -> Vec<u8> {
+    let mut public_values = BATCH_PROOF_PREFIX.to_vec();
+    for spell_hash in spell_hashes {
+        public_values.extend_from_slice(&spell_hash.0);
+    }
+    public_values
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::CURRENT_VERSION;
+
+    #[test]
+    fn batch_proof_roundtrip() {
+        let batch_proof = BatchProof {
+            spell_hashes: vec![B32([1; 32]), B32([2; 32])],
+            proof: vec![3; 8],
+        };
+        let proof = batch_proof.to_proof();
+        assert!(BatchProof::is_batch(&proof));
+        assert_eq!(BatchProof::from_proof(&proof).unwrap(), batch_proof);
+
+        assert!(!BatchProof::is_batch(&[3; 8]));
+        assert!(matches!(
+            BatchProof::from_proof(&[3; 8]),
+            Err(Error::Decode(_))
+        ));
+    }
+
+    #[test]
+    fn batch_public_values_layout() {
+        let public_values = batch_public_values(&[B32([1; 32]), B32([2; 32])]);
+        assert_eq!(public_values.len(), BATCH_PROOF_PREFIX.len() + 64);
+        assert!(public_values.starts_with(BATCH_PROOF_PREFIX));
+        assert_eq!(&public_values[BATCH_PROOF_PREFIX.len() + 32..], &[2; 32]);
+
+        let spell = NormalizedSpell {
+            version: CURRENT_VERSION,
+            tx: crate::NormalizedTransaction {
+                ins: None,
+                refs: None,
+                outs: vec![],
+                beamed_outs: None,
+            },
+            app_public_inputs: Default::default(),
+            mock: false,
+        };
+        let committed_data = util::write(&("vk", &spell)).unwrap();
+        assert!(!committed_data.starts_with(BATCH_PROOF_PREFIX));
+        assert_eq!(
+            spell_hash("vk", &spell),
+            committed_data_hash(&committed_data)
+        );
+    }
+}
@@ -1,66 +1,64 @@
-use crate::{NormalizedSpell, Proof, tx, tx::EnchantedTx};
-use anyhow::{anyhow, bail, ensure};
+use crate::{Error, NormalizedSpell, Proof, tx::EnchantedTx};
 use bitcoin::{
     TxIn,
     consensus::encode::{deserialize_hex, serialize_hex},
     hashes::Hash,
     opcodes::all::{OP_ENDIF, OP_IF},
     script::{Instruction, PushBytes},
+    taproot::TAPROOT_CONTROL_BASE_SIZE,
 };
-use charms_data::{TxId, UtxoId, util};
+use charms_data::{TxId, UtxoId};
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct BitcoinTx(pub bitcoin::Transaction);
 
 impl BitcoinTx {
-    pub fn from_hex(hex: &str) -> anyhow::Result<Self> {
-        let tx = deserialize_hex(hex)?;
+    pub fn from_hex(hex: &str) -> Result<Self, Error> {
+        let tx = deserialize_hex(hex).map_err(|e| Error::Decode(e.to_string()))?;
         Ok(Self(tx))
     }
 }
 
 impl EnchantedTx for BitcoinTx {
-    fn extract_and_verify_spell(
-        &self,
-        spell_vk: &str,
-        mock: bool,
-    ) -> anyhow::Result<NormalizedSpell> {
+    fn extract_spell(&self) -> Result<(NormalizedSpell, Proof), Error> {
         let tx = &self.0;
 
         let Some((spell_tx_in, tx_ins)) = tx.input.split_last() else {
-            bail!("transaction does not have inputs")
+            return Err(Error::NoSpell("transaction does not have inputs".into()));
         };
 
         let (spell, proof) = parse_spell_and_proof(spell_tx_in)?;
 
-        if !mock {
-            ensure!(!spell.mock, "spell is a mock, but we are not in mock mode");
+        if spell.tx.ins.is_some() {
+            return Err(Error::InputOutputMismatch(
+                "spell must inherit inputs from the enchanted tx".into(),
+            ));
+        }
+        if spell.tx.outs.len() > tx.output.len() {
+            return Err(Error::InputOutputMismatch("spell tx outs mismatch".into()));
         }
-        ensure!(
-            spell.tx.ins.is_none(),
-            "spell must inherit inputs from the enchanted tx"
-        );
-        ensure!(
-            spell.tx.outs.len() <= tx.output.len(),
-            "spell tx outs mismatch"
-        );
 
         let spell = spell_with_ins(spell, tx_ins);
 
-        let spell_vk = tx::spell_vk(spell.version, spell_vk, spell.mock)?;
-
-        let public_values = tx::to_serialized_pv(spell.version, &(spell_vk, &spell));
-
-        tx::verify_snark_proof(&proof, &public_values, spell_vk, spell.version, spell.mock)?;
-
-        Ok(spell)
+        Ok((spell, proof))
     }
 
     fn tx_outs_len(&self) -> usize {
         self.0.output.len()
     }
 
+    fn tx_ins(&self) -> Vec<UtxoId> {
+        self.0
+            .input
+            .iter()
+            .map(|tx_in| {
+                let out_point = tx_in.previous_output;
+                UtxoId(TxId(out_point.txid.to_byte_array()), out_point.vout)
+            })
+            .collect()
+    }
+
     fn tx_id(&self) -> TxId {
         TxId(self.0.compute_txid().to_byte_array())
     }
@@ -87,31 +85,39 @@ pub(crate) fn spell_with_ins(spell: NormalizedSpell, spell_tx_ins: &[TxIn]) -> N
 }
 
 #[tracing::instrument(level = "debug", skip_all)]
-pub fn parse_spell_and_proof(spell_tx_in: &TxIn) -> anyhow::Result<(NormalizedSpell, Proof)> {
-    ensure!(
-        spell_tx_in
-            .witness
-            .taproot_control_block()
-            .ok_or(anyhow!("no control block"))?
-            .len()
-            == 33,
-        "the Taproot tree contains more than one leaf: only a single script is supported"
-    );
+pub fn parse_spell_and_proof(spell_tx_in: &TxIn) -> Result<(NormalizedSpell, Proof), Error> {
+    // the spell leaf must be the only leaf in the Taproot tree: otherwise, the commitment output
+    // could be spent with a different spell, without changing the spending transaction's ID
+    let control_block = spell_tx_in
+        .witness
+        .taproot_control_block()
+        .ok_or(Error::NoSpell("no control block".into()))?;
+    if control_block.len() != TAPROOT_CONTROL_BASE_SIZE {
+        return Err(Error::MalformedEnvelope(
+            "the Taproot tree contains more than one leaf: only a single script is supported"
+                .into(),
+        ));
+    }
 
     let leaf_script = spell_tx_in
         .witness
         .taproot_leaf_script()
-        .ok_or(anyhow!("no spell data in the last input's witness"))?;
+        .ok_or(Error::NoSpell(
+            "no spell data in the last input's witness".into(),
+        ))?;
 
     let mut instructions = leaf_script.script.instructions();
 
-    ensure!(instructions.next() == Some(Ok(Instruction::PushBytes(PushBytes::empty()))));
-    ensure!(instructions.next() == Some(Ok(Instruction::Op(OP_IF))));
+    if instructions.next() != Some(Ok(Instruction::PushBytes(PushBytes::empty())))
+        || instructions.next() != Some(Ok(Instruction::Op(OP_IF)))
+    {
+        return Err(Error::NoSpell("no envelope".into()));
+    }
     let Some(Ok(Instruction::PushBytes(push_bytes))) = instructions.next() else {
-        bail!("no spell data")
+        return Err(Error::NoSpell("no spell data".into()));
     };
     if push_bytes.as_bytes() != b"spell" {
-        bail!("no spell marker")
+        return Err(Error::NoSpell("no spell marker".into()));
     }
 
     let mut spell_data = vec![];
@@ -125,12 +131,110 @@ pub fn parse_spell_and_proof(spell_tx_in: &TxIn) -> anyhow::Result<(NormalizedSp
                 break;
             }
             _ => {
-                bail!("unexpected opcode")
+                return Err(Error::MalformedEnvelope("unexpected opcode".into()));
             }
         }
     }
 
-    let (spell, proof): (NormalizedSpell, Proof) = util::read(spell_data.as_slice())
-        .map_err(|e| anyhow!("could not parse spell and proof: {}", e))?;
-    Ok((spell, proof))
+    crate::read_spell_data(&spell_data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CURRENT_VERSION, NormalizedTransaction};
+    use bitcoin::{
+        ScriptBuf, Witness,
+        key::Secp256k1,
+        opcodes::{OP_FALSE, OP_TRUE, all::OP_CHECKSIG},
+        secp256k1::Keypair,
+        taproot::{LeafVersion, TaprootBuilder},
+    };
+    use charms_data::util;
+    use std::collections::BTreeMap;
+
+    fn spell_tx_in(extra_leaves: usize) -> (TxIn, NormalizedSpell) {
+        let spell = NormalizedSpell {
+            version: CURRENT_VERSION,
+            tx: NormalizedTransaction {
+                ins: None,
+                refs: None,
+                outs: vec![BTreeMap::new()],
+                beamed_outs: None,
+            },
+            app_public_inputs: BTreeMap::new(),
+            mock: true,
+        };
+        let spell_data = util::write(&(&spell, vec![1u8; 8])).unwrap();
+
+        let secp256k1 = Secp256k1::new();
+        let keypair = Keypair::from_seckey_slice(&secp256k1, &[1; 32]).unwrap();
+        let (public_key, _) = keypair.x_only_public_key();
+        let script = ScriptBuf::builder()
+            .push_opcode(OP_FALSE)
+            .push_opcode(OP_IF)
+            .push_slice(b"spell")
+            .push_slice::<&PushBytes>(spell_data.as_slice().try_into().unwrap())
+            .push_opcode(OP_ENDIF)
+            .push_slice(public_key.serialize())
+            .push_opcode(OP_CHECKSIG)
+            .into_script();
+
+        let leaves = (0..extra_leaves).map(|i| {
+            let extra = ScriptBuf::builder()
+                .push_int(i as i64)
+                .push_opcode(OP_TRUE)
+                .into_script();
+            (1, extra)
+        });
+        let spend_info = TaprootBuilder::with_huffman_tree(
+            [(extra_leaves.max(1) as u32, script.clone())]
+                .into_iter()
+                .chain(leaves),
+        )
+        .unwrap()
+        .finalize(&secp256k1, public_key)
+        .unwrap();
+        let control_block = spend_info
+            .control_block(&(script.clone(), LeafVersion::TapScript))
+            .unwrap();
+
+        let mut witness = Witness::new();
+        witness.push([0u8; 64]);
+        witness.push(script);
+        witness.push(control_block.serialize());
+        let tx_in = TxIn {
+            witness,
+            ..Default::default()
+        };
+        (tx_in, spell)
+    }
+
+    #[test]
+    fn parse_single_leaf() {
+        let (tx_in, spell) = spell_tx_in(0);
+        let (parsed, proof) = parse_spell_and_proof(&tx_in).unwrap();
+        assert_eq!(parsed, spell);
+        assert_eq!(proof, vec![1u8; 8]);
+    }
+
+    #[test]
+    fn reject_leaf_in_larger_tree() {
+        for extra_leaves in [1, 2, 5] {
+            let (tx_in, _) = spell_tx_in(extra_leaves);
+            assert!(matches!(
+                parse_spell_and_proof(&tx_in),
+                Err(Error::MalformedEnvelope(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn reject_invalid_control_block() {
+        let (mut tx_in, _) = spell_tx_in(0);
+        let mut elements: Vec<Vec<u8>> = tx_in.witness.iter().map(|e| e.to_vec()).collect();
+        elements[2].push(0);
+        tx_in.witness = Witness::from_slice(&elements);
+        assert!(parse_spell_and_proof(&tx_in).is_err());
+    }
 }
@@ -0,0 +1,398 @@
+use crate::NormalizedSpell;
+use bitcoin::hashes::{Hash, HashEngine, Hmac, HmacEngine, sha256};
+use charms_data::{B32, util};
+use serde::{Serialize, de::DeserializeOwned};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+/// Key of an entry in an [`LruCache`].
+pub trait CacheKey: Clone + Ord {
+    /// Stable name of the key in persistent stores.
+    fn hash(&self) -> [u8; 32];
+}
+
+/// Key of a verified spell in a [`SpellCache`].
+///
+/// A spell extracted from a transaction is only known to be correct with respect to the spell VK
+/// it has been verified against (and whether mock spells were accepted).
+///
+/// Transactions are identified by their wtxid (see [`crate::tx::Tx::wtx_id`]): the transaction ID
+/// does not commit to witness data, so transactions with the same ID may carry different spells.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SpellCacheKey {
+    pub wtx_id: B32,
+    pub spell_vk: String,
+    pub mock: bool,
+}
+
+impl SpellCacheKey {
+    pub fn new(wtx_id: B32, spell_vk: &str, mock: bool) -> Self {
+        Self {
+            wtx_id,
+            spell_vk: spell_vk.to_string(),
+            mock,
+        }
+    }
+}
+
+impl CacheKey for SpellCacheKey {
+    /// SHA256 hash of the key.
+    fn hash(&self) -> [u8; 32] {
+        let bytes = util::write(&(&self.wtx_id, &self.spell_vk, self.mock))
+            .expect("serialization is expected to succeed");
+        Sha256::digest(bytes).into()
+    }
+}
+
+impl CacheKey for B32 {
+    fn hash(&self) -> [u8; 32] {
+        self.0
+    }
+}
+
+/// Storage for spells that have been extracted from transactions and successfully verified.
+///
+/// Only positive verification results are cached: the spell and its proof are committed to by the
+/// wtxid, so a verified spell stays verified. Transactions without a correct spell are re-checked
+/// every time.
+pub trait SpellCache: Send + Sync {
+    /// Get the verified spell for the key, if cached.
+    fn get(&self, key: &SpellCacheKey) -> Option<NormalizedSpell>;
+    /// Store the verified spell under the key.
+    fn put(&self, key: SpellCacheKey, spell: NormalizedSpell);
+}
+
+/// [`SpellCache`] that doesn't cache anything.
+pub struct NoSpellCache;
+
+impl SpellCache for NoSpellCache {
+    fn get(&self, _key: &SpellCacheKey) -> Option<NormalizedSpell> {
+        None
+    }
+
+    fn put(&self, _key: SpellCacheKey, _spell: NormalizedSpell) {}
+}
+
+/// In-memory LRU [`SpellCache`], optionally backed by an authenticated on-disk store.
+pub type MemorySpellCache = LruCache<SpellCacheKey, NormalizedSpell>;
+
+impl MemorySpellCache {
+    /// Back the cache with an on-disk store in directory `dir`: spells evicted from memory (or
+    /// cached by another process) are loaded from there.
+    ///
+    /// Entries are authenticated with `secret`: entries not written by a process holding it are
+    /// ignored. Keep it out of `dir`.
+    pub fn with_authenticated_disk_store(self, dir: impl Into<PathBuf>, secret: [u8; 32]) -> Self {
+        self.with_disk_store(DiskStore::new(dir).authenticated(secret))
+    }
+}
+
+impl SpellCache for MemorySpellCache {
+    fn get(&self, key: &SpellCacheKey) -> Option<NormalizedSpell> {
+        LruCache::get(self, key, |_| true)
+    }
+
+    fn put(&self, key: SpellCacheKey, spell: NormalizedSpell) {
+        LruCache::put(self, key, spell)
+    }
+}
+
+/// In-memory LRU cache, optionally backed by a [`DiskStore`].
+pub struct LruCache<K, V> {
+    capacity: usize,
+    state: Mutex<LruState<K, V>>,
+    disk: Option<DiskStore>,
+}
+
+struct LruState<K, V> {
+    tick: u64,
+    entries: BTreeMap<K, (u64, V)>,
+    recency: BTreeMap<u64, K>,
+}
+
+impl<K: CacheKey, V: Clone> LruState<K, V> {
+    fn touch(&mut self, key: &K) -> Option<V> {
+        self.tick += 1;
+        let tick = self.tick;
+        let (last_used, value) = self.entries.get_mut(key)?;
+        self.recency.remove(last_used);
+        self.recency.insert(tick, key.clone());
+        *last_used = tick;
+        Some(value.clone())
+    }
+
+    fn insert(&mut self, key: K, value: V, capacity: usize) {
+        self.tick += 1;
+        let tick = self.tick;
+        if let Some((last_used, _)) = self.entries.insert(key.clone(), (tick, value)) {
+            self.recency.remove(&last_used);
+        }
+        self.recency.insert(tick, key);
+        while self.entries.len() > capacity {
+            let Some((_, lru_key)) = self.recency.pop_first() else {
+                unreachable!("recency and entries are expected to have the same length")
+            };
+            self.entries.remove(&lru_key);
+        }
+    }
+}
+
+impl<K: CacheKey, V: Clone + Serialize + DeserializeOwned> LruCache<K, V> {
+    /// New in-memory cache holding up to `capacity` entries.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            state: Mutex::new(LruState {
+                tick: 0,
+                entries: BTreeMap::new(),
+                recency: BTreeMap::new(),
+            }),
+            disk: None,
+        }
+    }
+
+    /// Back the cache with the on-disk store: entries evicted from memory (or cached by another
+    /// process) are loaded from there.
+    pub fn with_disk_store(self, disk: DiskStore) -> Self {
+        Self {
+            disk: Some(disk),
+            ..self
+        }
+    }
+
+    /// Number of entries currently held in memory.
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().entries.len()
+    }
+
+    /// Is the in-memory part of the cache empty?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the value for the key, if cached. Values loaded from the disk store are only returned
+    /// if `check` accepts them.
+    pub fn get(&self, key: &K, check: impl FnOnce(&V) -> bool) -> Option<V> {
+        if let Some(value) = self.state.lock().unwrap().touch(key) {
+            return Some(value);
+        }
+        let value: V = self.disk.as_ref()?.get(&key.hash())?;
+        if !check(&value) {
+            tracing::warn!("ignoring invalid cache entry {}", hex::encode(key.hash()));
+            return None;
+        }
+        self.state
+            .lock()
+            .unwrap()
+            .insert(key.clone(), value.clone(), self.capacity);
+        Some(value)
+    }
+
+    /// Store the value under the key.
+    pub fn put(&self, key: K, value: V) {
+        if let Some(disk) = &self.disk {
+            disk.put(&key.hash(), &value);
+        }
+        self.state.lock().unwrap().insert(key, value, self.capacity);
+    }
+}
+
+/// Distinguishes temporary files of concurrent [`DiskStore`] writes within a process.
+static TMP_FILE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// On-disk store for an [`LruCache`]: one CBOR file per entry in a directory, named by the key
+/// hash.
+pub struct DiskStore {
+    dir: PathBuf,
+    secret: Option<[u8; 32]>,
+}
+
+impl DiskStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            secret: None,
+        }
+    }
+
+    /// Authenticate entries with HMAC-SHA256 keyed by `secret`: anyone who can write to the
+    /// directory can put anything there.
+    pub fn authenticated(self, secret: [u8; 32]) -> Self {
+        Self {
+            secret: Some(secret),
+            ..self
+        }
+    }
+
+    fn path(&self, key_hash: &[u8; 32]) -> PathBuf {
+        self.dir.join(format!("{}.cbor", hex::encode(key_hash)))
+    }
+
+    fn mac(&self, key_hash: &[u8; 32], value_bytes: &[u8]) -> Option<[u8; 32]> {
+        let secret = self.secret.as_ref()?;
+        let mut engine = HmacEngine::<sha256::Hash>::new(secret);
+        engine.input(key_hash);
+        engine.input(value_bytes);
+        Some(Hmac::from_engine(engine).to_byte_array())
+    }
+
+    fn get<V: DeserializeOwned>(&self, key_hash: &[u8; 32]) -> Option<V> {
+        let bytes = std::fs::read(self.path(key_hash)).ok()?;
+        let (stored_key_hash, value_bytes, mac): ([u8; 32], Vec<u8>, Option<[u8; 32]>) =
+            util::read(bytes.as_slice())
+                .map_err(|e| tracing::warn!("could not read cache entry: {}", e))
+                .ok()?;
+        if stored_key_hash != *key_hash {
+            return None;
+        }
+        if let Some(expected_mac) = self.mac(key_hash, &value_bytes)
+            && !mac.is_some_and(|mac| constant_time_eq(&mac, &expected_mac))
+        {
+            tracing::warn!(
+                "ignoring unauthenticated cache entry {}",
+                hex::encode(key_hash)
+            );
+            return None;
+        }
+        util::read(value_bytes.as_slice())
+            .map_err(|e| tracing::warn!("could not read cache entry: {}", e))
+            .ok()
+    }
+
+    fn put<V: Serialize>(&self, key_hash: &[u8; 32], value: &V) {
+        let path = self.path(key_hash);
+        let write = || -> anyhow::Result<()> {
+            std::fs::create_dir_all(&self.dir)?;
+            let value_bytes = util::write(value)?;
+            let mac = self.mac(key_hash, &value_bytes);
+            let bytes = util::write(&(key_hash, &value_bytes, mac))?;
+            // write to a temporary file first: concurrent readers never see partial writes
+            let tmp_path = path.with_extension(format!(
+                "{}.{}.tmp",
+                std::process::id(),
+                TMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+            ));
+            std::fs::write(&tmp_path, bytes)?;
+            std::fs::rename(&tmp_path, &path)?;
+            Ok(())
+        };
+        if let Err(e) = write() {
+            tracing::warn!("could not write cache entry {:?}: {}", path, e);
+        }
+    }
+}
+
+fn constant_time_eq(a: &[u8; 32], b: &[u8; 32]) -> bool {
+    a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CURRENT_VERSION, NormalizedTransaction};
+
+    fn spell(n: u32) -> NormalizedSpell {
+        NormalizedSpell {
+            version: CURRENT_VERSION,
+            tx: NormalizedTransaction {
+                ins: None,
+                refs: None,
+                outs: vec![BTreeMap::new(); n as usize],
+                beamed_outs: None,
+            },
+            app_public_inputs: BTreeMap::new(),
+            mock: false,
+        }
+    }
+
+    fn key(n: u8) -> SpellCacheKey {
+        SpellCacheKey::new(B32([n; 32]), "0x00", false)
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("charms-{}-{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let cache = MemorySpellCache::new(2);
+        SpellCache::put(&cache, key(1), spell(1));
+        SpellCache::put(&cache, key(2), spell(2));
+        assert_eq!(SpellCache::get(&cache, &key(1)), Some(spell(1)));
+
+        SpellCache::put(&cache, key(3), spell(3));
+        assert_eq!(cache.len(), 2);
+        assert_eq!(SpellCache::get(&cache, &key(2)), None);
+        assert_eq!(SpellCache::get(&cache, &key(1)), Some(spell(1)));
+        assert_eq!(SpellCache::get(&cache, &key(3)), Some(spell(3)));
+    }
+
+    #[test]
+    fn key_includes_vk_and_mock() {
+        let cache = MemorySpellCache::new(10);
+        SpellCache::put(&cache, key(1), spell(1));
+        assert_eq!(
+            SpellCache::get(&cache, &SpellCacheKey::new(B32([1; 32]), "0x01", false)),
+            None
+        );
+        assert_eq!(
+            SpellCache::get(&cache, &SpellCacheKey::new(B32([1; 32]), "0x00", true)),
+            None
+        );
+    }
+
+    #[test]
+    fn disk_store_roundtrip() {
+        let dir = temp_dir("spell-cache");
+        let cache = MemorySpellCache::new(1).with_authenticated_disk_store(&dir, [7; 32]);
+        SpellCache::put(&cache, key(1), spell(1));
+        SpellCache::put(&cache, key(2), spell(2));
+        assert_eq!(cache.len(), 1);
+        // evicted from memory, loaded from disk
+        assert_eq!(SpellCache::get(&cache, &key(1)), Some(spell(1)));
+
+        let other_cache = MemorySpellCache::new(1).with_authenticated_disk_store(&dir, [7; 32]);
+        assert_eq!(SpellCache::get(&other_cache, &key(2)), Some(spell(2)));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn disk_store_rejects_unauthenticated_entries() {
+        let dir = temp_dir("spell-cache-auth");
+        // written without (or with a different) secret: e.g. by someone else
+        let forged = MemorySpellCache::new(1).with_disk_store(DiskStore::new(&dir));
+        SpellCache::put(&forged, key(1), spell(1));
+        let forged = MemorySpellCache::new(1).with_authenticated_disk_store(&dir, [8; 32]);
+        SpellCache::put(&forged, key(2), spell(2));
+
+        let cache = MemorySpellCache::new(1).with_authenticated_disk_store(&dir, [7; 32]);
+        assert_eq!(SpellCache::get(&cache, &key(1)), None);
+        assert_eq!(SpellCache::get(&cache, &key(2)), None);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn disk_store_entries_are_checked() {
+        let dir = temp_dir("checked-cache");
+        let cache = LruCache::<B32, u64>::new(1).with_disk_store(DiskStore::new(&dir));
+        cache.put(B32([1; 32]), 1);
+        cache.put(B32([2; 32]), 2);
+
+        // evicted from memory: loaded from disk only if accepted
+        assert_eq!(cache.get(&B32([1; 32]), |&v| v == 2), None);
+        assert_eq!(cache.get(&B32([1; 32]), |&v| v == 1), Some(1));
+        // in memory: not re-checked
+        assert_eq!(cache.get(&B32([1; 32]), |_| false), Some(1));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,6 +1,5 @@
-use crate::{NormalizedSpell, Proof, tx, tx::EnchantedTx};
-use anyhow::{anyhow, ensure};
-use charms_data::{TxId, UtxoId, util};
+use crate::{Error, NormalizedSpell, Proof, tx::EnchantedTx};
+use charms_data::{TxId, UtxoId};
 use cml_chain::{
     Deserialize, Serialize, SetTransactionInput,
     crypto::TransactionHash,
@@ -20,27 +19,27 @@ impl PartialEq for CardanoTx {
 }
 
 impl CardanoTx {
-    pub fn from_hex(hex: &str) -> anyhow::Result<Self> {
+    pub fn from_hex(hex: &str) -> Result<Self, Error> {
+        let bytes = hex::decode(hex.as_bytes()).map_err(|e| Error::Decode(e.to_string()))?;
         Ok(Self(
-            Transaction::from_cbor_bytes(&hex::decode(hex.as_bytes())?)
-                .map_err(|e| anyhow!("{}", e))?,
+            Transaction::from_cbor_bytes(&bytes).map_err(|e| Error::Decode(e.to_string()))?,
         ))
     }
 }
 
 impl EnchantedTx for CardanoTx {
-    fn extract_and_verify_spell(
-        &self,
-        spell_vk: &str,
-        mock: bool,
-    ) -> anyhow::Result<NormalizedSpell> {
+    fn extract_spell(&self) -> Result<(NormalizedSpell, Proof), Error> {
         let tx = &self.0;
 
         let inputs = &tx.body.inputs;
-        ensure!(inputs.len() > 0, "Transaction has no inputs");
+        if inputs.len() == 0 {
+            return Err(Error::NoSpell("Transaction has no inputs".into()));
+        }
 
         let outputs = &tx.body.outputs;
-        ensure!(outputs.len() > 0, "Transaction has no outputs");
+        if outputs.len() == 0 {
+            return Err(Error::NoSpell("Transaction has no outputs".into()));
+        }
 
         let spell_output = outputs.get(outputs.len() - 1);
 
@@ -56,39 +55,38 @@ impl EnchantedTx for CardanoTx {
             ..
         })) = spell_output
         else {
-            return Err(anyhow::anyhow!("Transaction has no spell output"));
+            return Err(Error::NoSpell("Transaction has no spell output".into()));
         };
 
-        let (spell, proof): (NormalizedSpell, Proof) = util::read(spell_data.as_slice())
-            .map_err(|e| anyhow!("could not parse spell and proof: {}", e))?;
+        let (spell, proof) = crate::read_spell_data(spell_data)?;
 
-        if !mock {
-            ensure!(!spell.mock, "spell is a mock, but we are not in mock mode");
+        if spell.tx.ins.is_some() {
+            return Err(Error::InputOutputMismatch(
+                "spell must inherit inputs from the enchanted tx".into(),
+            ));
+        }
+        if spell.tx.outs.len() >= outputs.len() {
+            return Err(Error::InputOutputMismatch("spell tx outs mismatch".into()));
         }
-        ensure!(
-            &spell.tx.ins.is_none(),
-            "spell must inherit inputs from the enchanted tx"
-        );
-        ensure!(
-            spell.tx.outs.len() < outputs.len(),
-            "spell tx outs mismatch"
-        );
 
         let spell = spell_with_ins(spell, inputs);
 
-        let spell_vk = tx::spell_vk(spell.version, spell_vk, spell.mock)?;
-
-        let public_values = tx::to_serialized_pv(spell.version, &(spell_vk, &spell));
-
-        tx::verify_snark_proof(&proof, &public_values, spell_vk, spell.version, spell.mock)?;
-
-        Ok(spell)
+        Ok((spell, proof))
     }
 
     fn tx_outs_len(&self) -> usize {
         self.0.body.outputs.len()
     }
 
+    fn tx_ins(&self) -> Vec<UtxoId> {
+        self.0
+            .body
+            .inputs
+            .iter()
+            .map(|tx_in| UtxoId(tx_id(tx_in.transaction_id), tx_in.index as u32))
+            .collect()
+    }
+
     fn tx_id(&self) -> TxId {
         let transaction_hash = self.0.body.hash();
         tx_id(transaction_hash)
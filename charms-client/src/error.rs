@@ -0,0 +1,57 @@
+use std::fmt;
+
+/// Error returned by the charms-client public API.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The transaction does not carry a spell.
+    NoSpell(String),
+    /// The transaction carries a spell envelope, but it is malformed.
+    MalformedEnvelope(String),
+    /// The spell is a mock, but we are not in mock mode.
+    MockSpellInNonMockMode,
+    /// The spell has an unsupported protocol version.
+    UnsupportedVersion(u32),
+    /// The spell proof could not be verified.
+    ProofVerification(String),
+    /// The spell inputs or outputs don't match the transaction.
+    InputOutputMismatch(String),
+    /// Data (a transaction, spell data) could not be decoded.
+    Decode(String),
+}
+
+impl Error {
+    /// Name of the error variant: a stable identifier for FFI consumers.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::NoSpell(_) => "NoSpell",
+            Self::MalformedEnvelope(_) => "MalformedEnvelope",
+            Self::MockSpellInNonMockMode => "MockSpellInNonMockMode",
+            Self::UnsupportedVersion(_) => "UnsupportedVersion",
+            Self::ProofVerification(_) => "ProofVerification",
+            Self::InputOutputMismatch(_) => "InputOutputMismatch",
+            Self::Decode(_) => "Decode",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoSpell(reason) => write!(f, "no spell: {}", reason),
+            Self::MalformedEnvelope(reason) => write!(f, "malformed spell envelope: {}", reason),
+            Self::MockSpellInNonMockMode => {
+                write!(f, "spell is a mock, but we are not in mock mode")
+            }
+            Self::UnsupportedVersion(version) => {
+                write!(f, "unsupported spell version: {}", version)
+            }
+            Self::ProofVerification(reason) => {
+                write!(f, "could not verify spell proof: {}", reason)
+            }
+            Self::InputOutputMismatch(reason) => write!(f, "{}", reason),
+            Self::Decode(reason) => write!(f, "could not decode: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
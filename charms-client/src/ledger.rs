@@ -0,0 +1,547 @@
+use crate::{
+    NormalizedSpell, charms,
+    tx::{EnchantedTx, Tx, extract_and_verify_spell},
+    utxo_id_hash,
+};
+use anyhow::{bail, ensure};
+use charms_data::{App, B32, Charms, TxId, UtxoId};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+/// Default number of most recent blocks [`CharmsLedger`] keeps undo data for: deeper reorgs are
+/// not supported.
+pub const DEFAULT_MAX_REORG_DEPTH: u64 = 100;
+
+/// Charms held by an unspent UTXO.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    pub charms: Charms,
+    /// If the charms have been beamed out, the hash of the destination UTXO
+    /// ([`utxo_id_hash`]). Such entry is a placeholder: the charms are in transit to the
+    /// destination and stay in the ledger until claimed.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub beamed_to: Option<B32>,
+}
+
+/// A single change made to the ledger store.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LedgerChange {
+    /// An entry has been created.
+    Created(UtxoId),
+    /// An entry has been removed: spent, or (beamed-out placeholder) claimed.
+    Removed(UtxoId, LedgerEntry),
+}
+
+/// Undo data for a block: enough to disconnect the block from the ledger.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockUndo {
+    pub height: u64,
+    /// Changes made by the block, in the order they were made.
+    pub changes: Vec<LedgerChange>,
+}
+
+/// Transaction in a block, as seen by the ledger.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockTx {
+    pub tx_id: TxId,
+    /// All UTXOs spent by the transaction.
+    pub ins: Vec<UtxoId>,
+    /// Verified spell of the transaction, if any.
+    pub spell: Option<NormalizedSpell>,
+    /// Beamed-out UTXOs claimed by the transaction's inputs: input UTXO -> beam source UTXO
+    /// (`beamed_from` of the spell's inputs). Claims are not committed to on-chain: they are
+    /// supplied along with the transaction.
+    pub beamed_from: BTreeMap<UtxoId, UtxoId>,
+}
+
+/// Storage backend for [`CharmsLedger`].
+pub trait LedgerStore {
+    /// Get the entry for the UTXO.
+    fn get(&self, utxo_id: &UtxoId) -> anyhow::Result<Option<LedgerEntry>>;
+    /// Insert the entry for the UTXO.
+    fn insert(&mut self, utxo_id: UtxoId, entry: LedgerEntry) -> anyhow::Result<()>;
+    /// Remove the entry for the UTXO, returning it.
+    fn remove(&mut self, utxo_id: &UtxoId) -> anyhow::Result<Option<LedgerEntry>>;
+    /// Find the beamed-out placeholder UTXO with the destination UTXO hash.
+    fn beamed_source(&self, dest_utxo_hash: &B32) -> anyhow::Result<Option<UtxoId>>;
+    /// All UTXOs (excluding beamed-out placeholders) holding charms of the app.
+    fn utxos_with_app(&self, app: &App) -> anyhow::Result<Vec<UtxoId>>;
+    /// Store undo data of the newly connected block.
+    fn push_undo(&mut self, undo: BlockUndo) -> anyhow::Result<()>;
+    /// Remove and return undo data of the last connected block.
+    fn pop_undo(&mut self) -> anyhow::Result<Option<BlockUndo>>;
+    /// Remove undo data of blocks below `height`.
+    fn prune_undo(&mut self, height: u64) -> anyhow::Result<()>;
+    /// Height of the last connected block. Kept separately from undo data, which only covers the
+    /// most recent blocks.
+    fn tip(&self) -> anyhow::Result<Option<u64>>;
+    /// Set the height of the last connected block.
+    fn set_tip(&mut self, tip: Option<u64>) -> anyhow::Result<()>;
+}
+
+/// In-memory [`LedgerStore`].
+#[derive(Clone, Debug, Default)]
+pub struct MemoryLedgerStore {
+    utxos: BTreeMap<UtxoId, LedgerEntry>,
+    by_app: BTreeMap<App, BTreeSet<UtxoId>>,
+    beams: BTreeMap<B32, UtxoId>,
+    undo: VecDeque<BlockUndo>,
+    tip: Option<u64>,
+}
+
+impl MemoryLedgerStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl LedgerStore for MemoryLedgerStore {
+    fn get(&self, utxo_id: &UtxoId) -> anyhow::Result<Option<LedgerEntry>> {
+        Ok(self.utxos.get(utxo_id).cloned())
+    }
+
+    fn insert(&mut self, utxo_id: UtxoId, entry: LedgerEntry) -> anyhow::Result<()> {
+        self.remove(&utxo_id)?;
+        match &entry.beamed_to {
+            Some(dest_utxo_hash) => {
+                self.beams.insert(dest_utxo_hash.clone(), utxo_id.clone());
+            }
+            None => {
+                for app in entry.charms.keys() {
+                    self.by_app
+                        .entry(app.clone())
+                        .or_default()
+                        .insert(utxo_id.clone());
+                }
+            }
+        }
+        self.utxos.insert(utxo_id, entry);
+        Ok(())
+    }
+
+    fn remove(&mut self, utxo_id: &UtxoId) -> anyhow::Result<Option<LedgerEntry>> {
+        let Some(entry) = self.utxos.remove(utxo_id) else {
+            return Ok(None);
+        };
+        match &entry.beamed_to {
+            Some(dest_utxo_hash) => {
+                self.beams.remove(dest_utxo_hash);
+            }
+            None => {
+                for app in entry.charms.keys() {
+                    if let Some(utxo_ids) = self.by_app.get_mut(app) {
+                        utxo_ids.remove(utxo_id);
+                        if utxo_ids.is_empty() {
+                            self.by_app.remove(app);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(Some(entry))
+    }
+
+    fn beamed_source(&self, dest_utxo_hash: &B32) -> anyhow::Result<Option<UtxoId>> {
+        Ok(self.beams.get(dest_utxo_hash).cloned())
+    }
+
+    fn utxos_with_app(&self, app: &App) -> anyhow::Result<Vec<UtxoId>> {
+        Ok(self
+            .by_app
+            .get(app)
+            .map(|utxo_ids| utxo_ids.iter().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    fn push_undo(&mut self, undo: BlockUndo) -> anyhow::Result<()> {
+        self.undo.push_back(undo);
+        Ok(())
+    }
+
+    fn pop_undo(&mut self) -> anyhow::Result<Option<BlockUndo>> {
+        Ok(self.undo.pop_back())
+    }
+
+    fn prune_undo(&mut self, height: u64) -> anyhow::Result<()> {
+        while self.undo.front().is_some_and(|undo| undo.height < height) {
+            self.undo.pop_front();
+        }
+        Ok(())
+    }
+
+    fn tip(&self) -> anyhow::Result<Option<u64>> {
+        Ok(self.tip)
+    }
+
+    fn set_tip(&mut self, tip: Option<u64>) -> anyhow::Result<()> {
+        self.tip = tip;
+        Ok(())
+    }
+}
+
+/// Set of unspent charm-bearing UTXOs, maintained by applying verified spells block by block.
+///
+/// Any transaction spending a UTXO with charms removes it from the ledger (charms not carried
+/// over by a spell are burned). Spell transaction outputs with charms are added to the ledger.
+/// Beamed-out outputs are kept as placeholders (regardless of being spent on-chain) until a
+/// spell transaction spends the beam destination UTXO claiming them (see [`BlockTx::beamed_from`]).
+///
+/// Undo data is kept for the last [`DEFAULT_MAX_REORG_DEPTH`] blocks (see
+/// [`Self::with_max_reorg_depth`]).
+pub struct CharmsLedger<S: LedgerStore = MemoryLedgerStore> {
+    store: S,
+    max_reorg_depth: u64,
+}
+
+impl CharmsLedger<MemoryLedgerStore> {
+    /// New empty in-memory ledger.
+    pub fn new() -> Self {
+        Self::with_store(MemoryLedgerStore::new())
+    }
+}
+
+impl Default for CharmsLedger<MemoryLedgerStore> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: LedgerStore> CharmsLedger<S> {
+    pub fn with_store(store: S) -> Self {
+        Self {
+            store,
+            max_reorg_depth: DEFAULT_MAX_REORG_DEPTH,
+        }
+    }
+
+    /// Keep undo data for the last `max_reorg_depth` (at least 1) blocks only.
+    pub fn with_max_reorg_depth(self, max_reorg_depth: u64) -> Self {
+        assert!(max_reorg_depth > 0, "max_reorg_depth must be positive");
+        Self {
+            max_reorg_depth,
+            ..self
+        }
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Height of the last connected block.
+    pub fn tip(&self) -> anyhow::Result<Option<u64>> {
+        self.store.tip()
+    }
+
+    /// Extract and verify spells from the block's transactions, and apply the block to the ledger.
+    /// Transactions without a correct spell only spend UTXOs.
+    ///
+    /// `beamed_from` maps inputs of the block's transactions to the beamed-out UTXOs they claim
+    /// (see [`BlockTx::beamed_from`]).
+    pub fn connect_block(
+        &mut self,
+        height: u64,
+        txs: &[Tx],
+        beamed_from: &BTreeMap<UtxoId, UtxoId>,
+        spell_vk: &str,
+        mock: bool,
+    ) -> anyhow::Result<()> {
+        let block_txs = txs
+            .iter()
+            .map(|tx| {
+                let tx_id = tx.tx_id();
+                let spell = extract_and_verify_spell(spell_vk, tx, mock)
+                    .map_err(|e| {
+                        tracing::debug!("no correct spell in tx {}: {}", tx_id, e);
+                    })
+                    .ok();
+                let ins = tx.tx_ins();
+                let beamed_from = ins
+                    .iter()
+                    .filter_map(|utxo_id| {
+                        let source_utxo_id = beamed_from.get(utxo_id)?;
+                        Some((utxo_id.clone(), source_utxo_id.clone()))
+                    })
+                    .collect();
+                BlockTx {
+                    tx_id,
+                    ins,
+                    spell,
+                    beamed_from,
+                }
+            })
+            .collect();
+        self.apply_block(height, block_txs)
+    }
+
+    /// Apply the block's transactions (in block order) to the ledger. Spells are expected to be
+    /// already verified.
+    pub fn apply_block(&mut self, height: u64, txs: Vec<BlockTx>) -> anyhow::Result<()> {
+        if let Some(tip) = self.store.tip()? {
+            ensure!(
+                height == tip + 1,
+                "block height {} does not follow the tip {}",
+                height,
+                tip
+            );
+        }
+
+        let mut changes = vec![];
+        for tx in txs {
+            for utxo_id in &tx.ins {
+                // beamed-out charms belong to the beam destination
+                if let Some(entry) = self.store.get(utxo_id)?
+                    && entry.beamed_to.is_none()
+                {
+                    self.store.remove(utxo_id)?;
+                    changes.push(LedgerChange::Removed(utxo_id.clone(), entry));
+                }
+            }
+
+            let Some(spell) = tx.spell else {
+                continue;
+            };
+
+            for (utxo_id, claimed_source_utxo_id) in &tx.beamed_from {
+                if !tx.ins.contains(utxo_id) {
+                    tracing::warn!("tx {}: beam claim by non-input {}", tx.tx_id, utxo_id);
+                    continue;
+                }
+                let source_utxo_id = self.store.beamed_source(&utxo_id_hash(utxo_id))?;
+                if source_utxo_id.as_ref() != Some(claimed_source_utxo_id) {
+                    tracing::warn!(
+                        "tx {}: input {} claims {}, which is not beamed out to it",
+                        tx.tx_id,
+                        utxo_id,
+                        claimed_source_utxo_id
+                    );
+                    continue;
+                }
+                if let Some(entry) = self.store.remove(claimed_source_utxo_id)? {
+                    changes.push(LedgerChange::Removed(claimed_source_utxo_id.clone(), entry));
+                }
+            }
+
+            let beamed_outs = spell.tx.beamed_outs.clone().unwrap_or_default();
+            for (i, n_charms) in spell.tx.outs.iter().enumerate() {
+                let beamed_to = beamed_outs.get(&(i as u32)).cloned();
+                let out_charms = charms(&spell, n_charms);
+                if out_charms.is_empty() && beamed_to.is_none() {
+                    continue;
+                }
+                let utxo_id = UtxoId(tx.tx_id, i as u32);
+                if let Some(entry) = self.store.remove(&utxo_id)? {
+                    changes.push(LedgerChange::Removed(utxo_id.clone(), entry));
+                }
+                self.store.insert(
+                    utxo_id.clone(),
+                    LedgerEntry {
+                        charms: out_charms,
+                        beamed_to,
+                    },
+                )?;
+                changes.push(LedgerChange::Created(utxo_id));
+            }
+        }
+
+        self.store.push_undo(BlockUndo { height, changes })?;
+        self.store.set_tip(Some(height))?;
+        if let Some(min_height) = (height + 1).checked_sub(self.max_reorg_depth) {
+            self.store.prune_undo(min_height)?;
+        }
+        Ok(())
+    }
+
+    /// Disconnect the last connected block (e.g. on reorg), returning its height.
+    /// At most the last `max_reorg_depth` blocks can be disconnected.
+    pub fn disconnect_block(&mut self) -> anyhow::Result<u64> {
+        let Some(undo) = self.store.pop_undo()? else {
+            bail!(
+                "no blocks to disconnect (reorgs deeper than {} blocks are not supported)",
+                self.max_reorg_depth
+            )
+        };
+        for change in undo.changes.into_iter().rev() {
+            match change {
+                LedgerChange::Created(utxo_id) => {
+                    self.store.remove(&utxo_id)?;
+                }
+                LedgerChange::Removed(utxo_id, entry) => {
+                    self.store.insert(utxo_id, entry)?;
+                }
+            }
+        }
+        self.store.set_tip(undo.height.checked_sub(1))?;
+        Ok(undo.height)
+    }
+
+    /// Ledger entry for the UTXO, including beamed-out placeholders.
+    pub fn entry(&self, utxo_id: &UtxoId) -> anyhow::Result<Option<LedgerEntry>> {
+        self.store.get(utxo_id)
+    }
+
+    /// Charms held by the UTXO. Beamed-out charms are not held by their source UTXO.
+    pub fn charms_at(&self, utxo_id: &UtxoId) -> anyhow::Result<Charms> {
+        Ok(self
+            .store
+            .get(utxo_id)?
+            .filter(|entry| entry.beamed_to.is_none())
+            .map(|entry| entry.charms)
+            .unwrap_or_default())
+    }
+
+    /// All UTXOs holding charms of the app.
+    pub fn utxos_with_app(&self, app: &App) -> anyhow::Result<Vec<UtxoId>> {
+        self.store.utxos_with_app(app)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        NormalizedCharms,
+        test_utils::{app, norm_spell, utxo_id},
+    };
+    use charms_data::Data;
+
+    fn token(amount: u64) -> NormalizedCharms {
+        BTreeMap::from([(0, Data::from(&amount))])
+    }
+
+    fn spell_tx(tx: u8, ins: Vec<UtxoId>, outs: Vec<NormalizedCharms>) -> BlockTx {
+        BlockTx {
+            tx_id: TxId([tx; 32]),
+            ins: ins.clone(),
+            beamed_from: BTreeMap::new(),
+            spell: Some(NormalizedSpell {
+                app_public_inputs: BTreeMap::from([(app('t'), Data::empty())]),
+                ..norm_spell(ins, outs)
+            }),
+        }
+    }
+
+    fn plain_tx(tx: u8, ins: Vec<UtxoId>) -> BlockTx {
+        BlockTx {
+            tx_id: TxId([tx; 32]),
+            ins,
+            spell: None,
+            beamed_from: BTreeMap::new(),
+        }
+    }
+
+    fn amount_at(ledger: &CharmsLedger, utxo_id: &UtxoId) -> Option<u64> {
+        let charms = ledger.charms_at(utxo_id).unwrap();
+        charms.get(&app('t')).map(|data| data.value().unwrap())
+    }
+
+    #[test]
+    fn spend_and_reorg() {
+        let mut ledger = CharmsLedger::new();
+        ledger
+            .apply_block(1, vec![spell_tx(1, vec![utxo_id(0, 0)], vec![token(10)])])
+            .unwrap();
+        assert_eq!(amount_at(&ledger, &utxo_id(1, 0)), Some(10));
+
+        ledger
+            .apply_block(
+                2,
+                vec![
+                    spell_tx(2, vec![utxo_id(1, 0)], vec![token(4), token(6)]),
+                    // created and spent in the same block: charms are burned
+                    plain_tx(3, vec![utxo_id(2, 1)]),
+                ],
+            )
+            .unwrap();
+        assert_eq!(amount_at(&ledger, &utxo_id(1, 0)), None);
+        assert_eq!(amount_at(&ledger, &utxo_id(2, 0)), Some(4));
+        assert_eq!(amount_at(&ledger, &utxo_id(2, 1)), None);
+        assert_eq!(
+            ledger.utxos_with_app(&app('t')).unwrap(),
+            vec![utxo_id(2, 0)]
+        );
+
+        assert!(ledger.apply_block(4, vec![]).is_err());
+
+        assert_eq!(ledger.disconnect_block().unwrap(), 2);
+        assert_eq!(ledger.tip().unwrap(), Some(1));
+        assert_eq!(amount_at(&ledger, &utxo_id(1, 0)), Some(10));
+        assert_eq!(amount_at(&ledger, &utxo_id(2, 0)), None);
+        assert_eq!(
+            ledger.utxos_with_app(&app('t')).unwrap(),
+            vec![utxo_id(1, 0)]
+        );
+    }
+
+    #[test]
+    fn beam_out_and_claim() {
+        let mut ledger = CharmsLedger::new();
+        let dest = utxo_id(9, 0);
+
+        let mut beaming_tx = spell_tx(1, vec![utxo_id(0, 0)], vec![token(10)]);
+        if let Some(spell) = &mut beaming_tx.spell {
+            spell.tx.beamed_outs = Some(BTreeMap::from([(0, utxo_id_hash(&dest))]));
+        }
+        ledger
+            .apply_block(1, vec![beaming_tx, plain_tx(2, vec![utxo_id(1, 0)])])
+            .unwrap();
+        // the placeholder stays, even though spent on-chain
+        assert_eq!(amount_at(&ledger, &utxo_id(1, 0)), None);
+        assert_eq!(
+            ledger.entry(&utxo_id(1, 0)).unwrap().unwrap().beamed_to,
+            Some(utxo_id_hash(&dest))
+        );
+        assert!(ledger.utxos_with_app(&app('t')).unwrap().is_empty());
+
+        // spending the destination without claiming the beam
+        ledger
+            .apply_block(2, vec![spell_tx(4, vec![dest.clone()], vec![])])
+            .unwrap();
+        assert!(ledger.entry(&utxo_id(1, 0)).unwrap().is_some());
+        ledger.disconnect_block().unwrap();
+
+        // claiming another source
+        let mut claiming_tx = spell_tx(3, vec![dest.clone()], vec![token(10)]);
+        claiming_tx.beamed_from = BTreeMap::from([(dest.clone(), utxo_id(1, 1))]);
+        ledger.apply_block(2, vec![claiming_tx]).unwrap();
+        assert!(ledger.entry(&utxo_id(1, 0)).unwrap().is_some());
+        ledger.disconnect_block().unwrap();
+
+        let mut claiming_tx = spell_tx(3, vec![dest.clone()], vec![token(10)]);
+        claiming_tx.beamed_from = BTreeMap::from([(dest, utxo_id(1, 0))]);
+        ledger.apply_block(2, vec![claiming_tx]).unwrap();
+        assert_eq!(ledger.entry(&utxo_id(1, 0)).unwrap(), None);
+        assert_eq!(amount_at(&ledger, &utxo_id(3, 0)), Some(10));
+
+        ledger.disconnect_block().unwrap();
+        assert!(ledger.entry(&utxo_id(1, 0)).unwrap().is_some());
+        assert_eq!(amount_at(&ledger, &utxo_id(3, 0)), None);
+    }
+
+    #[test]
+    fn prunes_undo_data() {
+        let mut ledger = CharmsLedger::new().with_max_reorg_depth(2);
+        for height in 1..=4 {
+            ledger.apply_block(height, vec![]).unwrap();
+        }
+        assert_eq!(ledger.disconnect_block().unwrap(), 4);
+        assert_eq!(ledger.disconnect_block().unwrap(), 3);
+        assert!(ledger.disconnect_block().is_err());
+    }
+
+    #[test]
+    fn keeps_tip_without_undo_data() {
+        let mut ledger = CharmsLedger::new().with_max_reorg_depth(2);
+        for height in 1..=4 {
+            ledger.apply_block(height, vec![]).unwrap();
+        }
+        assert_eq!(ledger.disconnect_block().unwrap(), 4);
+        assert_eq!(ledger.tip().unwrap(), Some(3));
+        assert_eq!(ledger.disconnect_block().unwrap(), 3);
+        assert_eq!(ledger.tip().unwrap(), Some(2));
+
+        // the undo log is empty, but blocks must still follow the tip
+        assert!(ledger.apply_block(5, vec![]).is_err());
+        ledger.apply_block(3, vec![]).unwrap();
+        assert_eq!(ledger.tip().unwrap(), Some(3));
+    }
+}
@@ -1,14 +1,33 @@
-use crate::tx::{EnchantedTx, Tx, extract_and_verify_spell};
-use charms_data::{App, AppInput, B32, Charms, Data, Transaction, TxId, UtxoId, check};
+use crate::{
+    cache::{NoSpellCache, SpellCache, SpellCacheKey},
+    tx::{EnchantedTx, Tx, extract_and_verify_spell},
+};
+use charms_data::{
+    App, AppInput, B32, Charms, Data, Transaction, TxId, UtxoId, is_simple_transfer,
+    util::{self, CborLimits},
+};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::collections::{BTreeMap, BTreeSet};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
 
 pub mod ark;
+pub mod batch;
 pub mod bitcoin_tx;
+pub mod cache;
 pub mod cardano_tx;
+pub mod error;
+pub mod ledger;
+pub mod spell;
+/// Fixtures for tests of code working with spells (for other crates: with `test-utils` feature).
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
 pub mod tx;
 
+pub use error::Error;
+
 pub const MOCK_SPELL_VK: &str = "7c38e8639a2eac0074cee920982b92376513e8940f4a7ca6859f17a728af5b0e";
 
 /// Verification key for version `0` of the protocol implemented by `charms-spell-checker` binary.
@@ -25,6 +44,12 @@ pub const V4_SPELL_VK: &str = "0x00c707a155bf8dc18dc41db2994c214e93e906a3e97b458
 pub const V5_SPELL_VK: &str = "0x00e98665c417bd2e6e81c449af63b26ed5ad5c400ef55811b592450bf62c67cd";
 /// Verification key for version `6` of the protocol implemented by `charms-proof-wrapper` binary.
 pub const V6_SPELL_VK: &str = "0x005a1df17094445572e4dd474b3e5dd9093936cba62ca3a62bb2ce63d9db8cba";
+/// Verification key for version `7` of the protocol implemented by `charms-proof-wrapper` binary.
+pub const V7_SPELL_VK: &str = "0x0041d9843ec25ba04797a0ce29af364389f7eda9f7126ef39390c357432ad9aa";
+
+/// Verification key for the current `charms-spell-checker` binary
+/// (and the current protocol version).
+pub const SPELL_VK: &str = "0x0041d9843ec25ba04797a0ce29af364389f7eda9f7126ef39390c357432ad9aa";
 
 /// Version `0` of the protocol.
 pub const V0: u32 = 0;
@@ -42,9 +67,11 @@ pub const V5: u32 = 5;
 pub const V6: u32 = 6;
 /// Version `7` of the protocol.
 pub const V7: u32 = 7;
+/// Version `8` of the protocol.
+pub const V8: u32 = 8;
 
 /// Current version of the protocol.
-pub const CURRENT_VERSION: u32 = V7;
+pub const CURRENT_VERSION: u32 = V8;
 
 /// Maps the index of the charm's app (in [`NormalizedSpell`].`app_public_inputs`) to the charm's
 /// data.
@@ -102,37 +129,151 @@ pub struct NormalizedSpell {
     pub mock: bool,
 }
 
+/// Limits for decoding spell data (spell and proof) from transactions.
+pub const SPELL_DATA_LIMITS: CborLimits = CborLimits::DEFAULT;
+
+/// Decode (untrusted) spell data committed to in a transaction.
+pub fn read_spell_data(spell_data: &[u8]) -> Result<(NormalizedSpell, Proof), Error> {
+    util::read_bounded(spell_data, &SPELL_DATA_LIMITS)
+        .map_err(|e| Error::Decode(format!("spell and proof: {}", e)))
+}
+
 pub fn utxo_id_hash(utxo_id: &UtxoId) -> B32 {
     let hash = Sha256::digest(utxo_id.to_bytes());
     B32(hash.into())
 }
 
+/// Verification key of the app with the given Wasm binary.
+pub fn app_vk(binary: &[u8]) -> B32 {
+    let hash = Sha256::digest(binary);
+    B32(hash.into())
+}
+
 /// Extract spells from previous transactions.
 #[tracing::instrument(level = "debug", skip(prev_txs, spell_vk))]
 pub fn prev_spells(
     prev_txs: &Vec<Tx>,
     spell_vk: &str,
     mock: bool,
+) -> BTreeMap<TxId, (Option<NormalizedSpell>, usize)> {
+    prev_spells_cached(prev_txs, spell_vk, mock, &NoSpellCache)
+}
+
+/// Extract spells from previous transactions, reusing (and populating) `cache` with verified
+/// spells.
+#[tracing::instrument(level = "debug", skip(prev_txs, spell_vk, cache))]
+pub fn prev_spells_cached(
+    prev_txs: &[Tx],
+    spell_vk: &str,
+    mock: bool,
+    cache: &dyn SpellCache,
 ) -> BTreeMap<TxId, (Option<NormalizedSpell>, usize)> {
     prev_txs
         .iter()
         .map(|tx| {
             let tx_id = tx.tx_id();
-            (
-                tx_id,
-                (
-                    extract_and_verify_spell(spell_vk, tx, mock)
-                        .map_err(|e| {
-                            tracing::info!("no correct spell in tx {}: {}", tx_id, e);
-                        })
-                        .ok(),
-                    tx.tx_outs_len(),
-                ),
-            )
+            let key = SpellCacheKey::new(tx.wtx_id(), spell_vk, mock);
+            let n_spell_opt = cache.get(&key).or_else(|| {
+                let n_spell = extract_and_verify_spell(spell_vk, tx, mock)
+                    .map_err(|e| {
+                        tracing::info!("no correct spell in tx {}: {}", tx_id, e);
+                    })
+                    .ok()?;
+                cache.put(key, n_spell.clone());
+                Some(n_spell)
+            });
+            (tx_id, (n_spell_opt, tx.tx_outs_len()))
         })
         .collect()
 }
 
+/// Reason a spell is not well-formed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WellFormedError {
+    /// The spell version is not the current one.
+    VersionMismatch { expected: u32, actual: u32 },
+    /// A charm in output `output` refers to app index `app_index`, but the spell only has `apps`
+    /// apps.
+    OutputAppIndexOutOfRange {
+        output: usize,
+        app_index: u32,
+        apps: usize,
+    },
+    /// The spell has no `tx.ins`.
+    NoInputs,
+    /// A spent or referenced UTXO is not created by any of the supplied previous transactions.
+    InputNotCreatedByPrevTx(UtxoId),
+    /// A spent or referenced UTXO has been beamed out by its creating transaction.
+    SpendingBeamedOut(UtxoId),
+    /// The transaction creating a beam destination UTXO is not among the supplied previous
+    /// transactions.
+    BeamDestinationPrevTxMissing(UtxoId),
+    /// The transaction creating a beam destination (placeholder) UTXO carries a spell.
+    BeamDestinationHasSpell(UtxoId),
+    /// The beam source UTXO is not beamed out by a (supplied) previous transaction.
+    BeamSourceNotBeamedOut { destination: UtxoId, source: UtxoId },
+    /// The beam source UTXO is beamed to a different UTXO than the destination.
+    BeamHashMismatch { destination: UtxoId, source: UtxoId },
+}
+
+impl fmt::Display for WellFormedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::VersionMismatch { expected, actual } => {
+                write!(
+                    f,
+                    "spell version {} does not match expected version {}",
+                    actual, expected
+                )
+            }
+            Self::OutputAppIndexOutOfRange {
+                output,
+                app_index,
+                apps,
+            } => write!(
+                f,
+                "output {} has a charm of app index {}, but the spell has {} apps",
+                output, app_index, apps
+            ),
+            Self::NoInputs => write!(f, "spell has no tx.ins"),
+            Self::InputNotCreatedByPrevTx(utxo_id) => write!(
+                f,
+                "UTXO {} is not created by any of the supplied previous transactions",
+                utxo_id
+            ),
+            Self::SpendingBeamedOut(utxo_id) => write!(f, "UTXO {} is beamed out", utxo_id),
+            Self::BeamDestinationPrevTxMissing(utxo_id) => write!(
+                f,
+                "transaction creating beam destination UTXO {} is not supplied",
+                utxo_id
+            ),
+            Self::BeamDestinationHasSpell(utxo_id) => write!(
+                f,
+                "transaction creating beam destination UTXO {} carries a spell",
+                utxo_id
+            ),
+            Self::BeamSourceNotBeamedOut {
+                destination,
+                source,
+            } => write!(
+                f,
+                "beam source UTXO {} (for destination {}) is not beamed out",
+                source, destination
+            ),
+            Self::BeamHashMismatch {
+                destination,
+                source,
+            } => write!(
+                f,
+                "beam source UTXO {} is not beamed to destination UTXO {}",
+                source, destination
+            ),
+        }
+    }
+}
+
+impl std::error::Error for WellFormedError {}
+
 /// Check if the spell is well-formed.
 #[tracing::instrument(level = "debug", skip(spell, prev_spells))]
 pub fn well_formed(
@@ -140,75 +281,227 @@ pub fn well_formed(
     prev_spells: &BTreeMap<TxId, (Option<NormalizedSpell>, usize)>,
     tx_ins_beamed_source_utxos: &BTreeMap<UtxoId, UtxoId>,
 ) -> bool {
-    check!(spell.version == CURRENT_VERSION);
-    let directly_created_by_prev_txns = |utxo_id: &UtxoId| -> bool {
+    match well_formed_detailed(spell, prev_spells, tx_ins_beamed_source_utxos) {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("spell is not well-formed: {}", e);
+            false
+        }
+    }
+}
+
+/// Check if the spell is well-formed, returning the reason if it is not.
+#[tracing::instrument(level = "debug", skip(spell, prev_spells))]
+pub fn well_formed_detailed(
+    spell: &NormalizedSpell,
+    prev_spells: &BTreeMap<TxId, (Option<NormalizedSpell>, usize)>,
+    tx_ins_beamed_source_utxos: &BTreeMap<UtxoId, UtxoId>,
+) -> Result<(), WellFormedError> {
+    if spell.version != CURRENT_VERSION {
+        return Err(WellFormedError::VersionMismatch {
+            expected: CURRENT_VERSION,
+            actual: spell.version,
+        });
+    }
+    let directly_created_by_prev_txns = |utxo_id: &UtxoId| -> Result<(), WellFormedError> {
         let tx_id = utxo_id.0;
-        prev_spells
-            .get(&tx_id)
-            .is_some_and(|(n_spell_opt, num_tx_outs)| {
-                let utxo_index = utxo_id.1;
-
-                let is_beamed_out = n_spell_opt
-                    .as_ref()
-                    .and_then(|n_spell| n_spell.tx.beamed_outs.as_ref())
-                    .and_then(|beamed_outs| beamed_outs.get(&utxo_index))
-                    .is_some();
-
-                utxo_index <= *num_tx_outs as u32 && !is_beamed_out
-            })
+        let utxo_index = utxo_id.1;
+        let Some((n_spell_opt, num_tx_outs)) = prev_spells.get(&tx_id) else {
+            return Err(WellFormedError::InputNotCreatedByPrevTx(utxo_id.clone()));
+        };
+        if utxo_index > *num_tx_outs as u32 {
+            return Err(WellFormedError::InputNotCreatedByPrevTx(utxo_id.clone()));
+        }
+        let is_beamed_out = n_spell_opt
+            .as_ref()
+            .and_then(|n_spell| n_spell.tx.beamed_outs.as_ref())
+            .and_then(|beamed_outs| beamed_outs.get(&utxo_index))
+            .is_some();
+        if is_beamed_out {
+            return Err(WellFormedError::SpendingBeamedOut(utxo_id.clone()));
+        }
+        Ok(())
     };
-    check!({
-        spell.tx.outs.iter().all(|n_charm| {
-            n_charm
-                .keys()
-                .all(|&i| i < spell.app_public_inputs.len() as u32)
-        })
-    });
+    let apps = spell.app_public_inputs.len();
+    for (output, n_charms) in spell.tx.outs.iter().enumerate() {
+        if let Some(&app_index) = n_charms.keys().find(|&&i| i >= apps as u32) {
+            return Err(WellFormedError::OutputAppIndexOutOfRange {
+                output,
+                app_index,
+                apps,
+            });
+        }
+    }
     // check that UTXOs we're spending or referencing in this tx
     // are created by pre-req transactions
     let Some(tx_ins) = &spell.tx.ins else {
-        eprintln!("no tx.ins");
-        return false;
+        return Err(WellFormedError::NoInputs);
     };
-    check!(
-        tx_ins.iter().all(directly_created_by_prev_txns)
-            && spell
-                .tx
-                .refs
-                .iter()
-                .flatten()
-                .all(directly_created_by_prev_txns)
-    );
-    let beamed_source_utxos_point_to_placeholder_dest_utxos = tx_ins_beamed_source_utxos
+    tx_ins
         .iter()
-        .all(|(tx_in_utxo_id, beaming_source_utxo_id)| {
-            let prev_txid = tx_in_utxo_id.0;
-            let prev_tx = prev_spells.get(&prev_txid);
-            let Some((prev_spell_opt, _tx_outs)) = prev_tx else {
-                // prev_tx should be provided, so we know it doesn't carry a spell
-                return false;
-            };
-            // prev_tx must exist but not carry a spell
-            check!(prev_spell_opt.is_none());
-
-            let beaming_txid = beaming_source_utxo_id.0;
-            let beaming_utxo_index = beaming_source_utxo_id.1;
-
-            prev_spells
-                .get(&beaming_txid)
-                .and_then(|(n_spell_opt, _tx_outs)| {
-                    n_spell_opt.as_ref().and_then(|n_spell| {
-                        n_spell
-                            .tx
-                            .beamed_outs
-                            .as_ref()
-                            .and_then(|beamed_outs| beamed_outs.get(&beaming_utxo_index))
-                    })
-                })
-                .is_some_and(|dest_utxo_hash| dest_utxo_hash == &utxo_id_hash(tx_in_utxo_id))
+        .chain(spell.tx.refs.iter().flatten())
+        .try_for_each(directly_created_by_prev_txns)?;
+    // check that beamed source UTXOs point to placeholder destination UTXOs
+    for (tx_in_utxo_id, beaming_source_utxo_id) in tx_ins_beamed_source_utxos {
+        let prev_txid = tx_in_utxo_id.0;
+        let Some((prev_spell_opt, _tx_outs)) = prev_spells.get(&prev_txid) else {
+            // prev_tx should be provided, so we know it doesn't carry a spell
+            return Err(WellFormedError::BeamDestinationPrevTxMissing(
+                tx_in_utxo_id.clone(),
+            ));
+        };
+        // prev_tx must exist but not carry a spell
+        if prev_spell_opt.is_some() {
+            return Err(WellFormedError::BeamDestinationHasSpell(
+                tx_in_utxo_id.clone(),
+            ));
+        }
+
+        let beaming_txid = beaming_source_utxo_id.0;
+        let beaming_utxo_index = beaming_source_utxo_id.1;
+
+        let Some(dest_utxo_hash) = prev_spells
+            .get(&beaming_txid)
+            .and_then(|(n_spell_opt, _tx_outs)| n_spell_opt.as_ref())
+            .and_then(|n_spell| n_spell.tx.beamed_outs.as_ref())
+            .and_then(|beamed_outs| beamed_outs.get(&beaming_utxo_index))
+        else {
+            return Err(WellFormedError::BeamSourceNotBeamedOut {
+                destination: tx_in_utxo_id.clone(),
+                source: beaming_source_utxo_id.clone(),
+            });
+        };
+        if dest_utxo_hash != &utxo_id_hash(tx_in_utxo_id) {
+            return Err(WellFormedError::BeamHashMismatch {
+                destination: tx_in_utxo_id.clone(),
+                source: beaming_source_utxo_id.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Reason a spell is not correct (see [`is_correct_detailed`]).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SpellCheckError {
+    /// The spell is not well-formed.
+    NotWellFormed(WellFormedError),
+    /// The supplied previous transactions are not exactly those creating the spell's inputs,
+    /// references and beam sources.
+    PrevTxsMismatch {
+        missing: Vec<TxId>,
+        extra: Vec<TxId>,
+    },
+    /// The transaction is not a simple transfer of the app's charms, and its contract is neither
+    /// run nor proven.
+    AppContractNotChecked(App),
+    /// App contracts are run, but some apps are also claimed to be proven.
+    UnexpectedProvenApps,
+    /// A proven app is not among the spell's apps.
+    ProvenAppNotInSpell(App),
+    /// Some app contract is not satisfied by the transaction.
+    AppContractFailed(String),
+}
+
+impl fmt::Display for SpellCheckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotWellFormed(e) => write!(f, "spell is not well-formed: {}", e),
+            Self::PrevTxsMismatch { missing, extra } => write!(
+                f,
+                "previous transactions do not match the spell: missing {:?}, extra {:?}",
+                missing, extra
+            ),
+            Self::AppContractNotChecked(app) => write!(
+                f,
+                "not a simple transfer, and the contract is neither run nor proven for app: {}",
+                app
+            ),
+            Self::UnexpectedProvenApps => {
+                write!(f, "app contracts are run, but some apps are also proven")
+            }
+            Self::ProvenAppNotInSpell(app) => write!(f, "proven app is not in the spell: {}", app),
+            Self::AppContractFailed(e) => write!(f, "app contract not satisfied: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SpellCheckError {}
+
+impl From<WellFormedError> for SpellCheckError {
+    fn from(e: WellFormedError) -> Self {
+        Self::NotWellFormed(e)
+    }
+}
+
+/// Runs the contracts of all apps that the transaction is not a simple transfer of (see
+/// [`is_correct_detailed`]).
+pub type RunAppContracts<'a> = &'a mut dyn FnMut(&Transaction) -> anyhow::Result<()>;
+
+/// Check if the spell is correct, returning the reason if it is not: the checks performed by the
+/// spell checker, shared by the spell checker and the mock prover.
+///
+/// App contracts are checked either by `run_app_contracts` (running the contracts of all apps that
+/// the transaction is not a simple transfer of), or, if it is `None`, by
+/// `verify_app_contract_proof` for each of `proven_apps`.
+pub fn is_correct_detailed(
+    spell: &NormalizedSpell,
+    prev_spells: &BTreeMap<TxId, (Option<NormalizedSpell>, usize)>,
+    tx_ins_beamed_source_utxos: &BTreeMap<UtxoId, UtxoId>,
+    run_app_contracts: Option<RunAppContracts>,
+    proven_apps: &BTreeSet<App>,
+    verify_app_contract_proof: &dyn Fn(&App, &Transaction, &Data),
+) -> Result<(), SpellCheckError> {
+    well_formed_detailed(spell, prev_spells, tx_ins_beamed_source_utxos)?;
+
+    let Some(prev_txids) = spell.tx.prev_txids() else {
+        unreachable!("the spell is well formed: tx.ins MUST be Some");
+    };
+    let all_prev_txids: BTreeSet<_> = tx_ins_beamed_source_utxos
+        .values()
+        .map(|u| &u.0)
+        .chain(prev_txids)
+        .collect();
+    let supplied_prev_txids: BTreeSet<_> = prev_spells.keys().collect();
+    if all_prev_txids != supplied_prev_txids {
+        return Err(SpellCheckError::PrevTxsMismatch {
+            missing: all_prev_txids
+                .difference(&supplied_prev_txids)
+                .map(|&&tx_id| tx_id)
+                .collect(),
+            extra: supplied_prev_txids
+                .difference(&all_prev_txids)
+                .map(|&&tx_id| tx_id)
+                .collect(),
         });
-    check!(beamed_source_utxos_point_to_placeholder_dest_utxos);
-    true
+    }
+
+    let tx = to_tx(spell, prev_spells, tx_ins_beamed_source_utxos);
+    match run_app_contracts {
+        None => {
+            if let Some(app) = apps(spell)
+                .into_iter()
+                .find(|app| !is_simple_transfer(app, &tx) && !proven_apps.contains(app))
+            {
+                return Err(SpellCheckError::AppContractNotChecked(app));
+            }
+        }
+        Some(run_app_contracts) => {
+            if !proven_apps.is_empty() {
+                return Err(SpellCheckError::UnexpectedProvenApps);
+            }
+            run_app_contracts(&tx)
+                .map_err(|e| SpellCheckError::AppContractFailed(e.to_string()))?;
+        }
+    }
+    for app in proven_apps {
+        let Some(x) = spell.app_public_inputs.get(app) else {
+            return Err(SpellCheckError::ProvenAppNotInSpell(app.clone()));
+        };
+        verify_app_contract_proof(app, &tx, x);
+    }
+
+    Ok(())
 }
 
 /// Return the list of apps in the spell.
@@ -254,7 +547,7 @@ pub fn to_tx(
     }
 }
 
-fn charms_in_utxo(prev_spell: &NormalizedSpell, utxo_id: &UtxoId) -> Option<Charms> {
+pub(crate) fn charms_in_utxo(prev_spell: &NormalizedSpell, utxo_id: &UtxoId) -> Option<Charms> {
     prev_spell
         .tx
         .outs
@@ -279,10 +572,292 @@ pub struct SpellProverInput {
     pub tx_ins_beamed_source_utxos: BTreeMap<UtxoId, UtxoId>,
     /// indices of apps in the spell that have contract proofs
     pub app_input: Option<AppInput>,
+    /// VK of the spell checker program: spells of [`Self::recursive_prev_txids`] are verified
+    /// against it. Committed to in the public values (see [`split_spell_checker_pv`]).
+    pub spell_checker_vk: [u32; 8],
+    /// Previous transactions whose spells are verified recursively (from their compressed spell
+    /// checker proofs, supplied to the zkVM in the same order) instead of from their Groth16
+    /// proofs.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub recursive_prev_txids: BTreeSet<TxId>,
+    /// Apps whose contracts are proven by separate `charms-app-checker` runs (instead of being run
+    /// by the spell checker). Their proofs are supplied to the zkVM in this order, after those of
+    /// [`Self::recursive_prev_txids`].
+    ///
+    /// Since [`V8`]: spell checkers of earlier versions always run app contracts themselves.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub proven_apps: BTreeSet<App>,
+}
+
+/// Verification key of the `charms-app-checker` program: proves app contracts are satisfied.
+///
+/// Placeholder: the `charms-app-checker` binary hasn't been built yet. Until it is (and this VK is
+/// set to match it), the prover runs app contracts in the spell checker and never sends
+/// [`SpellProverInput::proven_apps`].
+pub const APP_CHECKER_VK: [u32; 8] = [0; 8];
+
+/// Input of the `charms-app-checker` program, which runs a single app contract.
+///
+/// The app checker commits to `(app, tx, x)`: see [`app_checker_pv`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AppProverInput {
+    pub app_binary: Vec<u8>,
+    pub app: App,
+    pub tx: Transaction,
+    /// Public input of the app.
+    pub x: Data,
+    /// Private input of the app.
+    pub w: Data,
+}
+
+/// Public values of the app checker proof that `app` contract is satisfied by `tx` with public
+/// input `x`.
+pub fn app_checker_pv(app: &App, tx: &Transaction, x: &Data) -> Vec<u8> {
+    util::write(&(app, tx, x)).expect("serialization is expected to succeed")
+}
+
+/// Length of the spell checker VK appended to the spell checker public values.
+pub const SPELL_CHECKER_VK_LEN: usize = 32;
+
+/// Spell checker VK as appended to the spell checker public values.
+pub fn spell_checker_vk_bytes(vk: &[u32; 8]) -> [u8; SPELL_CHECKER_VK_LEN] {
+    let mut bytes = [0u8; SPELL_CHECKER_VK_LEN];
+    for (chunk, word) in bytes.chunks_exact_mut(4).zip(vk) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    bytes
+}
+
+/// Split spell checker public values into the committed data `(spell_vk, spell)` (as committed by
+/// the proof wrapper) and the spell checker VK the recursively verified parent spells were
+/// verified against.
+pub fn split_spell_checker_pv(public_values: &[u8]) -> Option<(&[u8], &[u8])> {
+    let len = public_values.len().checked_sub(SPELL_CHECKER_VK_LEN)?;
+    Some(public_values.split_at(len))
 }
 
 #[cfg(test)]
 mod test {
+    use super::*;
+    use crate::test_utils::{norm_spell, prev_spells, utxo_id};
+
     #[test]
     fn dummy() {}
+
+    #[test]
+    fn spell_checker_pv_roundtrip() {
+        let vk = [1, 2, 3, 4, 5, 6, 7, u32::MAX];
+        let committed_data = util::write(&("vk", norm_spell(vec![], vec![]))).unwrap();
+        let mut public_values = committed_data.clone();
+        public_values.extend(spell_checker_vk_bytes(&vk));
+
+        let (data, vk_bytes) = split_spell_checker_pv(&public_values).unwrap();
+        assert_eq!(data, committed_data.as_slice());
+        assert_eq!(vk_bytes, spell_checker_vk_bytes(&vk));
+        assert_eq!(&vk_bytes[..4], &[1, 0, 0, 0]);
+
+        assert!(split_spell_checker_pv(&[0; SPELL_CHECKER_VK_LEN - 1]).is_none());
+    }
+
+    #[test]
+    fn well_formed_ok() {
+        let n_spell = norm_spell(vec![utxo_id(1, 0)], vec![]);
+        let prev_spells = prev_spells(vec![(1, None, 2)]);
+        assert_eq!(
+            well_formed_detailed(&n_spell, &prev_spells, &BTreeMap::new()),
+            Ok(())
+        );
+        assert!(well_formed(&n_spell, &prev_spells, &BTreeMap::new()));
+    }
+
+    #[test]
+    fn well_formed_version_mismatch() {
+        let mut n_spell = norm_spell(vec![utxo_id(1, 0)], vec![]);
+        n_spell.version = CURRENT_VERSION - 1;
+        assert_eq!(
+            well_formed_detailed(&n_spell, &prev_spells(vec![(1, None, 2)]), &BTreeMap::new()),
+            Err(WellFormedError::VersionMismatch {
+                expected: CURRENT_VERSION,
+                actual: CURRENT_VERSION - 1,
+            })
+        );
+    }
+
+    #[test]
+    fn well_formed_app_index_out_of_range() {
+        let n_spell = norm_spell(
+            vec![utxo_id(1, 0)],
+            vec![BTreeMap::new(), BTreeMap::from([(0, Data::empty())])],
+        );
+        assert_eq!(
+            well_formed_detailed(&n_spell, &prev_spells(vec![(1, None, 2)]), &BTreeMap::new()),
+            Err(WellFormedError::OutputAppIndexOutOfRange {
+                output: 1,
+                app_index: 0,
+                apps: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn well_formed_input_not_created_by_prev_tx() {
+        let mut n_spell = norm_spell(vec![utxo_id(1, 0)], vec![]);
+        n_spell.tx.refs = Some(vec![utxo_id(2, 0)]);
+        assert_eq!(
+            well_formed_detailed(&n_spell, &prev_spells(vec![(1, None, 2)]), &BTreeMap::new()),
+            Err(WellFormedError::InputNotCreatedByPrevTx(utxo_id(2, 0)))
+        );
+    }
+
+    #[test]
+    fn well_formed_spending_beamed_out() {
+        let mut prev_spell = norm_spell(vec![utxo_id(0, 0)], vec![]);
+        prev_spell.tx.beamed_outs = Some(BTreeMap::from([(1, B32([0; 32]))]));
+        let n_spell = norm_spell(vec![utxo_id(1, 1)], vec![]);
+        assert_eq!(
+            well_formed_detailed(
+                &n_spell,
+                &prev_spells(vec![(1, Some(prev_spell), 2)]),
+                &BTreeMap::new()
+            ),
+            Err(WellFormedError::SpendingBeamedOut(utxo_id(1, 1)))
+        );
+    }
+
+    #[test]
+    fn well_formed_beam_hash_mismatch() {
+        let mut source_spell = norm_spell(vec![utxo_id(0, 0)], vec![]);
+        source_spell.tx.beamed_outs = Some(BTreeMap::from([(0, utxo_id_hash(&utxo_id(2, 1)))]));
+        let prev_spells = prev_spells(vec![(1, Some(source_spell), 2), (2, None, 2)]);
+
+        let n_spell = norm_spell(vec![utxo_id(2, 1)], vec![]);
+        let beamed_source_utxos = BTreeMap::from([(utxo_id(2, 1), utxo_id(1, 0))]);
+        assert_eq!(
+            well_formed_detailed(&n_spell, &prev_spells, &beamed_source_utxos),
+            Ok(())
+        );
+
+        let n_spell = norm_spell(vec![utxo_id(2, 0)], vec![]);
+        let beamed_source_utxos = BTreeMap::from([(utxo_id(2, 0), utxo_id(1, 0))]);
+        assert_eq!(
+            well_formed_detailed(&n_spell, &prev_spells, &beamed_source_utxos),
+            Err(WellFormedError::BeamHashMismatch {
+                destination: utxo_id(2, 0),
+                source: utxo_id(1, 0),
+            })
+        );
+    }
+
+    fn minting_spell() -> (NormalizedSpell, App) {
+        let app = App {
+            tag: charms_data::NFT,
+            identity: B32([1; 32]),
+            vk: B32([2; 32]),
+        };
+        let mut n_spell = norm_spell(
+            vec![utxo_id(1, 0)],
+            vec![BTreeMap::from([(0, Data::from(&"nft"))])],
+        );
+        n_spell.app_public_inputs = BTreeMap::from([(app.clone(), Data::empty())]);
+        (n_spell, app)
+    }
+
+    #[test]
+    fn is_correct_prev_txs_mismatch() {
+        let n_spell = norm_spell(vec![utxo_id(1, 0)], vec![]);
+        assert_eq!(
+            is_correct_detailed(
+                &n_spell,
+                &prev_spells(vec![(1, None, 2), (2, None, 2)]),
+                &BTreeMap::new(),
+                None,
+                &BTreeSet::new(),
+                &|_, _, _| unreachable!(),
+            ),
+            Err(SpellCheckError::PrevTxsMismatch {
+                missing: vec![],
+                extra: vec![TxId([2; 32])],
+            })
+        );
+    }
+
+    #[test]
+    fn is_correct_app_contracts() {
+        let (n_spell, app) = minting_spell();
+        let prev_spells = prev_spells(vec![(1, None, 2)]);
+        let no_proof = |_: &App, _: &Transaction, _: &Data| unreachable!();
+
+        assert_eq!(
+            is_correct_detailed(
+                &n_spell,
+                &prev_spells,
+                &BTreeMap::new(),
+                None,
+                &BTreeSet::new(),
+                &no_proof,
+            ),
+            Err(SpellCheckError::AppContractNotChecked(app.clone()))
+        );
+
+        let runs = std::cell::Cell::new(0);
+        let mut run = |_: &Transaction| -> anyhow::Result<()> {
+            runs.set(runs.get() + 1);
+            Ok(())
+        };
+        assert_eq!(
+            is_correct_detailed(
+                &n_spell,
+                &prev_spells,
+                &BTreeMap::new(),
+                Some(&mut run),
+                &BTreeSet::new(),
+                &no_proof,
+            ),
+            Ok(())
+        );
+        assert_eq!(runs.get(), 1);
+
+        let mut fail = |_: &Transaction| -> anyhow::Result<()> { anyhow::bail!("nope") };
+        assert_eq!(
+            is_correct_detailed(
+                &n_spell,
+                &prev_spells,
+                &BTreeMap::new(),
+                Some(&mut fail),
+                &BTreeSet::new(),
+                &no_proof,
+            ),
+            Err(SpellCheckError::AppContractFailed("nope".to_string()))
+        );
+
+        let proven_apps = BTreeSet::from([app.clone()]);
+        let verified = std::cell::Cell::new(0);
+        assert_eq!(
+            is_correct_detailed(
+                &n_spell,
+                &prev_spells,
+                &BTreeMap::new(),
+                None,
+                &proven_apps,
+                &|proven_app, _, _| {
+                    assert_eq!(proven_app, &app);
+                    verified.set(verified.get() + 1);
+                },
+            ),
+            Ok(())
+        );
+        assert_eq!(verified.get(), 1);
+
+        assert_eq!(
+            is_correct_detailed(
+                &n_spell,
+                &prev_spells,
+                &BTreeMap::new(),
+                Some(&mut run),
+                &proven_apps,
+                &no_proof,
+            ),
+            Err(SpellCheckError::UnexpectedProvenApps)
+        );
+    }
 }
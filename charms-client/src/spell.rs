@@ -0,0 +1,604 @@
+use crate::{
+    CURRENT_VERSION, NormalizedCharms, NormalizedSpell, NormalizedTransaction, charms_in_utxo,
+};
+use anyhow::{anyhow, bail, ensure};
+use charms_data::{App, B32, Charms, Data, Transaction, TxId, UtxoId};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Charm as represented in a spell.
+/// Map of `$KEY: data`.
+pub type KeyedCharms = BTreeMap<String, Data>;
+
+/// UTXO as represented in a spell.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Input {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub utxo_id: Option<UtxoId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub charms: Option<KeyedCharms>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub beamed_from: Option<UtxoId>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Output {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    #[serde(alias = "sats", skip_serializing_if = "Option::is_none")]
+    pub amount: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub charms: Option<KeyedCharms>,
+    #[serde(alias = "beamed_to", skip_serializing_if = "Option::is_none")]
+    pub beam_to: Option<B32>,
+}
+
+/// Defines how spells are represented in their source form and in CLI outputs,
+/// in both human-friendly (JSON/YAML) and machine-friendly (CBOR) formats.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Spell {
+    /// Version of the protocol.
+    pub version: u32,
+
+    /// Apps used in the spell. Map of `$KEY: App`.
+    /// Keys are arbitrary strings. They just need to be unique (inside the spell).
+    pub apps: BTreeMap<String, App>,
+
+    /// Public inputs to the apps for this spell. Map of `$KEY: Data`.
+    #[serde(alias = "public_inputs", skip_serializing_if = "Option::is_none")]
+    pub public_args: Option<BTreeMap<String, Data>>,
+
+    /// Private inputs to the apps for this spell. Map of `$KEY: Data`.
+    #[serde(alias = "private_inputs", skip_serializing_if = "Option::is_none")]
+    pub private_args: Option<BTreeMap<String, Data>>,
+
+    /// Transaction inputs.
+    pub ins: Vec<Input>,
+    /// Reference inputs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refs: Option<Vec<Input>>,
+    /// Transaction outputs.
+    pub outs: Vec<Output>,
+}
+
+impl Default for Spell {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Spell {
+    /// New empty spell.
+    pub fn new() -> Self {
+        Self {
+            version: CURRENT_VERSION,
+            apps: BTreeMap::new(),
+            public_args: None,
+            private_args: None,
+            ins: vec![],
+            refs: None,
+            outs: vec![],
+        }
+    }
+
+    /// Get a [`Transaction`] for the spell.
+    pub fn to_tx(&self) -> anyhow::Result<Transaction> {
+        let ins = self.strings_of_charms(&self.ins)?;
+        let empty_vec = vec![];
+        let refs = self.strings_of_charms(self.refs.as_ref().unwrap_or(&empty_vec))?;
+        let outs = self
+            .outs
+            .iter()
+            .map(|output| self.charms(&output.charms))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Transaction { ins, refs, outs })
+    }
+
+    fn strings_of_charms(&self, inputs: &[Input]) -> anyhow::Result<Vec<(UtxoId, Charms)>> {
+        inputs
+            .iter()
+            .map(|input| {
+                let utxo_id = input
+                    .utxo_id
+                    .as_ref()
+                    .ok_or(anyhow!("missing input utxo_id"))?;
+                let charms = self.charms(&input.charms)?;
+                Ok((utxo_id.clone(), charms))
+            })
+            .collect::<Result<_, _>>()
+    }
+
+    fn charms(&self, charms_opt: &Option<KeyedCharms>) -> anyhow::Result<Charms> {
+        charms_opt
+            .as_ref()
+            .ok_or(anyhow!("missing charms field"))?
+            .iter()
+            .map(|(k, v)| {
+                let app = self.apps.get(k).ok_or(anyhow!("missing app {}", k))?;
+                Ok((app.clone(), Data::from(v)))
+            })
+            .collect::<Result<Charms, _>>()
+    }
+
+    /// Fill in the charms of inputs and references from the spells of the previous transactions
+    /// creating them: `prev_spells` (see [`crate::prev_spells`]). Charms already specified in the
+    /// spell must match those.
+    ///
+    /// Apps of the charms are resolved to their keys in [`Self::apps`]: all apps with charms in
+    /// the inputs and references must be listed there.
+    pub fn enrich_from_prev_txs(
+        &mut self,
+        prev_spells: &BTreeMap<TxId, (Option<NormalizedSpell>, usize)>,
+    ) -> anyhow::Result<()> {
+        let app_keys: BTreeMap<&App, &String> =
+            self.apps.iter().map(|(key, app)| (app, key)).collect();
+
+        for input in self.ins.iter_mut().chain(self.refs.iter_mut().flatten()) {
+            let utxo_id = input
+                .utxo_id
+                .as_ref()
+                .ok_or(anyhow!("missing input utxo_id"))?;
+            let utxo_charms = |utxo_id: &UtxoId| -> anyhow::Result<Option<Charms>> {
+                let (prev_spell_opt, _) = prev_spells.get(&utxo_id.0).ok_or(anyhow!(
+                    "transaction {} creating UTXO {} is not supplied",
+                    utxo_id.0,
+                    utxo_id
+                ))?;
+                Ok(prev_spell_opt
+                    .as_ref()
+                    .and_then(|prev_spell| charms_in_utxo(prev_spell, utxo_id)))
+            };
+            let charms = match (utxo_charms(utxo_id)?, &input.beamed_from) {
+                (Some(charms), _) => charms,
+                (None, Some(beam_source_utxo_id)) => {
+                    utxo_charms(beam_source_utxo_id)?.unwrap_or_default()
+                }
+                (None, None) => Charms::new(),
+            };
+
+            let keyed_charms = charms
+                .into_iter()
+                .map(|(app, data)| {
+                    let key = app_keys.get(&app).ok_or(anyhow!(
+                        "UTXO {} has charms of app {}, which is not in the spell's apps",
+                        utxo_id,
+                        app
+                    ))?;
+                    Ok(((*key).clone(), data))
+                })
+                .collect::<anyhow::Result<KeyedCharms>>()?;
+
+            match &input.charms {
+                None => input.charms = Some(keyed_charms),
+                Some(charms) => ensure!(
+                    charms == &keyed_charms,
+                    "charms of UTXO {} in the spell do not match its previous transaction: \
+                    expected {:?}, got {:?}",
+                    utxo_id,
+                    keyed_charms,
+                    charms
+                ),
+            }
+        }
+        Ok(())
+    }
+
+    /// Get a [`NormalizedSpell`] and apps' private inputs for the spell.
+    pub fn normalized(
+        &self,
+    ) -> anyhow::Result<(
+        NormalizedSpell,
+        BTreeMap<App, Data>,
+        BTreeMap<UtxoId, UtxoId>,
+    )> {
+        ensure!(
+            self.version == CURRENT_VERSION,
+            "spell version {} is not the current protocol version {}: \
+            run `charms spell upgrade` to upgrade the spell",
+            self.version,
+            CURRENT_VERSION
+        );
+
+        let empty_map = BTreeMap::new();
+        let keyed_public_inputs = self.public_args.as_ref().unwrap_or(&empty_map);
+
+        let keyed_apps = &self.apps;
+        let apps: BTreeSet<App> = keyed_apps.values().cloned().collect();
+        let app_to_index: BTreeMap<App, u32> = apps.iter().cloned().zip(0..).collect();
+        ensure!(apps.len() == keyed_apps.len(), "duplicate apps");
+
+        let app_public_inputs: BTreeMap<App, Data> = app_inputs(keyed_apps, keyed_public_inputs);
+
+        let ins: Vec<UtxoId> = self
+            .ins
+            .iter()
+            .map(|utxo| utxo.utxo_id.clone().ok_or(anyhow!("missing input utxo_id")))
+            .collect::<Result<_, _>>()?;
+        ensure!(
+            ins.iter().collect::<BTreeSet<_>>().len() == ins.len(),
+            "duplicate inputs"
+        );
+        let ins = Some(ins);
+
+        let refs = self
+            .refs
+            .as_ref()
+            .map(|refs| {
+                refs.iter()
+                    .map(|utxo| utxo.utxo_id.clone().ok_or(anyhow!("missing input utxo_id")))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()?;
+
+        let empty_charm = KeyedCharms::new();
+
+        let outs: Vec<NormalizedCharms> = self
+            .outs
+            .iter()
+            .map(|utxo| {
+                let n_charms = utxo
+                    .charms
+                    .as_ref()
+                    .unwrap_or(&empty_charm)
+                    .iter()
+                    .map(|(k, v)| {
+                        let app = keyed_apps.get(k).ok_or(anyhow!("missing app key"))?;
+                        let i = *app_to_index
+                            .get(app)
+                            .ok_or(anyhow!("app is expected to be in app_to_index"))?;
+                        Ok((i, v.clone()))
+                    })
+                    .collect::<anyhow::Result<NormalizedCharms>>()?;
+                Ok(n_charms)
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        let beamed_outs: BTreeMap<_, _> = self
+            .outs
+            .iter()
+            .zip(0u32..)
+            .filter_map(|(o, i)| o.beam_to.as_ref().map(|b32| (i, b32.clone())))
+            .collect();
+        let beamed_outs = Some(beamed_outs).filter(|m| !m.is_empty());
+
+        let norm_spell = NormalizedSpell {
+            version: self.version,
+            tx: NormalizedTransaction {
+                ins,
+                refs,
+                outs,
+                beamed_outs,
+            },
+            app_public_inputs,
+            mock: false,
+        };
+
+        let keyed_private_inputs = self.private_args.as_ref().unwrap_or(&empty_map);
+        let app_private_inputs = app_inputs(keyed_apps, keyed_private_inputs);
+
+        let tx_ins_beamed_source_utxos = self
+            .ins
+            .iter()
+            .filter_map(|input| {
+                let tx_in = input
+                    .utxo_id
+                    .as_ref()
+                    .expect("inputs are expected to have utxo_id set")
+                    .clone();
+                input
+                    .beamed_from
+                    .as_ref()
+                    .map(|beam_source_utxo_id| (tx_in, beam_source_utxo_id.clone()))
+            })
+            .collect();
+
+        Ok((norm_spell, app_private_inputs, tx_ins_beamed_source_utxos))
+    }
+
+    /// De-normalize a normalized spell.
+    pub fn denormalized(norm_spell: &NormalizedSpell) -> anyhow::Result<Self> {
+        Self::denormalized_with_keys(norm_spell, &AppKeys::new())
+    }
+
+    /// De-normalize a normalized spell, using `app_keys` as the keys of the apps that have them
+    /// (see [`keys_of_apps`]).
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn denormalized_with_keys(
+        norm_spell: &NormalizedSpell,
+        app_keys: &AppKeys,
+    ) -> anyhow::Result<Self> {
+        let keys = keys_of_apps(norm_spell.app_public_inputs.keys(), app_keys);
+        let key = |i: &u32| -> String {
+            keys.get(*i as usize)
+                .cloned()
+                .unwrap_or_else(|| str_index(i))
+        };
+
+        let apps = (0..)
+            .zip(norm_spell.app_public_inputs.keys())
+            .map(|(i, app)| (key(&i), app.clone()))
+            .collect();
+
+        let public_inputs = match norm_spell
+            .app_public_inputs
+            .values()
+            .enumerate()
+            .filter_map(|(i, data)| match data {
+                data if data.is_empty() => None,
+                data => Some((key(&(i as u32)), data.clone())),
+            })
+            .collect::<BTreeMap<_, _>>()
+        {
+            map if map.is_empty() => None,
+            map => Some(map),
+        };
+
+        let Some(norm_spell_ins) = &norm_spell.tx.ins else {
+            bail!("spell must have inputs");
+        };
+        let ins = norm_spell_ins
+            .iter()
+            .map(|utxo_id| Input {
+                utxo_id: Some(utxo_id.clone()),
+                charms: None,
+                beamed_from: None,
+            })
+            .collect();
+
+        let refs = norm_spell.tx.refs.as_ref().map(|refs| {
+            refs.iter()
+                .map(|utxo_id| Input {
+                    utxo_id: Some(utxo_id.clone()),
+                    charms: None,
+                    beamed_from: None,
+                })
+                .collect::<Vec<_>>()
+        });
+
+        let outs = norm_spell
+            .tx
+            .outs
+            .iter()
+            .zip(0u32..)
+            .map(|(n_charms, i)| Output {
+                address: None,
+                amount: None,
+                charms: match n_charms
+                    .iter()
+                    .map(|(i, data)| (key(i), data.clone()))
+                    .collect::<KeyedCharms>()
+                {
+                    charms if charms.is_empty() => None,
+                    charms => Some(charms),
+                },
+                beam_to: norm_spell
+                    .tx
+                    .beamed_outs
+                    .as_ref()
+                    .and_then(|beamed_to| beamed_to.get(&i).cloned()),
+            })
+            .collect();
+
+        Ok(Self {
+            version: norm_spell.version,
+            apps,
+            public_args: public_inputs,
+            private_args: None,
+            ins,
+            refs,
+            outs,
+        })
+    }
+}
+
+fn app_inputs(
+    keyed_apps: &BTreeMap<String, App>,
+    keyed_inputs: &BTreeMap<String, Data>,
+) -> BTreeMap<App, Data> {
+    keyed_apps
+        .iter()
+        .map(|(k, app)| {
+            (
+                app.clone(),
+                keyed_inputs.get(k).cloned().unwrap_or_default(),
+            )
+        })
+        .collect()
+}
+
+/// Keys of apps by app, e.g. from a registry of named apps: `$usdc` for a USDC token app.
+pub type AppKeys = BTreeMap<App, String>;
+
+/// Keys of `apps` (in the same order): from `app_keys` if an app has a key there that no other app
+/// in `apps` has, its index (in the format `$xxxx`) otherwise.
+pub fn keys_of_apps<'a>(
+    apps: impl IntoIterator<Item = &'a App>,
+    app_keys: &AppKeys,
+) -> Vec<String> {
+    let apps: Vec<&App> = apps.into_iter().collect();
+    let index_keys: BTreeSet<String> = (0..apps.len() as u32).map(|i| str_index(&i)).collect();
+    let mut key_counts: BTreeMap<&String, usize> = BTreeMap::new();
+    for key in apps.iter().filter_map(|app| app_keys.get(*app)) {
+        *key_counts.entry(key).or_default() += 1;
+    }
+    (0..)
+        .zip(apps)
+        .map(|(i, app)| match app_keys.get(app) {
+            Some(key) if key_counts[key] == 1 && !index_keys.contains(key) => key.clone(),
+            _ => str_index(&i),
+        })
+        .collect()
+}
+
+/// Create a string representation of the index `i` in the format `$xxxx`.
+pub fn str_index(i: &u32) -> String {
+    format!("${:04}", i)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn normalized_denormalized_round_trip() {
+        let app = App {
+            tag: 't',
+            identity: B32([1; 32]),
+            vk: B32([2; 32]),
+        };
+        let spell = Spell {
+            apps: BTreeMap::from([(str_index(&0), app.clone())]),
+            ins: vec![Input {
+                utxo_id: Some(UtxoId(TxId([3; 32]), 0)),
+                charms: None,
+                beamed_from: None,
+            }],
+            outs: vec![
+                Output {
+                    address: None,
+                    amount: None,
+                    charms: Some(KeyedCharms::from([(str_index(&0), Data::from(&42))])),
+                    beam_to: None,
+                },
+                Output {
+                    address: None,
+                    amount: None,
+                    charms: None,
+                    beam_to: None,
+                },
+            ],
+            ..Spell::new()
+        };
+
+        let (norm_spell, _, _) = spell.normalized().unwrap();
+        let denormalized = Spell::denormalized(&norm_spell).unwrap();
+        assert_eq!(denormalized.apps, spell.apps);
+        assert_eq!(denormalized.ins, spell.ins);
+        assert_eq!(denormalized.outs, spell.outs);
+    }
+
+    #[test]
+    fn denormalized_with_keys() {
+        let app = |byte: u8| App {
+            tag: 't',
+            identity: B32([byte; 32]),
+            vk: B32([2; 32]),
+        };
+        let spell = Spell {
+            apps: BTreeMap::from([
+                ("$a".to_string(), app(1)),
+                ("$b".to_string(), app(2)),
+                ("$c".to_string(), app(3)),
+                ("$d".to_string(), app(4)),
+            ]),
+            ins: vec![Input {
+                utxo_id: Some(UtxoId(TxId([3; 32]), 0)),
+                charms: None,
+                beamed_from: None,
+            }],
+            outs: vec![Output {
+                address: None,
+                amount: None,
+                charms: Some(KeyedCharms::from([
+                    ("$a".to_string(), Data::from(&1)),
+                    ("$d".to_string(), Data::from(&4)),
+                ])),
+                beam_to: None,
+            }],
+            ..Spell::new()
+        };
+        let app_keys = AppKeys::from([
+            (app(1), "$usd".to_string()),
+            (app(2), "$dup".to_string()),
+            (app(3), "$dup".to_string()),
+        ]);
+
+        let (norm_spell, _, _) = spell.normalized().unwrap();
+        let denormalized = Spell::denormalized_with_keys(&norm_spell, &app_keys).unwrap();
+        assert_eq!(
+            denormalized.apps,
+            BTreeMap::from([
+                ("$usd".to_string(), app(1)),
+                (str_index(&1), app(2)),
+                (str_index(&2), app(3)),
+                (str_index(&3), app(4)),
+            ])
+        );
+        assert_eq!(
+            denormalized.outs[0].charms,
+            Some(KeyedCharms::from([
+                ("$usd".to_string(), Data::from(&1)),
+                (str_index(&3), Data::from(&4)),
+            ]))
+        );
+    }
+
+    #[test]
+    fn enrich_from_prev_txs() {
+        let token = App {
+            tag: 't',
+            identity: B32([1; 32]),
+            vk: B32([2; 32]),
+        };
+        let prev_spell = NormalizedSpell {
+            version: CURRENT_VERSION,
+            tx: NormalizedTransaction {
+                ins: None,
+                refs: None,
+                outs: vec![BTreeMap::new(), BTreeMap::from([(0, Data::from(&42))])],
+                beamed_outs: None,
+            },
+            app_public_inputs: BTreeMap::from([(token.clone(), Data::empty())]),
+            mock: false,
+        };
+        let prev_spells = BTreeMap::from([
+            (TxId([3; 32]), (Some(prev_spell), 2)),
+            (TxId([4; 32]), (None, 1)),
+        ]);
+        let input = |utxo_id: UtxoId, charms: Option<KeyedCharms>| Input {
+            utxo_id: Some(utxo_id),
+            charms,
+            beamed_from: None,
+        };
+        let spell = Spell {
+            apps: BTreeMap::from([("$TOKEN".to_string(), token.clone())]),
+            ins: vec![
+                input(UtxoId(TxId([3; 32]), 1), None),
+                input(UtxoId(TxId([3; 32]), 0), None),
+                Input {
+                    beamed_from: Some(UtxoId(TxId([3; 32]), 1)),
+                    ..input(UtxoId(TxId([4; 32]), 0), None)
+                },
+            ],
+            ..Spell::new()
+        };
+
+        let mut enriched = spell.clone();
+        enriched.enrich_from_prev_txs(&prev_spells).unwrap();
+        let token_charms = KeyedCharms::from([("$TOKEN".to_string(), Data::from(&42))]);
+        assert_eq!(enriched.ins[0].charms, Some(token_charms.clone()));
+        assert_eq!(enriched.ins[1].charms, Some(KeyedCharms::new()));
+        assert_eq!(enriched.ins[2].charms, Some(token_charms.clone()));
+        assert_eq!(
+            enriched.to_tx().unwrap().ins[0].1,
+            Charms::from([(token.clone(), Data::from(&42))])
+        );
+
+        // user-supplied charms are checked
+        enriched.enrich_from_prev_txs(&prev_spells).unwrap();
+        let mut wrong = spell.clone();
+        wrong.ins[0].charms = Some(KeyedCharms::from([("$TOKEN".to_string(), Data::from(&43))]));
+        assert!(wrong.enrich_from_prev_txs(&prev_spells).is_err());
+
+        // apps of input charms must be in the spell
+        let mut unlisted = spell.clone();
+        unlisted.apps = BTreeMap::new();
+        assert!(unlisted.enrich_from_prev_txs(&prev_spells).is_err());
+
+        // previous transactions must be supplied
+        let mut missing = spell;
+        missing.ins.push(input(UtxoId(TxId([5; 32]), 0), None));
+        assert!(missing.enrich_from_prev_txs(&prev_spells).is_err());
+    }
+}
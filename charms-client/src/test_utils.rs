@@ -0,0 +1,98 @@
+use crate::{
+    CURRENT_VERSION, NormalizedCharms, NormalizedSpell, NormalizedTransaction,
+    spell::{Input, KeyedCharms, Output, Spell},
+};
+use charms_data::{App, B32, Data, TxId, UtxoId};
+use std::collections::BTreeMap;
+
+/// App with identity `[1; 32]` and VK `[2; 32]`.
+pub fn app(tag: char) -> App {
+    app_n(tag, 1)
+}
+
+/// App with identity `[n; 32]` and VK `[n + 1; 32]`: different `n` give different apps.
+pub fn app_n(tag: char, n: u8) -> App {
+    App {
+        tag,
+        identity: B32([n; 32]),
+        vk: B32([n + 1; 32]),
+    }
+}
+
+/// Output `index` of transaction `[tx; 32]`.
+pub fn utxo_id(tx: u8, index: u32) -> UtxoId {
+    UtxoId(TxId([tx; 32]), index)
+}
+
+/// Charms keyed by app keys (as in source spells).
+pub fn charms(entries: &[(&str, Data)]) -> Option<KeyedCharms> {
+    Some(
+        entries
+            .iter()
+            .map(|(key, data)| (key.to_string(), data.clone()))
+            .collect(),
+    )
+}
+
+/// Spell input spending [`utxo_id`]`(tx, index)`, with charms taken from the previous spell.
+pub fn input(tx: u8, index: u32) -> Input {
+    Input {
+        utxo_id: Some(utxo_id(tx, index)),
+        charms: None,
+        beamed_from: None,
+    }
+}
+
+pub fn output(address: &str, amount: u64, charms: Option<KeyedCharms>) -> Output {
+    Output {
+        address: Some(address.to_string()),
+        amount: Some(amount),
+        charms,
+        beam_to: None,
+    }
+}
+
+/// Source spell at [`CURRENT_VERSION`] with apps keyed as in `apps`.
+pub fn spell(apps: &[(&str, App)], ins: Vec<Input>, outs: Vec<Output>) -> Spell {
+    Spell {
+        apps: apps
+            .iter()
+            .map(|(key, app)| (key.to_string(), app.clone()))
+            .collect(),
+        ins,
+        outs,
+        ..Spell::new()
+    }
+}
+
+/// Normalized spell at [`CURRENT_VERSION`] without apps.
+pub fn norm_spell(ins: Vec<UtxoId>, outs: Vec<NormalizedCharms>) -> NormalizedSpell {
+    NormalizedSpell {
+        version: CURRENT_VERSION,
+        tx: NormalizedTransaction {
+            ins: Some(ins),
+            refs: None,
+            outs,
+            beamed_outs: None,
+        },
+        app_public_inputs: BTreeMap::new(),
+        mock: false,
+    }
+}
+
+/// Previous spells (as from [`crate::prev_spells`]): `(tx, spell, number of tx outputs)` for
+/// transactions `[tx; 32]`.
+pub fn prev_spells(
+    entries: Vec<(u8, Option<NormalizedSpell>, usize)>,
+) -> BTreeMap<TxId, (Option<NormalizedSpell>, usize)> {
+    entries
+        .into_iter()
+        .map(|(tx, n_spell, n_outs)| (TxId([tx; 32]), (n_spell, n_outs)))
+        .collect()
+}
+
+/// Normalize the source spell, as it would be recorded on-chain.
+pub fn normalized(spell: &Spell) -> NormalizedSpell {
+    let (norm_spell, _, _) = spell.normalized().expect("spell should normalize");
+    norm_spell
+}
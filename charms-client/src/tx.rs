@@ -1,24 +1,42 @@
 use crate::{
-    CURRENT_VERSION, MOCK_SPELL_VK, NormalizedSpell, V0, V0_SPELL_VK, V1, V1_SPELL_VK, V2,
-    V2_SPELL_VK, V3, V3_SPELL_VK, V4, V4_SPELL_VK, V5, V5_SPELL_VK, V6, V6_SPELL_VK, ark,
-    bitcoin_tx::BitcoinTx, cardano_tx::CardanoTx,
+    CURRENT_VERSION, Error, MOCK_SPELL_VK, NormalizedSpell, Proof, V0, V0_SPELL_VK, V1,
+    V1_SPELL_VK, V2, V2_SPELL_VK, V3, V3_SPELL_VK, V4, V4_SPELL_VK, V5, V5_SPELL_VK, V6,
+    V6_SPELL_VK, V7, V7_SPELL_VK, V8, ark,
+    batch::{BatchProof, spell_hash},
+    bitcoin_tx::BitcoinTx,
+    cardano_tx::CardanoTx,
 };
-use anyhow::{anyhow, bail};
-use charms_data::{TxId, util};
+use bitcoin::hashes::Hash;
+use charms_data::{B32, TxId, UtxoId, util};
 use enum_dispatch::enum_dispatch;
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
+use sha2::{Digest, Sha256};
 use sp1_primitives::io::SP1PublicValues;
-use sp1_verifier::Groth16Verifier;
+use sp1_verifier::{Groth16Verifier, PlonkVerifier};
+use std::str::FromStr;
 
 #[enum_dispatch]
 pub trait EnchantedTx {
+    /// Extract the spell (with inputs inherited from the transaction) and its proof.
+    /// The proof is **not** verified.
+    fn extract_spell(&self) -> Result<(NormalizedSpell, Proof), Error>;
+    /// Extract the spell and verify its proof.
     fn extract_and_verify_spell(
         &self,
         spell_vk: &str,
         mock: bool,
-    ) -> anyhow::Result<NormalizedSpell>;
+    ) -> Result<NormalizedSpell, Error> {
+        let (spell, proof) = self.extract_spell()?;
+        if !mock && spell.mock {
+            return Err(Error::MockSpellInNonMockMode);
+        }
+        verify_spell_proof(&spell, &proof, spell_vk)?;
+        Ok(spell)
+    }
     fn tx_outs_len(&self) -> usize;
+    /// All UTXOs spent by the transaction (including the spell commitment input, if any).
+    fn tx_ins(&self) -> Vec<UtxoId>;
     fn tx_id(&self) -> TxId;
     fn hex(&self) -> String;
 }
@@ -52,13 +70,13 @@ impl Tx {
         tx.into()
     }
 
-    pub fn from_hex(hex: &str) -> anyhow::Result<Self> {
+    pub fn from_hex(hex: &str) -> Result<Self, Error> {
         if let Ok(b_tx @ BitcoinTx(_)) = BitcoinTx::from_hex(hex) {
             Ok(Self::Bitcoin(b_tx))
         } else if let Ok(c_tx @ CardanoTx(_)) = CardanoTx::from_hex(hex) {
             Ok(Self::Cardano(c_tx))
         } else {
-            bail!("invalid hex")
+            Err(Error::Decode("invalid transaction hex".to_string()))
         }
     }
 
@@ -68,6 +86,18 @@ impl Tx {
             Tx::Cardano(tx) => tx.hex(),
         }
     }
+
+    /// Hash of the whole transaction, including witness data (the wtxid for Bitcoin). Unlike the
+    /// transaction ID, it commits to the spell and its proof.
+    pub fn wtx_id(&self) -> B32 {
+        match self {
+            Tx::Bitcoin(BitcoinTx(tx)) => B32(tx.compute_wtxid().to_byte_array()),
+            Tx::Cardano(tx) => {
+                let bytes = hex::decode(tx.hex()).expect("tx hex is expected to be valid");
+                B32(Sha256::digest(bytes).into())
+            }
+        }
+    }
 }
 
 /// Extract a [`NormalizedSpell`] from a transaction and verify it.
@@ -77,42 +107,236 @@ pub fn extract_and_verify_spell(
     spell_vk: &str,
     tx: &Tx,
     mock: bool,
-) -> anyhow::Result<NormalizedSpell> {
+) -> Result<NormalizedSpell, Error> {
     tx.extract_and_verify_spell(spell_vk, mock)
 }
 
-pub fn spell_vk(spell_version: u32, spell_vk: &str, mock: bool) -> anyhow::Result<&str> {
+/// Spell verification result.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Verification {
+    /// The spell proof is valid.
+    Verified,
+    /// The spell proof could not be verified.
+    Failed(Error),
+}
+
+/// Spell decoded from a transaction of any protocol version, together with its verification
+/// status.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistoricalSpell {
+    /// Protocol version of the spell.
+    pub version: u32,
+    /// The spell, with inputs inherited from the transaction.
+    pub spell: NormalizedSpell,
+    /// Verification status of the spell proof.
+    pub verification: Verification,
+}
+
+impl HistoricalSpell {
+    pub fn is_verified(&self) -> bool {
+        self.verification == Verification::Verified
+    }
+}
+
+/// Decode the spell from a transaction of any protocol version (from [`V0`] to
+/// [`CURRENT_VERSION`]) and verify its proof.
+///
+/// Returns an error only if the transaction does not carry a well-formed spell of a supported
+/// version. Spells that fail verification are returned with [`Verification::Failed`].
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn decode_historical_spell(
+    tx: &Tx,
+    spell_vk: &str,
+    mock: bool,
+) -> Result<HistoricalSpell, Error> {
+    let (spell, proof) = tx.extract_spell()?;
+    version_params(spell.version)?;
+
+    let result = match !mock && spell.mock {
+        true => Err(Error::MockSpellInNonMockMode),
+        false => verify_spell_proof(&spell, &proof, spell_vk),
+    };
+    let verification = match result {
+        Ok(()) => Verification::Verified,
+        Err(e) => Verification::Failed(e),
+    };
+
+    Ok(HistoricalSpell {
+        version: spell.version,
+        spell,
+        verification,
+    })
+}
+
+/// Verify the proof of an extracted spell, according to the spell's protocol version.
+///
+/// The proof is either a proof of this single spell or a [`BatchProof`] covering it.
+pub fn verify_spell_proof(
+    spell: &NormalizedSpell,
+    proof: &[u8],
+    spell_vk: &str,
+) -> Result<(), Error> {
+    let spell_vk = self::spell_vk(spell.version, spell_vk, spell.mock)?;
+    let params = version_params(spell.version)?;
+    if !BatchProof::is_batch(proof) {
+        let public_values = params.public_values.serialize(&(spell_vk, spell));
+        return verify_snark_proof(proof, &public_values, spell_vk, spell.version, spell.mock);
+    }
+
+    if !params.batch_proofs {
+        return Err(Error::ProofVerification(format!(
+            "batch proofs are not supported in version {}",
+            spell.version
+        )));
+    }
+    let batch_proof = BatchProof::from_proof(proof)?;
+    if !batch_proof
+        .spell_hashes
+        .contains(&spell_hash(spell_vk, spell))
+    {
+        return Err(Error::ProofVerification(
+            "spell is not in the batch".to_string(),
+        ));
+    }
+    verify_snark_proof(
+        &batch_proof.proof,
+        &batch_proof.public_values(),
+        spell_vk,
+        spell.version,
+        spell.mock,
+    )
+}
+
+/// How the spell checker commits to its public values `(spell_vk, spell)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PublicValuesEncoding {
+    /// Serialized internally by SP1 (version `0`).
+    Sp1,
+    /// CBOR-encoded (versions `1` and later).
+    Cbor,
+}
+
+impl PublicValuesEncoding {
+    pub fn serialize<T: Serialize>(&self, t: &T) -> Vec<u8> {
+        match self {
+            Self::Sp1 => {
+                let mut pv = SP1PublicValues::new();
+                pv.write(t);
+                pv.to_vec()
+            }
+            Self::Cbor => util::write(t).unwrap(),
+        }
+    }
+}
+
+/// Parameters for verifying spells of a protocol version.
+#[derive(Clone, Copy, Debug)]
+pub struct VersionParams {
+    pub version: u32,
+    /// Spell VK. `None` for the current version: it is supplied by the caller.
+    pub spell_vk: Option<&'static str>,
+    pub groth16_vk: &'static [u8],
+    pub public_values: PublicValuesEncoding,
+    /// Are [`BatchProof`]s (one proof covering several spells) accepted?
+    pub batch_proofs: bool,
+    /// Are [`ProofSystem::Plonk`] proofs accepted?
+    pub plonk: bool,
+}
+
+/// Parameters of all supported protocol versions, indexed by version.
+pub const VERSION_PARAMS: [VersionParams; CURRENT_VERSION as usize + 1] = [
+    VersionParams {
+        version: V0,
+        spell_vk: Some(V0_SPELL_VK),
+        groth16_vk: V0_GROTH16_VK_BYTES,
+        public_values: PublicValuesEncoding::Sp1,
+        batch_proofs: false,
+        plonk: false,
+    },
+    VersionParams {
+        version: V1,
+        spell_vk: Some(V1_SPELL_VK),
+        groth16_vk: V1_GROTH16_VK_BYTES,
+        public_values: PublicValuesEncoding::Cbor,
+        batch_proofs: false,
+        plonk: false,
+    },
+    VersionParams {
+        version: V2,
+        spell_vk: Some(V2_SPELL_VK),
+        groth16_vk: V2_GROTH16_VK_BYTES,
+        public_values: PublicValuesEncoding::Cbor,
+        batch_proofs: false,
+        plonk: false,
+    },
+    VersionParams {
+        version: V3,
+        spell_vk: Some(V3_SPELL_VK),
+        groth16_vk: V3_GROTH16_VK_BYTES,
+        public_values: PublicValuesEncoding::Cbor,
+        batch_proofs: false,
+        plonk: false,
+    },
+    VersionParams {
+        version: V4,
+        spell_vk: Some(V4_SPELL_VK),
+        groth16_vk: V4_GROTH16_VK_BYTES,
+        public_values: PublicValuesEncoding::Cbor,
+        batch_proofs: false,
+        plonk: false,
+    },
+    VersionParams {
+        version: V5,
+        spell_vk: Some(V5_SPELL_VK),
+        groth16_vk: V5_GROTH16_VK_BYTES,
+        public_values: PublicValuesEncoding::Cbor,
+        batch_proofs: false,
+        plonk: false,
+    },
+    VersionParams {
+        version: V6,
+        spell_vk: Some(V6_SPELL_VK),
+        groth16_vk: V6_GROTH16_VK_BYTES,
+        public_values: PublicValuesEncoding::Cbor,
+        batch_proofs: false,
+        plonk: false,
+    },
+    VersionParams {
+        version: V7,
+        spell_vk: Some(V7_SPELL_VK),
+        groth16_vk: V7_GROTH16_VK_BYTES,
+        public_values: PublicValuesEncoding::Cbor,
+        batch_proofs: false,
+        plonk: false,
+    },
+    VersionParams {
+        version: V8,
+        spell_vk: None,
+        groth16_vk: V8_GROTH16_VK_BYTES,
+        public_values: PublicValuesEncoding::Cbor,
+        batch_proofs: true,
+        plonk: true,
+    },
+];
+
+pub fn version_params(spell_version: u32) -> Result<&'static VersionParams, Error> {
+    VERSION_PARAMS
+        .get(spell_version as usize)
+        .ok_or(Error::UnsupportedVersion(spell_version))
+}
+
+pub fn spell_vk(spell_version: u32, spell_vk: &str, mock: bool) -> Result<&str, Error> {
     if mock {
         return Ok(MOCK_SPELL_VK);
     }
-    match spell_version {
-        CURRENT_VERSION => Ok(spell_vk),
-        V6 => Ok(V6_SPELL_VK),
-        V5 => Ok(V5_SPELL_VK),
-        V4 => Ok(V4_SPELL_VK),
-        V3 => Ok(V3_SPELL_VK),
-        V2 => Ok(V2_SPELL_VK),
-        V1 => Ok(V1_SPELL_VK),
-        V0 => Ok(V0_SPELL_VK),
-        _ => bail!("unsupported spell version: {}", spell_version),
-    }
+    Ok(version_params(spell_version)?.spell_vk.unwrap_or(spell_vk))
 }
 
-pub fn groth16_vk(spell_version: u32, mock: bool) -> anyhow::Result<&'static [u8]> {
+pub fn groth16_vk(spell_version: u32, mock: bool) -> Result<&'static [u8], Error> {
     if mock {
         return Ok(MOCK_GROTH16_VK_BYTES);
     }
-    match spell_version {
-        CURRENT_VERSION => Ok(CURRENT_GROTH16_VK_BYTES),
-        V6 => Ok(V6_GROTH16_VK_BYTES),
-        V5 => Ok(V5_GROTH16_VK_BYTES),
-        V4 => Ok(V4_GROTH16_VK_BYTES),
-        V3 => Ok(V3_GROTH16_VK_BYTES),
-        V2 => Ok(V2_GROTH16_VK_BYTES),
-        V1 => Ok(V1_GROTH16_VK_BYTES),
-        V0 => Ok(V0_GROTH16_VK_BYTES),
-        _ => bail!("unsupported spell version: {}", spell_version),
-    }
+    Ok(version_params(spell_version)?.groth16_vk)
 }
 
 pub const MOCK_GROTH16_VK_BYTES: &'static [u8] = include_bytes!("../vk/mock/mock-groth16-vk.bin");
@@ -125,37 +349,88 @@ pub const V4_GROTH16_VK_BYTES: &'static [u8] = include_bytes!("../vk/v4/groth16_
 pub const V5_GROTH16_VK_BYTES: &'static [u8] = V4_GROTH16_VK_BYTES;
 pub const V6_GROTH16_VK_BYTES: &'static [u8] = V5_GROTH16_VK_BYTES;
 pub const V7_GROTH16_VK_BYTES: &'static [u8] = V6_GROTH16_VK_BYTES;
-pub const CURRENT_GROTH16_VK_BYTES: &'static [u8] = V7_GROTH16_VK_BYTES;
+pub const V8_GROTH16_VK_BYTES: &'static [u8] = V7_GROTH16_VK_BYTES;
+pub const CURRENT_GROTH16_VK_BYTES: &'static [u8] = V8_GROTH16_VK_BYTES;
 
 pub fn to_serialized_pv<T: Serialize>(spell_version: u32, t: &T) -> Vec<u8> {
-    match spell_version {
-        CURRENT_VERSION | V6 | V5 | V4 | V3 | V2 | V1 => {
-            // we commit to CBOR-encoded tuple `(spell_vk, n_spell)`
-            util::write(t).unwrap()
+    version_params(spell_version)
+        .expect("supported spell version")
+        .public_values
+        .serialize(t)
+}
+
+/// SNARK system of the proof wrapper proof.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProofSystem {
+    #[default]
+    Groth16,
+    /// Universal setup: no circuit-specific trusted setup ceremony.
+    Plonk,
+}
+
+/// Prefix of PLONK proofs in spell data. Groth16 proofs are not prefixed.
+pub const PLONK_PROOF_PREFIX: &[u8] = b"charms-plonk";
+
+impl ProofSystem {
+    /// Tag the SNARK proof with its proof system, for committing in spell data.
+    pub fn tag(self, proof: Proof) -> Proof {
+        match self {
+            Self::Groth16 => proof,
+            Self::Plonk => [PLONK_PROOF_PREFIX, &proof].concat(),
         }
-        V0 => {
-            // we used to commit to the tuple `(spell_vk, n_spell)`, which was serialized internally
-            // by SP1
-            let mut pv = SP1PublicValues::new();
-            pv.write(t);
-            pv.to_vec()
+    }
+
+    /// Split a proof from spell data into its proof system and the SNARK proof.
+    pub fn untag(proof: &[u8]) -> (Self, &[u8]) {
+        match proof.strip_prefix(PLONK_PROOF_PREFIX) {
+            Some(proof) => (Self::Plonk, proof),
+            None => (Self::Groth16, proof),
+        }
+    }
+}
+
+impl FromStr for ProofSystem {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "groth16" => Ok(Self::Groth16),
+            "plonk" => Ok(Self::Plonk),
+            _ => Err(Error::Decode(format!("unknown proof system: {}", s))),
         }
-        _ => unreachable!(),
     }
 }
 
+/// Verify the (tagged, see [`ProofSystem::tag`]) proof wrapper proof.
+///
+/// Mock proofs of both proof systems are verified by the mock (Groth16) verifier.
 pub fn verify_snark_proof(
     proof: &[u8],
     public_inputs: &[u8],
     vk_hash: &str,
     spell_version: u32,
     mock: bool,
-) -> anyhow::Result<()> {
+) -> Result<(), Error> {
+    let (proof_system, proof) = ProofSystem::untag(proof);
+    if proof_system == ProofSystem::Plonk && !version_params(spell_version)?.plonk {
+        return Err(Error::ProofVerification(format!(
+            "PLONK proofs are not supported in version {}",
+            spell_version
+        )));
+    }
     let groth16_vk = groth16_vk(spell_version, mock)?;
-    match mock {
-        false => Groth16Verifier::verify(proof, public_inputs, vk_hash, groth16_vk)
-            .map_err(|e| anyhow!("could not verify spell proof: {}", e)),
-        true => ark::verify_groth16_proof(proof, public_inputs, groth16_vk),
+    match (proof_system, mock) {
+        (ProofSystem::Groth16, false) => {
+            Groth16Verifier::verify(proof, public_inputs, vk_hash, groth16_vk)
+                .map_err(|e| Error::ProofVerification(e.to_string()))
+        }
+        (ProofSystem::Plonk, false) => {
+            PlonkVerifier::verify(proof, public_inputs, vk_hash, sp1_verifier::PLONK_VK_BYTES)
+                .map_err(|e| Error::ProofVerification(e.to_string()))
+        }
+        (_, true) => ark::verify_groth16_proof(proof, public_inputs, groth16_vk)
+            .map_err(|e| Error::ProofVerification(e.to_string())),
     }
 }
 
@@ -163,6 +438,191 @@ pub fn verify_snark_proof(
 mod tests {
     use super::*;
 
+    /// Mock spell transaction (protocol version 6).
+    const MOCK_SPELL_TX_HEX: &str = "020000000001027467e6e2686e61a50a71c95f616305e605e397b33af8492e4bf25ea1e43723730100000000ffffffff7b33d1ac103acde00a47e60428475a5255a726c8fd21ac62623ce7a6765b19600000000000ffffffff02e803000000000000160014243c6e1617b5f3e43b0f295c9104089f462e1875f3b20f0000000000160014242c4baed605d0e147fd0f73b3d3bff61421a626000341ab8ba921df634d7527ffefbb4e6df80ca7a2f41980b494da107ff9f8470881785d701f20a364f6563dcf3507a95bcf616df8bdcc56c05693c7ae644d23ae13dc81fd5e020063057370656c6c4d080282a46776657273696f6e06627478a1646f75747381a1001b0000000867ba4900716170705f7075626c69635f696e70757473a1836174982018c6189418ff181d188e1866187b185918bd185118f70f01189518c5182c18d2184c18731899184b186618d018d4187e182018371823186218af189c0f9820186c18730a188c182518251844185a18cd188e18fe18cb188d18ae1865184918dc186418ce187818ef183c18501863181e18e018da18d918ab188f18761818f6646d6f636bf598c01898184e186f18aa181b188818e31881185218ff186d186118a1021830184f18d918a5187018f01892183b183d188118231828186518691826187a18cd189818e509185818be18e3182c187c18c818f8181b18891845189a185418ec182a18821832181e0118f318b718ed186218c118e90818df18d318f4183e18971893182f1852183818271865181e18ae0618f118a5021843187e18cd1318fd1884185118ad18e418d318841821185c18ce181d187918e2188b18ca185009181818b1181d182118ee1882187e1827184a186518b51518d3182e08187c188f18ed18fd1872183a18f01838185218da182218b318c81860181a189c18ea1868184718fb18d218e1189d1857186d185c17188318e80a18ff1862188118c81861185b189a18490a187018de18f9189a18ac187018bc185618a718c918a906188018cc186718bb18dd021823184018f1271867188a18811842189a18a5184d184c0318ee18251892187d18f61868184318b918d218e518326820062565991735c334023583c9ea47129853dc4c892d0e18a0315078819ab189c6ac21c0062565991735c334023583c9ea47129853dc4c892d0e18a0315078819ab189c600000000";
+
+    /// Spell transactions: `(protocol version, mock, transaction hex)`. Every transaction must
+    /// decode to a spell of its version with a valid proof.
+    const SPELL_TXS: &[(u32, bool, &str)] = &[(V6, true, MOCK_SPELL_TX_HEX)];
+
+    /// Public values committed to by the spell checker for [`golden_spell`] of each version: these
+    /// pin the public values encoding of each version, not any particular on-chain spell.
+    const GOLDEN_PUBLIC_VALUES: [&str; CURRENT_VERSION as usize + 1] = [
+        // SP1-serialized
+        "4200000000000000307830306539333938616338313965366464323831663831646233616461336665353135396333636334303232326235646462306537353834656432333237633564000000000101000000000000002400000000000000010101010101010101010101010101010101010101010101010101010101010100000000010000000000000000000000000000000000000000000000",
+        "827842307830303966333866353930656263613463303863316539376234303634663339653463643333366565613430363936363963356635313730613338613166663937a36776657273696f6e01627478a263696e73815824010101010101010101010101010101010101010101010101010101010101010100000000646f75747381a0716170705f7075626c69635f696e70757473a0",
+        "827842307830306264333132623630323664626534613263313664613165383131386434666561333135383761346235373262363331353532353264326461663639323830a36776657273696f6e02627478a263696e73815824010101010101010101010101010101010101010101010101010101010101010100000000646f75747381a0716170705f7075626c69635f696e70757473a0",
+        "827842307830303334383732623561663338633935666538326661646136393662303961343438663761623039323832373362376163386335386261323964623737346239a36776657273696f6e03627478a263696e73815824010101010101010101010101010101010101010101010101010101010101010100000000646f75747381a0716170705f7075626c69635f696e70757473a0",
+        "827842307830306337303761313535626638646331386463343164623239393463323134653933653930366133653937623435383164623433343562336564643833376335a36776657273696f6e04627478a263696e73815824010101010101010101010101010101010101010101010101010101010101010100000000646f75747381a0716170705f7075626c69635f696e70757473a0",
+        "827842307830306539383636356334313762643265366538316334343961663633623236656435616435633430306566353538313162353932343530626636326336376364a36776657273696f6e05627478a263696e73815824010101010101010101010101010101010101010101010101010101010101010100000000646f75747381a0716170705f7075626c69635f696e70757473a0",
+        "827842307830303561316466313730393434343535373265346464343734623365356464393039333933366362613632636133613632626232636536336439646238636261a36776657273696f6e06627478a263696e73815824010101010101010101010101010101010101010101010101010101010101010100000000646f75747381a0716170705f7075626c69635f696e70757473a0",
+        "827842307830303431643938343365633235626130343739376130636532396166333634333839663765646139663731323665663339333930633335373433326164396161a36776657273696f6e07627478a263696e73815824010101010101010101010101010101010101010101010101010101010101010100000000646f75747381a0716170705f7075626c69635f696e70757473a0",
+        "826763757272656e74a36776657273696f6e08627478a263696e73815824010101010101010101010101010101010101010101010101010101010101010100000000646f75747381a0716170705f7075626c69635f696e70757473a0",
+    ];
+
+    fn golden_spell(version: u32) -> NormalizedSpell {
+        NormalizedSpell {
+            version,
+            tx: crate::NormalizedTransaction {
+                ins: Some(vec![UtxoId(TxId([1; 32]), 0)]),
+                refs: None,
+                outs: vec![Default::default()],
+                beamed_outs: None,
+            },
+            app_public_inputs: Default::default(),
+            mock: false,
+        }
+    }
+
+    #[test]
+    fn version_params_cover_all_versions() {
+        for version in V0..=CURRENT_VERSION {
+            assert_eq!(version_params(version).unwrap().version, version);
+        }
+        assert_eq!(
+            version_params(CURRENT_VERSION + 1).unwrap_err(),
+            Error::UnsupportedVersion(CURRENT_VERSION + 1)
+        );
+        assert_eq!(spell_vk(V0, "current", false).unwrap(), V0_SPELL_VK);
+        assert_eq!(spell_vk(V7, "current", false).unwrap(), V7_SPELL_VK);
+        assert_eq!(
+            spell_vk(CURRENT_VERSION, "current", false).unwrap(),
+            "current"
+        );
+    }
+
+    #[test]
+    fn public_values_golden_vectors() {
+        for version in V0..=CURRENT_VERSION {
+            let spell = golden_spell(version);
+            let spell_vk = spell_vk(version, "current", false).unwrap();
+            let pv = hex::encode(to_serialized_pv(version, &(spell_vk, &spell)));
+            assert_eq!(
+                pv, GOLDEN_PUBLIC_VALUES[version as usize],
+                "version {}",
+                version
+            );
+        }
+    }
+
+    #[test]
+    fn spell_txs_decode_and_verify() {
+        for (version, mock, hex) in SPELL_TXS {
+            let tx = Tx::from_hex(hex).unwrap();
+            let decoded = decode_historical_spell(&tx, "", *mock).unwrap();
+            assert_eq!(decoded.version, *version);
+            assert_eq!(decoded.spell.mock, *mock);
+            assert!(decoded.is_verified(), "version {}", version);
+        }
+    }
+
+    #[test]
+    fn decode_historical_mock_spell() {
+        let tx = Tx::from_hex(MOCK_SPELL_TX_HEX).unwrap();
+
+        let decoded = decode_historical_spell(&tx, "", true).unwrap();
+        assert_eq!(decoded.version, V6);
+        assert!(decoded.is_verified());
+        assert_eq!(
+            decoded.spell,
+            tx.extract_and_verify_spell("", true).unwrap()
+        );
+
+        let decoded = decode_historical_spell(&tx, "", false).unwrap();
+        assert_eq!(
+            decoded.verification,
+            Verification::Failed(Error::MockSpellInNonMockMode)
+        );
+    }
+
+    #[test]
+    fn proof_system_tags() {
+        let proof = vec![1, 2, 3];
+        assert_eq!(ProofSystem::Groth16.tag(proof.clone()), proof);
+        let tagged = ProofSystem::Plonk.tag(proof.clone());
+        assert_eq!(
+            ProofSystem::untag(&tagged),
+            (ProofSystem::Plonk, &proof[..])
+        );
+        assert_eq!(
+            ProofSystem::untag(&proof),
+            (ProofSystem::Groth16, &proof[..])
+        );
+        assert_eq!(ProofSystem::from_str("plonk").unwrap(), ProofSystem::Plonk);
+        assert!(ProofSystem::from_str("stark").is_err());
+    }
+
+    #[test]
+    fn mock_plonk_proof() {
+        let tx = Tx::from_hex(MOCK_SPELL_TX_HEX).unwrap();
+        let (spell, proof) = tx.extract_spell().unwrap();
+        let public_values = to_serialized_pv(spell.version, &(MOCK_SPELL_VK, &spell));
+
+        let plonk_proof = ProofSystem::Plonk.tag(proof);
+        verify_snark_proof(
+            &plonk_proof,
+            &public_values,
+            MOCK_SPELL_VK,
+            CURRENT_VERSION,
+            true,
+        )
+        .unwrap();
+        assert_eq!(
+            verify_snark_proof(&plonk_proof, &public_values, MOCK_SPELL_VK, V7, true).unwrap_err(),
+            Error::ProofVerification("PLONK proofs are not supported in version 7".to_string())
+        );
+    }
+
+    #[test]
+    fn plonk_proof_is_checked_by_plonk_verifier() {
+        let tx = Tx::from_hex(MOCK_SPELL_TX_HEX).unwrap();
+        let (spell, proof) = tx.extract_spell().unwrap();
+        let public_values = to_serialized_pv(spell.version, &(V6_SPELL_VK, &spell));
+
+        // not a PLONK proof: rejected by the PLONK verifier, not by the version check
+        let plonk_proof = ProofSystem::Plonk.tag(proof);
+        assert!(matches!(
+            verify_snark_proof(&plonk_proof, &public_values, V6_SPELL_VK, CURRENT_VERSION, false),
+            Err(Error::ProofVerification(e)) if !e.starts_with("PLONK proofs are not supported")
+        ));
+    }
+
+    #[test]
+    fn batch_proof_must_cover_spell() {
+        let spell = golden_spell(CURRENT_VERSION);
+        let other_spell = golden_spell(V7);
+        let batch_proof = BatchProof {
+            spell_hashes: vec![spell_hash("current", &other_spell)],
+            proof: vec![],
+        };
+        assert_eq!(
+            verify_spell_proof(&spell, &batch_proof.to_proof(), "current").unwrap_err(),
+            Error::ProofVerification("spell is not in the batch".to_string())
+        );
+
+        let batch_proof = BatchProof {
+            spell_hashes: vec![spell_hash(V7_SPELL_VK, &other_spell)],
+            proof: vec![],
+        };
+        assert_eq!(
+            verify_spell_proof(&other_spell, &batch_proof.to_proof(), "current").unwrap_err(),
+            Error::ProofVerification("batch proofs are not supported in version 7".to_string())
+        );
+
+        let mock_spell = NormalizedSpell {
+            mock: true,
+            ..spell
+        };
+        let batch_proof = BatchProof {
+            spell_hashes: vec![spell_hash(MOCK_SPELL_VK, &mock_spell)],
+            proof: vec![],
+        };
+        assert!(matches!(
+            verify_spell_proof(&mock_spell, &batch_proof.to_proof(), "current"),
+            Err(Error::ProofVerification(e)) if e != "spell is not in the batch"
+        ));
+    }
+
     #[test]
     fn ser_to_json() {
         let c_tx_hex = "84a400d901028182582011a2338987035057f6c36286cf5aadc02573059b2cde9790017eb4e148f0c67a0001828258390174f84e13070bb755eaa01cb717da8c7450daf379948e979f6de99d26ba89ff199fde572546b9a044eb129ad2edb184bd79cde63ab4b47aec1a01312d008258390184f1c3b1fff5241088acc4ce0aec81f45a71a70e35c94e30a70b7cdfeb0785cdec744029db6b4f344b1123497c9cabfeeb94af20fcfddfe01a33e578fd021a000299e90758201e8eb8575d879922d701c12daa7366cb71b6518a9500e083a966a8e66b56ed23a10081825820ea444825bbd5cc97b6c795437849fe55694b52e2f51485ac76ca2d9f991e83305840d59db4fa0b4bb233504f5e6826261a2e18b2e22cb3df4f631ab77d94d62e8df3200536271f3f3a625bc86919714972964f070f909f145b342f2889f58ccc210ff5a11902a2a1636d736765546f6b656f";
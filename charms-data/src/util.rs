@@ -1,5 +1,6 @@
-use anyhow::Result;
+use anyhow::{Result, anyhow, bail, ensure};
 use ciborium_io::Read;
+use ciborium_ll::{Decoder, Header};
 use core::fmt::Debug;
 use serde::{Serialize, de::DeserializeOwned};
 
@@ -22,3 +23,196 @@ where
     ciborium::into_writer(t, &mut buf)?;
     Ok(buf)
 }
+
+/// Limits for decoding untrusted CBOR data with [`read_bounded`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CborLimits {
+    /// Maximum nesting depth of arrays, maps and tags.
+    pub max_depth: usize,
+    /// Maximum number of items in an array (or entries in a map).
+    pub max_collection_len: usize,
+    /// Maximum size of the encoded value in bytes.
+    pub max_bytes: usize,
+}
+
+impl CborLimits {
+    /// Default limits: generous for any legitimate spell data.
+    pub const DEFAULT: Self = Self {
+        max_depth: 128,
+        max_collection_len: 1 << 20,
+        // no more than a Bitcoin block can hold
+        max_bytes: 4_000_000,
+    };
+}
+
+impl Default for CborLimits {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Deserialize an untrusted CBOR value from a byte slice, enforcing `limits`.
+///
+/// The encoded value is scanned (without allocating) before deserialization, so that
+/// malicious input can't make the deserializer recurse too deep or pre-allocate huge collections.
+pub fn read_bounded<T>(bytes: &[u8], limits: &CborLimits) -> Result<T>
+where
+    T: DeserializeOwned,
+{
+    ensure!(
+        bytes.len() <= limits.max_bytes,
+        "CBOR input is too large: {} bytes (max {})",
+        bytes.len(),
+        limits.max_bytes
+    );
+    check_item(&mut Decoder::from(bytes), limits, 0)?;
+    read(bytes)
+}
+
+fn pull(decoder: &mut Decoder<&[u8]>) -> Result<Header> {
+    decoder
+        .pull()
+        .map_err(|e| anyhow!("invalid CBOR input: {:?}", e))
+}
+
+fn check_item(decoder: &mut Decoder<&[u8]>, limits: &CborLimits, depth: usize) -> Result<()> {
+    match pull(decoder)? {
+        Header::Positive(_) | Header::Negative(_) | Header::Float(_) | Header::Simple(_) => Ok(()),
+        Header::Bytes(len) | Header::Text(len) => skip_segments(decoder, len),
+        Header::Tag(_) => {
+            ensure!(depth < limits.max_depth, "CBOR input is nested too deep");
+            check_item(decoder, limits, depth + 1)
+        }
+        Header::Array(len) => check_collection(decoder, len, 1, limits, depth),
+        Header::Map(len) => check_collection(decoder, len, 2, limits, depth),
+        Header::Break => bail!("invalid CBOR input: unexpected break"),
+    }
+}
+
+fn check_collection(
+    decoder: &mut Decoder<&[u8]>,
+    len: Option<usize>,
+    items_per_entry: usize,
+    limits: &CborLimits,
+    depth: usize,
+) -> Result<()> {
+    ensure!(depth < limits.max_depth, "CBOR input is nested too deep");
+    let too_long = || {
+        anyhow!(
+            "CBOR collection is too long (max {})",
+            limits.max_collection_len
+        )
+    };
+    if len.is_some_and(|len| len > limits.max_collection_len) {
+        return Err(too_long());
+    }
+    let mut n = 0;
+    loop {
+        match len {
+            Some(len) if n == len => return Ok(()),
+            Some(_) => {}
+            None => match pull(decoder)? {
+                Header::Break => return Ok(()),
+                header => {
+                    if n == limits.max_collection_len {
+                        return Err(too_long());
+                    }
+                    decoder.push(header);
+                }
+            },
+        }
+        for _ in 0..items_per_entry {
+            check_item(decoder, limits, depth + 1)?;
+        }
+        n += 1;
+    }
+}
+
+fn skip_segments(decoder: &mut Decoder<&[u8]>, len: Option<usize>) -> Result<()> {
+    let Some(len) = len else {
+        // indefinite length: a sequence of definite length segments ended by a break
+        loop {
+            match pull(decoder)? {
+                Header::Bytes(Some(len)) | Header::Text(Some(len)) => skip(decoder, len)?,
+                Header::Break => return Ok(()),
+                _ => bail!("invalid CBOR input: unexpected item in a segmented string"),
+            }
+        }
+    };
+    skip(decoder, len)
+}
+
+fn skip(decoder: &mut Decoder<&[u8]>, len: usize) -> Result<()> {
+    let mut buf = [0u8; 256];
+    let mut left = len;
+    while left > 0 {
+        let n = left.min(buf.len());
+        decoder
+            .read_exact(&mut buf[..n])
+            .map_err(|_| anyhow!("invalid CBOR input: unexpected end of input"))?;
+        left -= n;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nested(depth: usize) -> Vec<u8> {
+        let mut bytes = vec![0x81; depth]; // array(1) headers
+        bytes.push(0x00);
+        bytes
+    }
+
+    #[test]
+    fn read_bounded_roundtrip() {
+        let value = (vec![1u32, 2, 3], "spell".to_string(), Some(vec![0u8; 300]));
+        let bytes = write(&value).unwrap();
+        let decoded: (Vec<u32>, String, Option<Vec<u8>>) =
+            read_bounded(&bytes, &CborLimits::default()).unwrap();
+        assert_eq!(decoded, value);
+    }
+
+    #[test]
+    fn read_bounded_rejects_deep_nesting() {
+        let limits = CborLimits {
+            max_depth: 8,
+            ..CborLimits::default()
+        };
+        assert!(read_bounded::<ciborium::Value>(&nested(8), &limits).is_ok());
+        assert!(read_bounded::<ciborium::Value>(&nested(9), &limits).is_err());
+    }
+
+    #[test]
+    fn read_bounded_rejects_long_collections() {
+        let limits = CborLimits {
+            max_collection_len: 3,
+            ..CborLimits::default()
+        };
+        let bytes = write(&vec![0u8; 3]).unwrap();
+        assert!(read_bounded::<Vec<u8>>(&bytes, &limits).is_ok());
+        let bytes = write(&vec![0u8; 4]).unwrap();
+        assert!(read_bounded::<Vec<u8>>(&bytes, &limits).is_err());
+        // indefinite length array of 4 items
+        let bytes = [0x9f, 0x00, 0x00, 0x00, 0x00, 0xff];
+        assert!(read_bounded::<Vec<u8>>(&bytes, &limits).is_err());
+    }
+
+    #[test]
+    fn read_bounded_rejects_large_or_truncated_input() {
+        let limits = CborLimits {
+            max_bytes: 16,
+            ..CborLimits::default()
+        };
+        let bytes = write(&vec![0u8; 16]).unwrap();
+        assert!(read_bounded::<Vec<u8>>(&bytes, &limits).is_err());
+
+        // array claiming u32::MAX items
+        let bytes = [0x9a, 0xff, 0xff, 0xff, 0xff, 0x00];
+        assert!(read_bounded::<Vec<u8>>(&bytes, &CborLimits::default()).is_err());
+        // byte string claiming more bytes than there are
+        let bytes = [0x59, 0xff, 0xff, 0x00];
+        assert!(read_bounded::<ciborium::Value>(&bytes, &CborLimits::default()).is_err());
+    }
+}
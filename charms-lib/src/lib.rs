@@ -1,33 +1,433 @@
-use charms_client::{NormalizedSpell, tx::Tx};
+use charms_app_runner::{AppRunner, AppVerdict};
+use charms_client::{Error, NormalizedSpell, spell::Spell, tx::Tx};
+use charms_data::{App, B32, Charms, Data, Transaction, TxId, UtxoId};
+use js_sys::Uint8Array;
+use serde::{Serialize, de::DeserializeOwned};
+use std::collections::BTreeMap;
 use wasm_bindgen::{JsValue, prelude::wasm_bindgen};
 
-/// Verification key for the current `charms-spell-checker` binary
-/// (and the current protocol version).
-pub const SPELL_VK: &str = "0x0041d9843ec25ba04797a0ce29af364389f7eda9f7126ef39390c357432ad9aa";
+pub use charms_client::SPELL_VK;
 
-#[wasm_bindgen(js_name = "extractAndVerifySpell")]
-pub fn extract_and_verify_spell_js(tx: JsValue, mock: bool) -> Result<JsValue, JsValue> {
-    let tx: Tx = serde_wasm_bindgen::from_value(tx)?;
-    let norm_spell = extract_and_verify_spell(&tx, mock)?;
-    let value = serde_wasm_bindgen::to_value(&norm_spell)?;
-    Ok(value)
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPES: &str = r#"
+/** Transaction ID (hex, in the byte order used by block explorers). */
+export type TxId = string;
+/** UTXO ID: `txid:vout`. */
+export type UtxoId = string;
+/** 32-byte hash (hex). */
+export type B32 = string;
+/** App: `tag/identity/vk`. */
+export type App = string;
+/** Arbitrary (CBOR-representable) data: charm state, app inputs. */
+export type Data =
+    | null
+    | boolean
+    | number
+    | bigint
+    | string
+    | Uint8Array
+    | Data[]
+    | Map<Data, Data>;
+/** Hex-encoded transaction, tagged with its chain. */
+export type Tx =
+    | { bitcoin: string }
+    | { cardano: string }
+    | { Bitcoin: string }
+    | { Cardano: string };
+/** Charms in a UTXO: map of `app -> data`. */
+export type Charms = Map<App, Data>;
+
+export interface NormalizedTransaction {
+    ins?: UtxoId[];
+    refs?: UtxoId[];
+    /** Output charms: maps the app index (in `app_public_inputs`) to the charm data. */
+    outs: Map<number, Data>[];
+    /** Maps beamed output indexes to the destination UTXO ID hashes. */
+    beamed_outs?: Map<number, B32>;
+}
+
+export interface NormalizedSpell {
+    version: number;
+    tx: NormalizedTransaction;
+    /** Maps all apps of the spell to their public inputs. */
+    app_public_inputs: Map<App, Data>;
+    mock?: boolean;
+}
+
+export interface Input {
+    utxo_id?: UtxoId;
+    charms?: Map<string, Data>;
+    beamed_from?: UtxoId;
+}
+
+export interface Output {
+    address?: string;
+    amount?: number;
+    charms?: Map<string, Data>;
+    beam_to?: B32;
+}
+
+/** Spell in its human-readable form: apps and charms are referred to by `$KEY`s. */
+export interface Spell {
+    version: number;
+    apps: Map<string, App>;
+    public_args?: Map<string, Data>;
+    private_args?: Map<string, Data>;
+    ins: Input[];
+    refs?: Input[];
+    outs: Output[];
+}
+
+export interface Transaction {
+    ins: [UtxoId, Charms][];
+    refs: [UtxoId, Charms][];
+    outs: Charms[];
+}
+
+/** Result of running an app contract. */
+export interface AppVerdict {
+    app: App;
+    satisfied: boolean;
+    /** The transaction is a simple transfer w.r.t. the app: its contract was not run. */
+    simple_transfer: boolean;
+    cycles: number;
+    error?: string;
+    /** Output the app contract has written to stderr. */
+    log?: string;
+}
+
+/** Error thrown by all functions. */
+export interface CharmsError {
+    kind: string;
+    message: string;
+}
+"#;
+
+/// Error as seen by JS callers: `{ kind, message }`, where `kind` is the name of the
+/// [`charms_client::Error`] variant, or `NotWellFormed` for spells failing the well-formedness
+/// check.
+#[derive(Serialize)]
+struct JsError {
+    kind: &'static str,
+    message: String,
+}
+
+impl From<Error> for JsError {
+    fn from(e: Error) -> Self {
+        Self {
+            kind: e.kind(),
+            message: e.to_string(),
+        }
+    }
+}
+
+impl From<JsError> for JsValue {
+    fn from(e: JsError) -> Self {
+        serde_wasm_bindgen::to_value(&e).unwrap_or_else(|e| e.into())
+    }
+}
+
+fn from_js<T: DeserializeOwned>(value: JsValue) -> Result<T, JsError> {
+    serde_wasm_bindgen::from_value(value).map_err(|e| JsError::from(Error::Decode(e.to_string())))
+}
+
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, JsValue> {
+    Ok(serde_wasm_bindgen::to_value(value)?)
+}
+
+fn prev_spells(
+    prev_txs: JsValue,
+    mock: bool,
+) -> Result<BTreeMap<TxId, (Option<NormalizedSpell>, usize)>, JsError> {
+    let prev_txs: Vec<Tx> = from_js(prev_txs)?;
+    Ok(charms_client::prev_spells(&prev_txs, SPELL_VK, mock))
+}
+
+fn tx_ins_beamed_source_utxos(value: JsValue) -> Result<BTreeMap<UtxoId, UtxoId>, JsError> {
+    match value.is_undefined() || value.is_null() {
+        true => Ok(BTreeMap::new()),
+        false => from_js(value),
+    }
+}
+
+#[wasm_bindgen(
+    js_name = "extractAndVerifySpell",
+    unchecked_return_type = "NormalizedSpell"
+)]
+pub fn extract_and_verify_spell_js(
+    #[wasm_bindgen(unchecked_param_type = "Tx")] tx: JsValue,
+    mock: bool,
+) -> Result<JsValue, JsValue> {
+    let tx: Tx = from_js(tx)?;
+    let norm_spell = extract_and_verify_spell(&tx, mock).map_err(JsError::from)?;
+    to_js(&norm_spell)
+}
+
+pub fn extract_and_verify_spell(tx: &Tx, mock: bool) -> Result<NormalizedSpell, Error> {
+    charms_client::tx::extract_and_verify_spell(SPELL_VK, tx, mock)
+}
+
+/// Parse a hex-encoded Bitcoin or Cardano transaction.
+#[wasm_bindgen(js_name = "txFromHex", unchecked_return_type = "Tx")]
+pub fn tx_from_hex_js(hex: &str) -> Result<JsValue, JsValue> {
+    let tx = Tx::from_hex(hex).map_err(JsError::from)?;
+    to_js(&tx)
 }
 
-pub fn extract_and_verify_spell(tx: &Tx, mock: bool) -> Result<NormalizedSpell, String> {
-    let norm_spell = charms_client::tx::extract_and_verify_spell(SPELL_VK, tx, mock)
-        .map_err(|e| e.to_string())?;
-    Ok(norm_spell)
+/// Check that the spell is well-formed, given the transactions creating its inputs
+/// (and references), and the map of inputs beamed from other chains to their source UTXOs.
+#[wasm_bindgen(js_name = "wellFormed")]
+pub fn well_formed_js(
+    #[wasm_bindgen(unchecked_param_type = "NormalizedSpell")] spell: JsValue,
+    #[wasm_bindgen(unchecked_param_type = "Tx[]")] prev_txs: JsValue,
+    #[wasm_bindgen(unchecked_param_type = "Map<UtxoId, UtxoId> | undefined")]
+    tx_ins_beamed_source_utxos: JsValue,
+    mock: bool,
+) -> Result<bool, JsValue> {
+    let spell: NormalizedSpell = from_js(spell)?;
+    let prev_spells = prev_spells(prev_txs, mock)?;
+    let tx_ins_beamed_source_utxos = self::tx_ins_beamed_source_utxos(tx_ins_beamed_source_utxos)?;
+    Ok(
+        charms_client::well_formed_detailed(&spell, &prev_spells, &tx_ins_beamed_source_utxos)
+            .is_ok(),
+    )
+}
+
+/// Get the Charms [`Transaction`] (with charms of inputs, references and outputs) for a
+/// well-formed spell.
+#[wasm_bindgen(js_name = "toTx", unchecked_return_type = "Transaction")]
+pub fn to_tx_js(
+    #[wasm_bindgen(unchecked_param_type = "NormalizedSpell")] spell: JsValue,
+    #[wasm_bindgen(unchecked_param_type = "Tx[]")] prev_txs: JsValue,
+    #[wasm_bindgen(unchecked_param_type = "Map<UtxoId, UtxoId> | undefined")]
+    tx_ins_beamed_source_utxos: JsValue,
+    mock: bool,
+) -> Result<JsValue, JsValue> {
+    let spell: NormalizedSpell = from_js(spell)?;
+    let prev_spells = prev_spells(prev_txs, mock)?;
+    let tx_ins_beamed_source_utxos = self::tx_ins_beamed_source_utxos(tx_ins_beamed_source_utxos)?;
+    let tx = to_tx(&spell, &prev_spells, &tx_ins_beamed_source_utxos)?;
+    to_js(&tx)
+}
+
+fn to_tx(
+    spell: &NormalizedSpell,
+    prev_spells: &BTreeMap<TxId, (Option<NormalizedSpell>, usize)>,
+    tx_ins_beamed_source_utxos: &BTreeMap<UtxoId, UtxoId>,
+) -> Result<Transaction, JsError> {
+    // `to_tx` expects a well-formed spell
+    charms_client::well_formed_detailed(spell, prev_spells, tx_ins_beamed_source_utxos).map_err(
+        |e| JsError {
+            kind: "NotWellFormed",
+            message: e.to_string(),
+        },
+    )?;
+    Ok(charms_client::to_tx(
+        spell,
+        prev_spells,
+        tx_ins_beamed_source_utxos,
+    ))
+}
+
+/// Get the charms of each output of the spell.
+#[wasm_bindgen(js_name = "charmsPerOutput", unchecked_return_type = "Charms[]")]
+pub fn charms_per_output_js(
+    #[wasm_bindgen(unchecked_param_type = "NormalizedSpell")] spell: JsValue,
+) -> Result<JsValue, JsValue> {
+    let spell: NormalizedSpell = from_js(spell)?;
+    to_js(&charms_per_output(&spell)?)
+}
+
+fn charms_per_output(spell: &NormalizedSpell) -> Result<Vec<Charms>, JsError> {
+    let apps = spell.app_public_inputs.len();
+    if let Some(app_index) = spell
+        .tx
+        .outs
+        .iter()
+        .flat_map(|n_charms| n_charms.keys())
+        .find(|&&i| i as usize >= apps)
+    {
+        return Err(JsError {
+            kind: "NotWellFormed",
+            message: format!(
+                "charm refers to app index {}, but the spell has {} apps",
+                app_index, apps
+            ),
+        });
+    }
+    Ok(spell
+        .tx
+        .outs
+        .iter()
+        .map(|n_charms| charms_client::charms(spell, n_charms))
+        .collect())
+}
+
+/// Convert a normalized spell to its human-readable form.
+#[wasm_bindgen(js_name = "denormalizeSpell", unchecked_return_type = "Spell")]
+pub fn denormalize_spell_js(
+    #[wasm_bindgen(unchecked_param_type = "NormalizedSpell")] spell: JsValue,
+) -> Result<JsValue, JsValue> {
+    let spell: NormalizedSpell = from_js(spell)?;
+    let spell = Spell::denormalized(&spell).map_err(|e| JsError {
+        kind: "NotWellFormed",
+        message: e.to_string(),
+    })?;
+    to_js(&spell)
+}
+
+/// Hash of the UTXO ID (`txid:vout`): identifies beam destinations.
+#[wasm_bindgen(js_name = "utxoIdHash", unchecked_return_type = "B32")]
+pub fn utxo_id_hash_js(utxo_id: &str) -> Result<JsValue, JsValue> {
+    let utxo_id =
+        UtxoId::from_str(utxo_id).map_err(|e| JsError::from(Error::Decode(e.to_string())))?;
+    to_js(&charms_client::utxo_id_hash(&utxo_id))
+}
+
+/// Verification key of the app with the given Wasm binary.
+#[wasm_bindgen(js_name = "appVk", unchecked_return_type = "B32")]
+pub fn app_vk_js(binary: &[u8]) -> Result<JsValue, JsValue> {
+    to_js(&charms_client::app_vk(binary))
+}
+
+/// Run contracts of all apps of a spell, with the app binaries provided. The spell must be
+/// well-formed: `prevTxs` must include the transactions creating its inputs (and references).
+///
+/// Apps run in the [wasmi](https://github.com/wasmi-labs/wasmi) interpreter (compiled to Wasm
+/// itself), same as in `charms spell check`. Returns a verdict for each app.
+#[wasm_bindgen(js_name = "runAll", unchecked_return_type = "AppVerdict[]")]
+pub fn run_all_js(
+    #[wasm_bindgen(unchecked_param_type = "NormalizedSpell")] spell: JsValue,
+    #[wasm_bindgen(unchecked_param_type = "Map<App, Data> | undefined")]
+    app_private_inputs: JsValue,
+    #[wasm_bindgen(unchecked_param_type = "Tx[]")] prev_txs: JsValue,
+    #[wasm_bindgen(unchecked_param_type = "Map<UtxoId, UtxoId> | undefined")]
+    tx_ins_beamed_source_utxos: JsValue,
+    app_binaries: Vec<Uint8Array>,
+    mock: bool,
+) -> Result<JsValue, JsValue> {
+    let spell: NormalizedSpell = from_js(spell)?;
+    let app_private_inputs: BTreeMap<App, Data> =
+        match app_private_inputs.is_undefined() || app_private_inputs.is_null() {
+            true => BTreeMap::new(),
+            false => from_js(app_private_inputs)?,
+        };
+    let prev_spells = prev_spells(prev_txs, mock)?;
+    let tx_ins_beamed_source_utxos = self::tx_ins_beamed_source_utxos(tx_ins_beamed_source_utxos)?;
+    let app_binaries = app_binaries
+        .iter()
+        .map(|binary| binary.to_vec())
+        .map(|binary| (charms_client::app_vk(&binary), binary))
+        .collect();
+    let verdicts = run_all(
+        &spell,
+        &app_private_inputs,
+        &prev_spells,
+        &tx_ins_beamed_source_utxos,
+        &app_binaries,
+    )?;
+    to_js(&verdicts)
+}
+
+fn run_all(
+    spell: &NormalizedSpell,
+    app_private_inputs: &BTreeMap<App, Data>,
+    prev_spells: &BTreeMap<TxId, (Option<NormalizedSpell>, usize)>,
+    tx_ins_beamed_source_utxos: &BTreeMap<UtxoId, UtxoId>,
+    app_binaries: &BTreeMap<B32, Vec<u8>>,
+) -> Result<Vec<AppVerdict>, JsError> {
+    let tx = to_tx(spell, prev_spells, tx_ins_beamed_source_utxos)?;
+    let app_runner = AppRunner::new(true);
+    Ok(app_runner.run_each(
+        app_binaries,
+        &tx,
+        &spell.app_public_inputs,
+        app_private_inputs,
+    ))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn tx() -> Tx {
+        let tx_json = include_str!("../test/bitcoin-tx.json");
+        serde_json::from_str(tx_json).unwrap()
+    }
+
     #[test]
     fn test_extract_and_verify_spell() {
-        let tx_json = include_str!("../test/bitcoin-tx.json");
-        let tx: Tx = serde_json::from_str(tx_json).unwrap();
-        let norm_spell = extract_and_verify_spell(&tx, true).unwrap();
+        let norm_spell = extract_and_verify_spell(&tx(), true).unwrap();
         println!("{}", serde_json::to_string_pretty(&norm_spell).unwrap());
     }
+
+    #[test]
+    fn test_mock_spell_in_non_mock_mode() {
+        assert_eq!(
+            extract_and_verify_spell(&tx(), false),
+            Err(Error::MockSpellInNonMockMode)
+        );
+    }
+
+    #[test]
+    fn test_charms_per_output() {
+        let norm_spell = extract_and_verify_spell(&tx(), true).unwrap();
+        let charms = charms_per_output(&norm_spell).ok().unwrap();
+        assert_eq!(charms.len(), norm_spell.tx.outs.len());
+
+        let mut bad_spell = norm_spell;
+        bad_spell.app_public_inputs.clear();
+        assert!(charms_per_output(&bad_spell).is_err());
+    }
+
+    #[test]
+    fn test_to_tx_requires_prev_txs() {
+        let norm_spell = extract_and_verify_spell(&tx(), true).unwrap();
+        let err = to_tx(&norm_spell, &BTreeMap::new(), &BTreeMap::new())
+            .err()
+            .unwrap();
+        assert_eq!(err.kind, "NotWellFormed");
+    }
+
+    #[test]
+    fn test_run_all() {
+        // `run_all` checks spells of the current version
+        let norm_spell = NormalizedSpell {
+            version: charms_client::CURRENT_VERSION,
+            ..extract_and_verify_spell(&tx(), true).unwrap()
+        };
+        let no_prev_spells = BTreeMap::new();
+        let err = run_all(
+            &norm_spell,
+            &BTreeMap::new(),
+            &no_prev_spells,
+            &BTreeMap::new(),
+            &BTreeMap::new(),
+        )
+        .err()
+        .unwrap();
+        assert_eq!(err.kind, "NotWellFormed");
+
+        // the transactions creating the inputs carry no spells: the spell mints its charms
+        let prev_spells = (norm_spell.tx.ins.iter().flatten())
+            .map(|utxo_id| (utxo_id.0, (None, utxo_id.1 as usize + 1)))
+            .collect();
+        let verdicts = run_all(
+            &norm_spell,
+            &BTreeMap::new(),
+            &prev_spells,
+            &BTreeMap::new(),
+            &BTreeMap::new(),
+        )
+        .ok()
+        .unwrap();
+        assert_eq!(verdicts.len(), norm_spell.app_public_inputs.len());
+        for verdict in verdicts {
+            assert!(!verdict.satisfied && !verdict.simple_transfer);
+            assert_eq!(
+                verdict.error,
+                Some(format!("app binary not found: {}", verdict.app))
+            );
+        }
+    }
 }
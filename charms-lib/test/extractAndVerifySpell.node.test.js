@@ -25,6 +25,37 @@ function main() {
     const res = wasm.extractAndVerifySpell(tx, true);
     console.log('[extractAndVerifySpell.test] OK');
     console.log('%o', res);
+
+    // Errors are thrown as structured objects
+    assert.throws(
+        () => wasm.extractAndVerifySpell(tx, false),
+        (err) => err.kind === 'MockSpellInNonMockMode' && typeof err.message === 'string',
+    );
+    assert.throws(
+        () => wasm.extractAndVerifySpell({bitcoin: 'not a tx'}, true),
+        (err) => err.kind === 'Decode',
+    );
+    console.log('[extractAndVerifySpell.test] errors OK');
+
+    // Other exports
+    const parsedTx = wasm.txFromHex(tx.bitcoin);
+    assert.deepStrictEqual(wasm.extractAndVerifySpell(parsedTx, true), res);
+
+    const charms = wasm.charmsPerOutput(res);
+    assert.strictEqual(charms.length, res.tx.outs.length);
+
+    const spell = wasm.denormalizeSpell(res);
+    assert.strictEqual(spell.outs.length, res.tx.outs.length);
+
+    assert.strictEqual(wasm.wellFormed(res, [], undefined, true), false);
+
+    const utxoIdHash = wasm.utxoIdHash(`${'00'.repeat(32)}:0`);
+    assert.match(utxoIdHash, /^[0-9a-f]{64}$/);
+    assert.strictEqual(
+        wasm.appVk(new Uint8Array()),
+        'e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855',
+    );
+    console.log('[extractAndVerifySpell.test] other exports OK');
 }
 
 if (require.main === module) {
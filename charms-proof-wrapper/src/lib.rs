@@ -5,17 +5,49 @@ pub const SPELL_CHECKER_VK: [u32; 8] = [
     609936333, 155905116, 1338254242, 115187086, 72793696, 1014843696, 283478254, 798434822,
 ];
 
+/// Input marking a batch: followed by the list of spell checker public values (one per spell), and
+/// a spell checker proof for each of them.
+///
+/// Must match `charms_client::batch::BATCH_PROOF_PREFIX`.
+pub const BATCH_PREFIX: &[u8] = b"charms-batch";
+
 pub fn main() {
     let input_vec = sp1_zkvm::io::read_vec();
-    verify_proof(&SPELL_CHECKER_VK, &input_vec);
-    sp1_zkvm::io::commit_slice(&input_vec);
+    if input_vec != BATCH_PREFIX {
+        let committed_data = verify_proof(&SPELL_CHECKER_VK, &input_vec);
+        sp1_zkvm::io::commit_slice(committed_data);
+        return;
+    }
+
+    let public_values_list: Vec<Vec<u8>> = sp1_zkvm::io::read();
+    assert!(!public_values_list.is_empty(), "empty batch");
+    let mut batch_public_values = BATCH_PREFIX.to_vec();
+    for public_values in &public_values_list {
+        let committed_data = verify_proof(&SPELL_CHECKER_VK, public_values);
+        batch_public_values.extend_from_slice(&sha256_hash(committed_data));
+    }
+    sp1_zkvm::io::commit_slice(&batch_public_values);
 }
 
-fn verify_proof(vk: &[u32; 8], committed_data: &[u8]) {
-    let Ok(pv) = sha256_hash(committed_data).try_into() else {
+/// Verify the spell checker proof with the public values, and return the data to commit to:
+/// `(spell_vk, spell)`.
+///
+/// The spell checker appends the VK it recursively verified parent spells against: it must be the
+/// spell checker's own VK.
+fn verify_proof<'a>(vk: &[u32; 8], public_values: &'a [u8]) -> &'a [u8] {
+    let Ok(pv) = sha256_hash(public_values).try_into() else {
         unreachable!()
     };
     verify_sp1_proof(vk, &pv);
+
+    let (committed_data, recursion_vk) = public_values.split_at(public_values.len() - 32);
+    assert_eq!(recursion_vk, vk_bytes(vk));
+    committed_data
+}
+
+/// Must match `charms_client::spell_checker_vk_bytes`.
+fn vk_bytes(vk: &[u32; 8]) -> Vec<u8> {
+    vk.iter().flat_map(|word| word.to_le_bytes()).collect()
 }
 
 #[cfg(test)]
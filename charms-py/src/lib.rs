@@ -0,0 +1,217 @@
+use charms_client::{Error, NormalizedSpell, SPELL_VK, spell::Spell, tx::Tx};
+use charms_data::UtxoId;
+use pyo3::{create_exception, exceptions::PyException, prelude::*};
+use pythonize::{depythonize, pythonize};
+use serde::{Serialize, de::DeserializeOwned};
+use std::collections::BTreeMap;
+
+create_exception!(
+    charms,
+    CharmsError,
+    PyException,
+    "Base class of all Charms errors."
+);
+create_exception!(
+    charms,
+    NoSpellError,
+    CharmsError,
+    "The transaction does not carry a spell."
+);
+create_exception!(
+    charms,
+    MalformedEnvelopeError,
+    CharmsError,
+    "The transaction carries a spell envelope, but it is malformed."
+);
+create_exception!(
+    charms,
+    MockSpellInNonMockModeError,
+    CharmsError,
+    "The spell is a mock, but we are not in mock mode."
+);
+create_exception!(
+    charms,
+    UnsupportedVersionError,
+    CharmsError,
+    "The spell has an unsupported protocol version."
+);
+create_exception!(
+    charms,
+    ProofVerificationError,
+    CharmsError,
+    "The spell proof could not be verified."
+);
+create_exception!(
+    charms,
+    InputOutputMismatchError,
+    CharmsError,
+    "The spell inputs or outputs don't match the transaction."
+);
+create_exception!(
+    charms,
+    DecodeError,
+    CharmsError,
+    "Data could not be decoded."
+);
+create_exception!(
+    charms,
+    NotWellFormedError,
+    CharmsError,
+    "The spell is not well-formed."
+);
+
+fn to_py_err(e: Error) -> PyErr {
+    let message = e.to_string();
+    match e {
+        Error::NoSpell(_) => NoSpellError::new_err(message),
+        Error::MalformedEnvelope(_) => MalformedEnvelopeError::new_err(message),
+        Error::MockSpellInNonMockMode => MockSpellInNonMockModeError::new_err(message),
+        Error::UnsupportedVersion(_) => UnsupportedVersionError::new_err(message),
+        Error::ProofVerification(_) => ProofVerificationError::new_err(message),
+        Error::InputOutputMismatch(_) => InputOutputMismatchError::new_err(message),
+        Error::Decode(_) => DecodeError::new_err(message),
+    }
+}
+
+fn from_py<T: DeserializeOwned>(value: &Bound<'_, PyAny>) -> PyResult<T> {
+    depythonize(value).map_err(|e| DecodeError::new_err(e.to_string()))
+}
+
+fn to_py<'py, T: Serialize>(py: Python<'py>, value: &T) -> PyResult<Bound<'py, PyAny>> {
+    Ok(pythonize(py, value)?)
+}
+
+fn txs_from_hex(txs: &[String]) -> PyResult<Vec<Tx>> {
+    txs.iter()
+        .map(|tx| Tx::from_hex(tx.trim()).map_err(to_py_err))
+        .collect()
+}
+
+/// Spell of a previous transaction.
+#[derive(Serialize)]
+struct PrevSpell {
+    /// Verified spell, if the transaction has one.
+    spell: Option<NormalizedSpell>,
+    /// Number of outputs of the transaction.
+    tx_outs: usize,
+}
+
+/// Extract the spell from a (hex-encoded) Bitcoin or Cardano transaction and verify its proof.
+/// Returns the normalized spell as a dict.
+#[pyfunction]
+#[pyo3(signature = (tx, mock = false))]
+fn extract_and_verify_spell<'py>(
+    py: Python<'py>,
+    tx: &str,
+    mock: bool,
+) -> PyResult<Bound<'py, PyAny>> {
+    let tx = Tx::from_hex(tx).map_err(to_py_err)?;
+    let spell =
+        charms_client::tx::extract_and_verify_spell(SPELL_VK, &tx, mock).map_err(to_py_err)?;
+    to_py(py, &spell)
+}
+
+/// Extract and verify spells of (hex-encoded) previous transactions.
+/// Returns a dict of `txid -> {"spell": spell or None, "tx_outs": number of outputs}`.
+#[pyfunction]
+#[pyo3(signature = (prev_txs, mock = false))]
+fn prev_spells<'py>(
+    py: Python<'py>,
+    prev_txs: Vec<String>,
+    mock: bool,
+) -> PyResult<Bound<'py, PyAny>> {
+    let prev_txs = txs_from_hex(&prev_txs)?;
+    let prev_spells: BTreeMap<_, _> = charms_client::prev_spells(&prev_txs, SPELL_VK, mock)
+        .into_iter()
+        .map(|(tx_id, (spell, tx_outs))| (tx_id, PrevSpell { spell, tx_outs }))
+        .collect();
+    to_py(py, &prev_spells)
+}
+
+/// Check the (normalized) spell is well-formed, given the (hex-encoded) transactions creating its
+/// inputs and references, and the dict of inputs beamed from other chains to their source UTXOs.
+/// Raises `NotWellFormedError` explaining why if it is not.
+#[pyfunction]
+#[pyo3(signature = (spell, prev_txs, tx_ins_beamed_source_utxos = None, mock = false))]
+fn check_well_formed(
+    spell: &Bound<'_, PyAny>,
+    prev_txs: Vec<String>,
+    tx_ins_beamed_source_utxos: Option<&Bound<'_, PyAny>>,
+    mock: bool,
+) -> PyResult<()> {
+    let spell: NormalizedSpell = from_py(spell)?;
+    let prev_txs = txs_from_hex(&prev_txs)?;
+    let tx_ins_beamed_source_utxos: BTreeMap<UtxoId, UtxoId> = tx_ins_beamed_source_utxos
+        .map(from_py)
+        .transpose()?
+        .unwrap_or_default();
+    let prev_spells = charms_client::prev_spells(&prev_txs, SPELL_VK, mock);
+    charms_client::well_formed_detailed(&spell, &prev_spells, &tx_ins_beamed_source_utxos)
+        .map_err(|e| NotWellFormedError::new_err(e.to_string()))
+}
+
+/// Same as `check_well_formed`, but returns `False` instead of raising `NotWellFormedError`.
+#[pyfunction]
+#[pyo3(signature = (spell, prev_txs, tx_ins_beamed_source_utxos = None, mock = false))]
+fn well_formed(
+    py: Python<'_>,
+    spell: &Bound<'_, PyAny>,
+    prev_txs: Vec<String>,
+    tx_ins_beamed_source_utxos: Option<&Bound<'_, PyAny>>,
+    mock: bool,
+) -> PyResult<bool> {
+    match check_well_formed(spell, prev_txs, tx_ins_beamed_source_utxos, mock) {
+        Ok(()) => Ok(true),
+        Err(e) if e.is_instance_of::<NotWellFormedError>(py) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Convert a normalized spell to its human-readable form (as in `charms tx show-spell`).
+#[pyfunction]
+fn denormalize_spell<'py>(
+    py: Python<'py>,
+    spell: &Bound<'py, PyAny>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let spell: NormalizedSpell = from_py(spell)?;
+    let spell =
+        Spell::denormalized(&spell).map_err(|e| NotWellFormedError::new_err(e.to_string()))?;
+    to_py(py, &spell)
+}
+
+/// Charms: extract and verify spells.
+#[pymodule]
+fn charms(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = m.py();
+    m.add("SPELL_VK", SPELL_VK)?;
+    m.add("CharmsError", py.get_type::<CharmsError>())?;
+    m.add("NoSpellError", py.get_type::<NoSpellError>())?;
+    m.add(
+        "MalformedEnvelopeError",
+        py.get_type::<MalformedEnvelopeError>(),
+    )?;
+    m.add(
+        "MockSpellInNonMockModeError",
+        py.get_type::<MockSpellInNonMockModeError>(),
+    )?;
+    m.add(
+        "UnsupportedVersionError",
+        py.get_type::<UnsupportedVersionError>(),
+    )?;
+    m.add(
+        "ProofVerificationError",
+        py.get_type::<ProofVerificationError>(),
+    )?;
+    m.add(
+        "InputOutputMismatchError",
+        py.get_type::<InputOutputMismatchError>(),
+    )?;
+    m.add("DecodeError", py.get_type::<DecodeError>())?;
+    m.add("NotWellFormedError", py.get_type::<NotWellFormedError>())?;
+    m.add_function(wrap_pyfunction!(extract_and_verify_spell, m)?)?;
+    m.add_function(wrap_pyfunction!(prev_spells, m)?)?;
+    m.add_function(wrap_pyfunction!(check_well_formed, m)?)?;
+    m.add_function(wrap_pyfunction!(well_formed, m)?)?;
+    m.add_function(wrap_pyfunction!(denormalize_spell, m)?)?;
+    Ok(())
+}
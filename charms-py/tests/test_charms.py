@@ -0,0 +1,38 @@
+import json
+from pathlib import Path
+
+import pytest
+
+import charms
+
+TX = json.loads((Path(__file__).parents[2] / "charms-lib/test/bitcoin-tx.json").read_text())["bitcoin"]
+
+
+def test_extract_and_verify_spell():
+    spell = charms.extract_and_verify_spell(TX, mock=True)
+    assert spell["mock"]
+    assert spell["tx"]["ins"]
+
+    denormalized = charms.denormalize_spell(spell)
+    assert len(denormalized["outs"]) == len(spell["tx"]["outs"])
+
+
+def test_typed_errors():
+    with pytest.raises(charms.MockSpellInNonMockModeError):
+        charms.extract_and_verify_spell(TX)
+    with pytest.raises(charms.DecodeError):
+        charms.extract_and_verify_spell("not a tx")
+    with pytest.raises(charms.CharmsError):
+        charms.extract_and_verify_spell("not a tx")
+
+
+def test_prev_spells_and_well_formed():
+    prev_spells = charms.prev_spells([TX], mock=True)
+    [(txid, prev_spell)] = prev_spells.items()
+    assert prev_spell["spell"]["mock"]
+    assert prev_spell["tx_outs"] > 0
+
+    spell = charms.extract_and_verify_spell(TX, mock=True)
+    assert not charms.well_formed(spell, [], mock=True)
+    with pytest.raises(charms.NotWellFormedError):
+        charms.check_well_formed(spell, [], mock=True)
@@ -1,18 +1,21 @@
 use crate::is_correct;
-use charms_client::{NormalizedSpell, SpellProverInput};
+use charms_client::{NormalizedSpell, SpellProverInput, spell_checker_vk_bytes};
 use charms_data::util;
 
 pub fn main() {
     // Read an input to the program.
     let input_vec = sp1_zkvm::io::read_vec();
     let input: SpellProverInput = util::read(input_vec.as_slice()).unwrap();
+    let spell_checker_vk = input.spell_checker_vk;
 
     let output = run(input);
 
     eprintln!("about to commit");
 
-    // Commit to the public values of the program.
-    let output_vec = util::write(&output).unwrap();
+    // Commit to the public values of the program: the proof wrapper checks the spell checker VK
+    // (that recursively verified parent spells were verified against) and strips it.
+    let mut output_vec = util::write(&output).unwrap();
+    output_vec.extend(spell_checker_vk_bytes(&spell_checker_vk));
     sp1_zkvm::io::commit_slice(output_vec.as_slice());
 }
 
@@ -23,6 +26,9 @@ pub(crate) fn run(input: SpellProverInput) -> (String, NormalizedSpell) {
         spell,
         tx_ins_beamed_source_utxos,
         app_input,
+        spell_checker_vk,
+        recursive_prev_txids,
+        proven_apps,
     } = input;
 
     // Check the spell that we're proving is correct.
@@ -32,6 +38,9 @@ pub(crate) fn run(input: SpellProverInput) -> (String, NormalizedSpell) {
         app_input,
         &self_spell_vk,
         &tx_ins_beamed_source_utxos,
+        &spell_checker_vk,
+        &recursive_prev_txids,
+        &proven_apps,
     ));
 
     eprintln!("Spell is correct!");
@@ -1,7 +1,13 @@
 pub mod bin;
 
-use charms_client::{NormalizedSpell, tx::Tx};
-use charms_data::{AppInput, Transaction, UtxoId, check, is_simple_transfer};
+use charms_client::{
+    APP_CHECKER_VK, CURRENT_VERSION, NormalizedSpell, app_checker_pv,
+    batch::committed_data_hash,
+    spell_checker_vk_bytes,
+    tx::{EnchantedTx, Tx, extract_and_verify_spell},
+};
+use charms_data::{App, AppInput, Data, Transaction, TxId, UtxoId, util};
+use sp1_zkvm::lib::verify::verify_sp1_proof;
 use std::collections::{BTreeMap, BTreeSet};
 
 /// Check if the spell is correct.
@@ -11,47 +17,90 @@ pub(crate) fn is_correct(
     app_input: Option<AppInput>,
     spell_vk: &String,
     tx_ins_beamed_source_utxos: &BTreeMap<UtxoId, UtxoId>,
+    spell_checker_vk: &[u32; 8],
+    recursive_prev_txids: &BTreeSet<TxId>,
+    proven_apps: &BTreeSet<App>,
 ) -> bool {
-    let prev_spells = charms_client::prev_spells(prev_txs, spell_vk, false);
+    let prev_spells = prev_spells(prev_txs, spell_vk, spell_checker_vk, recursive_prev_txids);
 
-    check!(charms_client::well_formed(
+    let app_runner = charms_app_runner::AppRunner::new(false);
+    let mut run_app_contracts = |tx: &Transaction| {
+        let Some(app_input) = &app_input else {
+            unreachable!("app contracts are only run with app_input");
+        };
+        app_runner.run_all(
+            &app_input.app_binaries,
+            tx,
+            &app_input.app_public_inputs,
+            &app_input.app_private_inputs,
+        )?;
+        Ok(())
+    };
+
+    match charms_client::is_correct_detailed(
         spell,
         &prev_spells,
-        tx_ins_beamed_source_utxos
-    ));
+        tx_ins_beamed_source_utxos,
+        match app_input.is_some() {
+            true => Some(&mut run_app_contracts),
+            false => None,
+        },
+        proven_apps,
+        &verify_app_contract_proof,
+    ) {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("spell is not correct: {}", e);
+            false
+        }
+    }
+}
 
-    let Some(prev_txids) = spell.tx.prev_txids() else {
-        unreachable!("the spell is well formed: tx.ins MUST be Some");
-    };
-    let all_prev_txids: BTreeSet<_> = tx_ins_beamed_source_utxos
-        .values()
-        .map(|u| &u.0)
-        .chain(prev_txids)
-        .collect();
-    check!(all_prev_txids == prev_spells.keys().collect());
-
-    let apps = charms_client::apps(spell);
-
-    let charms_tx = charms_client::to_tx(spell, &prev_spells, tx_ins_beamed_source_utxos);
-    let tx_is_simple_transfer_or_app_contracts_satisfied =
-        apps.iter().all(|app| is_simple_transfer(app, &charms_tx)) && app_input.is_none()
-            || app_input.is_some_and(|app_input| apps_satisfied(&app_input, &charms_tx));
-    check!(tx_is_simple_transfer_or_app_contracts_satisfied);
-
-    true
+/// Extract spells from previous transactions.
+///
+/// Spells of `recursive_prev_txids` are verified via (deferred) recursive verification of their
+/// spell checker proofs, which is much cheaper than verifying their Groth16 proofs.
+fn prev_spells(
+    prev_txs: &[Tx],
+    spell_vk: &str,
+    spell_checker_vk: &[u32; 8],
+    recursive_prev_txids: &BTreeSet<TxId>,
+) -> BTreeMap<TxId, (Option<NormalizedSpell>, usize)> {
+    prev_txs
+        .iter()
+        .map(|tx| {
+            let tx_id = tx.tx_id();
+            let n_spell_opt = match recursive_prev_txids.contains(&tx_id) {
+                true => Some(verify_recursively(tx, spell_vk, spell_checker_vk)),
+                false => extract_and_verify_spell(spell_vk, tx, false).ok(),
+            };
+            (tx_id, (n_spell_opt, tx.tx_outs_len()))
+        })
+        .collect()
 }
 
-fn apps_satisfied(app_input: &AppInput, tx: &Transaction) -> bool {
-    let app_runner = charms_app_runner::AppRunner::new(false);
-    app_runner
-        .run_all(
-            &app_input.app_binaries,
-            &tx,
-            &app_input.app_public_inputs,
-            &app_input.app_private_inputs,
-        )
-        .expect("all apps should run successfully");
-    true
+/// Verify the spell of the transaction by recursively verifying the spell checker proof for it.
+/// The proof itself is supplied to the zkVM by the prover.
+fn verify_recursively(tx: &Tx, spell_vk: &str, spell_checker_vk: &[u32; 8]) -> NormalizedSpell {
+    let (spell, _) = tx
+        .extract_spell()
+        .expect("recursively verified tx should have a spell");
+    assert_eq!(spell.version, CURRENT_VERSION);
+    assert!(!spell.mock);
+
+    let mut public_values = util::write(&(spell_vk, &spell)).unwrap();
+    public_values.extend(spell_checker_vk_bytes(spell_checker_vk));
+    verify_sp1_proof(spell_checker_vk, &committed_data_hash(&public_values).0);
+    spell
+}
+
+/// Verify (recursively) the app checker proof for `app`: its contract is satisfied by the
+/// transaction. The proof itself is supplied to the zkVM by the prover.
+fn verify_app_contract_proof(app: &App, tx: &Transaction, x: &Data) {
+    verify_sp1_proof(
+        &APP_CHECKER_VK,
+        &committed_data_hash(&app_checker_pv(app, tx, x)).0,
+    );
 }
 
 #[cfg(test)]
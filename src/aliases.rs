@@ -0,0 +1,174 @@
+use anyhow::{Result, anyhow, ensure};
+use charms_client::spell::AppKeys;
+use charms_data::{App, UtxoId};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
+
+/// Location of the app alias registry, relative to the home directory.
+/// Can be overridden with `CHARMS_APPS_FILE` environment variable.
+const APPS_FILE: &str = ".charms/apps.yaml";
+
+/// Local registry of named apps, e.g.
+///
+/// ```yaml
+/// t/3d7fe7e4cea6121947af73d70e5119bebd8aa5b7edfe74bfaf6e779a1847bd9b/c975d4e0c292fb95efbda5c13312d6ac1d8b5aeff7f0f1e5578645a2da70ff5f:
+///   name: usdc
+///   metadata:
+///     ticker: USDC
+///     decimals: 6
+/// ```
+///
+/// Named apps get `$name` keys in de-normalized spells (instead of `$0000`-style indices).
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AppAliases(pub BTreeMap<App, AppAlias>);
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AppAlias {
+    /// Name of the app: letters, digits, `_` or `-`, starting with a letter or `_`.
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<TokenMetadata>,
+}
+
+/// Token metadata as defined in CHIP-420 (all fields are optional).
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TokenMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ticker: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decimals: Option<u8>,
+    #[serde(rename = "ref", default, skip_serializing_if = "Option::is_none")]
+    pub reference: Option<UtxoId>,
+    /// Additional fields.
+    #[serde(flatten)]
+    pub other: BTreeMap<String, serde_yaml::Value>,
+}
+
+impl AppAliases {
+    /// Load the registry from `CHARMS_APPS_FILE` (if set) or `~/.charms/apps.yaml`.
+    /// If the file does not exist, the registry is empty.
+    pub fn load() -> Result<Self> {
+        match apps_file() {
+            Some(path) if path.exists() => Self::read(&path),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    /// Read the registry from the YAML (or JSON) file.
+    pub fn read(path: &Path) -> Result<Self> {
+        let file =
+            std::fs::File::open(path).map_err(|e| anyhow!("error opening {:?}: {}", path, e))?;
+        let aliases: Self = serde_yaml::from_reader(file)
+            .map_err(|e| anyhow!("error parsing app aliases {:?}: {}", path, e))?;
+        aliases
+            .validate()
+            .map_err(|e| anyhow!("app aliases {:?}: {}", path, e))?;
+        Ok(aliases)
+    }
+
+    fn validate(&self) -> Result<()> {
+        let mut names = BTreeSet::new();
+        for (app, alias) in &self.0 {
+            ensure!(
+                is_name(&alias.name),
+                "invalid name {:?} of app {}",
+                alias.name,
+                app
+            );
+            ensure!(names.insert(&alias.name), "duplicate name {:?}", alias.name);
+        }
+        Ok(())
+    }
+
+    /// Keys of the named apps in spells: `$name`.
+    pub fn app_keys(&self) -> AppKeys {
+        self.0
+            .iter()
+            .map(|(app, alias)| (app.clone(), format!("${}", alias.name)))
+            .collect()
+    }
+
+    /// Metadata of the app, if it is named and has metadata.
+    pub fn metadata(&self, app: &App) -> Option<&TokenMetadata> {
+        self.0.get(app).and_then(|alias| alias.metadata.as_ref())
+    }
+}
+
+fn apps_file() -> Option<PathBuf> {
+    match std::env::var("CHARMS_APPS_FILE") {
+        Ok(path) => Some(PathBuf::from(path)),
+        Err(_) => std::env::var("HOME")
+            .ok()
+            .map(|home| Path::new(&home).join(APPS_FILE)),
+    }
+}
+
+/// Names must not look like `$0000`-style app indices.
+fn is_name(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const APPS_YAML: &str = r#"
+t/0101010101010101010101010101010101010101010101010101010101010101/0202020202020202020202020202020202020202020202020202020202020202:
+  name: usdc
+  metadata:
+    ticker: USDC
+    decimals: 6
+    website: https://example.com
+n/0101010101010101010101010101010101010101010101010101010101010101/0202020202020202020202020202020202020202020202020202020202020202:
+  name: usdc-ref
+"#;
+
+    #[test]
+    fn parses_aliases() {
+        let aliases: AppAliases = serde_yaml::from_str(APPS_YAML).unwrap();
+        aliases.validate().unwrap();
+
+        let token: App = serde_yaml::from_str(
+            "t/0101010101010101010101010101010101010101010101010101010101010101/0202020202020202020202020202020202020202020202020202020202020202",
+        )
+        .unwrap();
+        assert_eq!(aliases.app_keys()[&token], "$usdc");
+        let metadata = aliases.metadata(&token).unwrap();
+        assert_eq!(metadata.ticker.as_deref(), Some("USDC"));
+        assert_eq!(metadata.decimals, Some(6));
+        assert_eq!(
+            metadata.other["website"],
+            serde_yaml::Value::from("https://example.com")
+        );
+        assert_eq!(aliases.app_keys().len(), 2);
+    }
+
+    #[test]
+    fn rejects_invalid_names() {
+        let aliases: AppAliases =
+            serde_yaml::from_str(&APPS_YAML.replace("usdc-ref", "usdc")).unwrap();
+        assert!(aliases.validate().is_err(), "duplicate name");
+
+        let aliases: AppAliases =
+            serde_yaml::from_str(&APPS_YAML.replace("usdc-ref", "0001")).unwrap();
+        assert!(aliases.validate().is_err(), "index-like name");
+    }
+}
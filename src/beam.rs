@@ -0,0 +1,199 @@
+use crate::{
+    spell::{Input, KeyedCharms, Output, Spell},
+    tx, utils,
+};
+use anyhow::{anyhow, bail, ensure};
+use charms_client::{
+    tx::{EnchantedTx, Tx},
+    utxo_id_hash,
+};
+use charms_data::UtxoId;
+use charms_lib::SPELL_VK;
+use std::collections::BTreeMap;
+
+/// Mark output `out_index` of the spell to be beamed to `dest_utxo_id`: a placeholder UTXO,
+/// typically on another chain.
+pub fn beam_out(spell: &mut Spell, out_index: usize, dest_utxo_id: &UtxoId) -> anyhow::Result<()> {
+    ensure!(
+        spell
+            .ins
+            .iter()
+            .all(|input| input.utxo_id.as_ref() != Some(dest_utxo_id)),
+        "beam destination {} is spent by the spell itself",
+        dest_utxo_id
+    );
+    let Some(output) = spell.outs.get_mut(out_index) else {
+        bail!("spell has no output {}", out_index)
+    };
+    ensure!(
+        output
+            .charms
+            .as_ref()
+            .is_some_and(|charms| !charms.is_empty()),
+        "output {} has no charms to beam",
+        out_index
+    );
+    output.beam_to = Some(utxo_id_hash(dest_utxo_id));
+    Ok(())
+}
+
+/// Check that `dest_tx` can host the beam destination `dest_utxo_id`: it must create the UTXO and
+/// must not carry a spell.
+pub fn check_beam_destination(
+    dest_tx: &Tx,
+    dest_utxo_id: &UtxoId,
+    mock: bool,
+) -> anyhow::Result<()> {
+    ensure!(
+        dest_tx.tx_id() == dest_utxo_id.0,
+        "beam destination {} is not created by the supplied transaction",
+        dest_utxo_id
+    );
+    ensure!(
+        (dest_utxo_id.1 as usize) < dest_tx.tx_outs_len(),
+        "beam destination transaction has no output {}",
+        dest_utxo_id.1
+    );
+    ensure!(
+        tx::norm_spell(dest_tx, mock).is_none(),
+        "beam destination transaction carries a spell: its outputs cannot be beam placeholders"
+    );
+    Ok(())
+}
+
+/// Build the spell claiming the charms beamed from `source_utxo_id` (created by `source_tx`) to
+/// `dest_utxo_id` (created by `dest_tx`).
+///
+/// The claiming spell spends the destination UTXO and puts all the beamed charms into its single
+/// output. Its address (and amount) is left to be filled in by the caller.
+pub fn claim_spell(
+    source_tx: &Tx,
+    source_utxo_id: &UtxoId,
+    dest_tx: &Tx,
+    dest_utxo_id: &UtxoId,
+    mock: bool,
+) -> anyhow::Result<Spell> {
+    ensure!(
+        source_tx.tx_id() == source_utxo_id.0,
+        "beam source {} is not created by the supplied transaction",
+        source_utxo_id
+    );
+    check_beam_destination(dest_tx, dest_utxo_id, mock)?;
+
+    let source_spell = tx::norm_spell(source_tx, mock)
+        .ok_or(anyhow!("beam source transaction has no correct spell"))?;
+    let source_index = source_utxo_id.1;
+    let beamed_to = source_spell
+        .tx
+        .beamed_outs
+        .as_ref()
+        .and_then(|beamed_outs| beamed_outs.get(&source_index))
+        .ok_or(anyhow!("beam source {} is not beamed out", source_utxo_id))?;
+    ensure!(
+        beamed_to == &utxo_id_hash(dest_utxo_id),
+        "beam source {} is beamed to a different destination than {}",
+        source_utxo_id,
+        dest_utxo_id
+    );
+    let n_charms = source_spell
+        .tx
+        .outs
+        .get(source_index as usize)
+        .ok_or(anyhow!("beam source spell has no output {}", source_index))?;
+
+    let charms = charms_client::charms(&source_spell, n_charms);
+    let apps: BTreeMap<String, _> = (0..)
+        .zip(charms.keys())
+        .map(|(i, app)| (utils::str_index(&i), app.clone()))
+        .collect();
+    let keyed_charms: KeyedCharms = (0..)
+        .zip(charms.values())
+        .map(|(i, data)| (utils::str_index(&i), data.clone()))
+        .collect();
+
+    let spell = Spell {
+        apps,
+        ins: vec![Input {
+            utxo_id: Some(dest_utxo_id.clone()),
+            charms: Some(keyed_charms.clone()),
+            beamed_from: Some(source_utxo_id.clone()),
+        }],
+        outs: vec![Output {
+            address: None,
+            amount: None,
+            charms: Some(keyed_charms),
+            beam_to: None,
+        }],
+        ..Spell::new()
+    };
+
+    let (norm_spell, _, tx_ins_beamed_source_utxos) = spell.normalized()?;
+    let prev_txs = vec![source_tx.clone(), dest_tx.clone()];
+    let prev_spells =
+        charms_client::prev_spells_cached(&prev_txs, SPELL_VK, mock, utils::spell_cache());
+    charms_client::well_formed_detailed(&norm_spell, &prev_spells, &tx_ins_beamed_source_utxos)
+        .map_err(|e| anyhow!("claiming spell is not well-formed: {}", e))?;
+
+    Ok(spell)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use charms_data::{App, B32, Data, TxId};
+
+    fn spell() -> Spell {
+        Spell {
+            apps: BTreeMap::from([(
+                "$0000".to_string(),
+                App {
+                    tag: 't',
+                    identity: B32([1; 32]),
+                    vk: B32([2; 32]),
+                },
+            )]),
+            ins: vec![Input {
+                utxo_id: Some(UtxoId(TxId([1; 32]), 0)),
+                charms: None,
+                beamed_from: None,
+            }],
+            outs: vec![
+                Output {
+                    address: None,
+                    amount: None,
+                    charms: Some(KeyedCharms::from([("$0000".to_string(), Data::from(&10))])),
+                    beam_to: None,
+                },
+                Output {
+                    address: None,
+                    amount: None,
+                    charms: None,
+                    beam_to: None,
+                },
+            ],
+            ..Spell::new()
+        }
+    }
+
+    #[test]
+    fn beam_out_sets_destination_hash() {
+        let dest = UtxoId(TxId([9; 32]), 1);
+        let mut spell = spell();
+        beam_out(&mut spell, 0, &dest).unwrap();
+        assert_eq!(spell.outs[0].beam_to, Some(utxo_id_hash(&dest)));
+
+        let (norm_spell, _, _) = spell.normalized().unwrap();
+        assert_eq!(
+            norm_spell.tx.beamed_outs,
+            Some(BTreeMap::from([(0, utxo_id_hash(&dest))]))
+        );
+    }
+
+    #[test]
+    fn beam_out_rejects_bad_outputs() {
+        let mut spell = spell();
+        assert!(beam_out(&mut spell, 1, &UtxoId(TxId([9; 32]), 1)).is_err());
+        assert!(beam_out(&mut spell, 2, &UtxoId(TxId([9; 32]), 1)).is_err());
+        assert!(beam_out(&mut spell, 0, &UtxoId(TxId([1; 32]), 0)).is_err());
+    }
+}
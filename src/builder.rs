@@ -0,0 +1,264 @@
+use crate::spell::{Input, KeyedCharms, Output, Spell, ensure_no_zero_amounts};
+use anyhow::{Result, ensure};
+use charms_client::CURRENT_VERSION;
+use charms_data::{App, Data, UtxoId};
+use std::{collections::BTreeMap, marker::PhantomData};
+
+/// Invariant lifetime tying [`AppHandle`]s to the [`SpellBuilder`] that created them: each
+/// builder (see [`SpellBuilder::scope`]) has its own brand.
+type Brand<'b> = PhantomData<fn(&'b ()) -> &'b ()>;
+
+/// Handle of an app registered with [`SpellBuilder::app`].
+///
+/// Charms and app arguments refer to apps by their handles (instead of `$KEY` strings), so they
+/// can only refer to apps actually registered with the builder. Using a handle with another
+/// builder doesn't compile.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct AppHandle<'b> {
+    index: usize,
+    brand: Brand<'b>,
+}
+
+/// Builder of [`Spell`]s: register apps (getting their [`AppHandle`]s), add inputs and outputs
+/// with charms of these apps, set app arguments and [`build`](Self::build) the spell.
+///
+/// ```compile_fail
+/// use charms::builder::SpellBuilder;
+/// use charms_data::App;
+///
+/// fn foreign_handle(app: App) {
+///     SpellBuilder::scope(|mut builder| {
+///         let handle = builder.app(app.clone());
+///         SpellBuilder::scope(|mut other_builder| {
+///             other_builder.public_args(handle, Default::default());
+///         });
+///     });
+/// }
+/// ```
+#[derive(Debug)]
+pub struct SpellBuilder<'b> {
+    apps: Vec<App>,
+    public_args: BTreeMap<AppHandle<'b>, Data>,
+    private_args: BTreeMap<AppHandle<'b>, Data>,
+    ins: Vec<(UtxoId, HandleCharms<'b>)>,
+    refs: Vec<(UtxoId, HandleCharms<'b>)>,
+    outs: Vec<(String, u64, HandleCharms<'b>)>,
+    brand: Brand<'b>,
+}
+
+/// Charms (data by app handle) of an input or output.
+type HandleCharms<'b> = Vec<(AppHandle<'b>, Data)>;
+
+impl SpellBuilder<'_> {
+    /// Run `f` with a new builder of a spell of the current protocol version. App handles of the
+    /// builder can't outlive `f`.
+    pub fn scope<R>(f: impl for<'b> FnOnce(SpellBuilder<'b>) -> R) -> R {
+        f(SpellBuilder {
+            apps: vec![],
+            public_args: BTreeMap::new(),
+            private_args: BTreeMap::new(),
+            ins: vec![],
+            refs: vec![],
+            outs: vec![],
+            brand: PhantomData,
+        })
+    }
+}
+
+impl<'b> SpellBuilder<'b> {
+    /// Register the app with the spell. Registering the same app again returns the same handle.
+    pub fn app(&mut self, app: App) -> AppHandle<'b> {
+        let index = match self.apps.iter().position(|a| a == &app) {
+            Some(index) => index,
+            None => {
+                self.apps.push(app);
+                self.apps.len() - 1
+            }
+        };
+        AppHandle {
+            index,
+            brand: PhantomData,
+        }
+    }
+
+    /// Set the public arguments of the app for this spell.
+    pub fn public_args(&mut self, app: AppHandle<'b>, args: Data) -> &mut Self {
+        self.public_args.insert(app, args);
+        self
+    }
+
+    /// Set the private arguments of the app for this spell.
+    pub fn private_args(&mut self, app: AppHandle<'b>, args: Data) -> &mut Self {
+        self.private_args.insert(app, args);
+        self
+    }
+
+    /// Spend the UTXO, holding the `charms`.
+    pub fn input(
+        &mut self,
+        utxo_id: UtxoId,
+        charms: impl IntoIterator<Item = (AppHandle<'b>, Data)>,
+    ) -> &mut Self {
+        self.ins.push((utxo_id, charms.into_iter().collect()));
+        self
+    }
+
+    /// Reference (without spending) the UTXO, holding the `charms`.
+    pub fn reference(
+        &mut self,
+        utxo_id: UtxoId,
+        charms: impl IntoIterator<Item = (AppHandle<'b>, Data)>,
+    ) -> &mut Self {
+        self.refs.push((utxo_id, charms.into_iter().collect()));
+        self
+    }
+
+    /// Create an output to `address` with `amount` (in sats for Bitcoin), holding the `charms`.
+    pub fn output(
+        &mut self,
+        address: impl Into<String>,
+        amount: u64,
+        charms: impl IntoIterator<Item = (AppHandle<'b>, Data)>,
+    ) -> &mut Self {
+        self.outs
+            .push((address.into(), amount, charms.into_iter().collect()));
+        self
+    }
+
+    /// Build the spell, checking that it can be normalized (as it is when proving) and has no
+    /// zero token amounts.
+    pub fn build(&self) -> Result<Spell> {
+        ensure!(!self.ins.is_empty(), "spell has no inputs");
+        ensure!(!self.outs.is_empty(), "spell has no outputs");
+
+        let input = |(utxo_id, charms): &(UtxoId, HandleCharms<'b>)| -> Result<Input> {
+            Ok(Input {
+                utxo_id: Some(utxo_id.clone()),
+                charms: Some(self.keyed_charms(charms)?),
+                beamed_from: None,
+            })
+        };
+        let spell = Spell {
+            version: CURRENT_VERSION,
+            apps: (0..self.apps.len())
+                .map(|index| (key(index), self.apps[index].clone()))
+                .collect(),
+            public_args: Some(keyed_args(&self.public_args)),
+            private_args: Some(keyed_args(&self.private_args)),
+            ins: self.ins.iter().map(input).collect::<Result<_>>()?,
+            refs: match self.refs.is_empty() {
+                true => None,
+                false => Some(self.refs.iter().map(input).collect::<Result<_>>()?),
+            },
+            outs: self
+                .outs
+                .iter()
+                .map(|(address, amount, charms)| {
+                    Ok(Output {
+                        address: Some(address.clone()),
+                        amount: Some(*amount),
+                        charms: Some(self.keyed_charms(charms)?),
+                        beam_to: None,
+                    })
+                })
+                .collect::<Result<_>>()?,
+        };
+
+        let (norm_spell, _, _) = spell.normalized()?;
+        ensure_no_zero_amounts(&norm_spell)?;
+        Ok(spell)
+    }
+
+    fn keyed_charms(&self, charms: &[(AppHandle<'b>, Data)]) -> Result<KeyedCharms> {
+        let mut keyed_charms = KeyedCharms::new();
+        for (app, data) in charms {
+            ensure!(
+                keyed_charms.insert(key(app.index), data.clone()).is_none(),
+                "duplicate charms of app {}",
+                self.apps[app.index]
+            );
+        }
+        Ok(keyed_charms)
+    }
+}
+
+fn keyed_args(args: &BTreeMap<AppHandle, Data>) -> BTreeMap<String, Data> {
+    args.iter()
+        .map(|(app, data)| (key(app.index), data.clone()))
+        .collect()
+}
+
+/// `$KEY` of the app with the index.
+fn key(index: usize) -> String {
+    format!("${:02}", index)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use charms_client::test_utils::{app_n, utxo_id};
+    use charms_data::{NFT, TOKEN};
+
+    #[test]
+    fn builds_spell() {
+        let spell = SpellBuilder::scope(|mut builder| {
+            let nft = builder.app(app_n(NFT, 1));
+            let token = builder.app(app_n(TOKEN, 1));
+            assert_eq!(builder.app(app_n(NFT, 1)), nft);
+
+            builder
+                .public_args(token, Data::from(&"mint"))
+                .input(utxo_id(1, 0), [(nft, Data::from(&"nft"))])
+                .output("addr0", 1000, [(nft, Data::from(&"nft"))])
+                .output("addr1", 1000, [(token, Data::from(&100u64))]);
+            builder.build().unwrap()
+        });
+
+        assert_eq!(spell.apps["$00"], app_n(NFT, 1));
+        assert_eq!(spell.apps["$01"], app_n(TOKEN, 1));
+        assert_eq!(
+            spell.public_args.as_ref().unwrap()["$01"],
+            Data::from(&"mint")
+        );
+        assert_eq!(spell.outs[1].amount, Some(1000));
+        assert_eq!(
+            spell.outs[1].charms.as_ref().unwrap()["$01"],
+            Data::from(&100u64)
+        );
+        assert_eq!(spell.ins[0].utxo_id, Some(utxo_id(1, 0)));
+    }
+
+    #[test]
+    fn rejects_invalid_spells() {
+        SpellBuilder::scope(|mut builder| {
+            let token = builder.app(app_n(TOKEN, 1));
+            builder.output("addr0", 1000, [(token, Data::from(&100u64))]);
+            assert!(builder.build().is_err(), "no inputs");
+
+            builder.input(utxo_id(1, 0), []);
+            builder.build().unwrap();
+
+            builder.output("addr1", 1000, [(token, Data::from(&0u64))]);
+            assert!(builder.build().is_err(), "zero token amount");
+        });
+
+        SpellBuilder::scope(|mut builder| {
+            let token = builder.app(app_n(TOKEN, 1));
+            builder
+                .input(utxo_id(1, 0), [])
+                .input(utxo_id(1, 0), [])
+                .output("addr0", 1000, [(token, Data::from(&100u64))]);
+            assert!(builder.build().is_err(), "duplicate inputs");
+        });
+
+        SpellBuilder::scope(|mut builder| {
+            let token = builder.app(app_n(TOKEN, 1));
+            builder.input(utxo_id(1, 0), []).output(
+                "addr0",
+                1000,
+                [(token, Data::from(&1u64)), (token, Data::from(&2u64))],
+            );
+            assert!(builder.build().is_err(), "duplicate charms");
+        });
+    }
+}
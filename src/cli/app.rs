@@ -1,7 +1,8 @@
-use crate::spell::Spell;
+use crate::{cli, cli::SpellTemplateParams, spell::from_hex_txs, utils};
 use anyhow::{Result, anyhow, ensure};
 use charms_app_runner::AppRunner;
 use charms_data::{B32, Data};
+use charms_lib::SPELL_VK;
 use sha2::{Digest, Sha256};
 use std::{
     collections::BTreeMap,
@@ -80,7 +81,13 @@ pub fn vk(path: Option<PathBuf>) -> Result<()> {
     Ok(())
 }
 
-pub fn run(spell: PathBuf, path: Option<PathBuf>) -> Result<()> {
+pub fn run(
+    spell: PathBuf,
+    template: SpellTemplateParams,
+    prev_txs: Vec<String>,
+    mock: bool,
+    path: Option<PathBuf>,
+) -> Result<()> {
     let binary = match path {
         Some(path) => fs::read(path)?,
         None => {
@@ -91,9 +98,13 @@ pub fn run(spell: PathBuf, path: Option<PathBuf>) -> Result<()> {
     let app_runner = AppRunner::new(true);
     let vk = app_runner.vk(&binary);
 
-    let spell: Spell = serde_yaml::from_slice(
-        &fs::read(&spell).map_err(|e| anyhow!("error reading {:?}: {}", &spell, e))?,
-    )?;
+    let mut spell = cli::read_spell(&spell, template)?;
+    if !prev_txs.is_empty() {
+        let prev_txs = from_hex_txs(&prev_txs)?;
+        let prev_spells =
+            charms_client::prev_spells_cached(&prev_txs, SPELL_VK, mock, utils::spell_cache());
+        spell.enrich_from_prev_txs(&prev_spells)?;
+    }
     let tx = spell.to_tx()?;
 
     let public_inputs = spell.public_args.unwrap_or_default();
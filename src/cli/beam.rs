@@ -0,0 +1,52 @@
+use crate::{
+    beam,
+    cli::{self, BeamClaimParams, BeamOutParams},
+    spell::Spell,
+};
+use anyhow::Result;
+use charms_client::tx::Tx;
+use charms_data::UtxoId;
+
+pub fn beam_out(params: BeamOutParams) -> Result<()> {
+    let BeamOutParams {
+        spell,
+        output,
+        dest_utxo,
+        dest_tx,
+        json,
+        mock,
+    } = params;
+
+    let dest_utxo_id = UtxoId::from_str(&dest_utxo)?;
+    if let Some(dest_tx) = dest_tx {
+        beam::check_beam_destination(&Tx::from_hex(&dest_tx)?, &dest_utxo_id, mock)?;
+    }
+
+    let mut spell: Spell = serde_yaml::from_slice(&std::fs::read(spell)?)?;
+    beam::beam_out(&mut spell, output, &dest_utxo_id)?;
+
+    cli::print_output(&spell, json)
+}
+
+pub fn beam_claim(params: BeamClaimParams) -> Result<()> {
+    let BeamClaimParams {
+        source_tx,
+        source_utxo,
+        dest_tx,
+        dest_utxo,
+        address,
+        json,
+        mock,
+    } = params;
+
+    let mut spell = beam::claim_spell(
+        &Tx::from_hex(&source_tx)?,
+        &UtxoId::from_str(&source_utxo)?,
+        &Tx::from_hex(&dest_tx)?,
+        &UtxoId::from_str(&dest_utxo)?,
+        mock,
+    )?;
+    spell.outs[0].address = address;
+
+    cli::print_output(&spell, json)
+}
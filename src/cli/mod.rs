@@ -1,4 +1,5 @@
 pub mod app;
+pub mod beam;
 pub mod server;
 pub mod spell;
 pub mod tx;
@@ -10,7 +11,9 @@ use crate::{
         spell::{Check, Prove, SpellCli},
         wallet::{List, WalletCli},
     },
-    spell::{CharmsFee, MockProver, ProveSpellTx, ProveSpellTxImpl},
+    spell::{CharmsFee, MockProver, ProveSpellTx, ProveSpellTxImpl, Spell},
+    template,
+    template::Vars,
     utils,
     utils::BoxedSP1Prover,
 };
@@ -21,12 +24,19 @@ use crate::{
 };
 use bitcoin::{Address, Network};
 use charms_app_runner::AppRunner;
+use charms_client::tx::ProofSystem;
 use charms_data::check;
 use clap::{Args, CommandFactory, Parser, Subcommand};
 use clap_complete::{Shell, generate};
 use serde::Serialize;
 use sp1_sdk::{CpuProver, NetworkProver, ProverClient, install::try_install_circuit_artifacts};
-use std::{io, net::IpAddr, path::PathBuf, str::FromStr, sync::Arc};
+use std::{
+    io,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+};
 
 pub const BITCOIN: &str = "bitcoin";
 pub const CARDANO: &str = "cardano";
@@ -60,6 +70,12 @@ pub enum Commands {
         command: SpellCommands,
     },
 
+    /// Beam charms between chains.
+    Beam {
+        #[command(subcommand)]
+        command: BeamCommands,
+    },
+
     /// Work with underlying blockchain transactions.
     Tx {
         #[command(subcommand)]
@@ -99,6 +115,9 @@ pub struct SpellProveParams {
     #[arg(long, default_value = "/dev/stdin")]
     spell: PathBuf,
 
+    #[command(flatten)]
+    template: SpellTemplateParams,
+
     /// Pre-requisite transactions (hex-encoded) separated by commas (`,`).
     /// These are the transactions that create the UTXOs that the `tx` (and the spell) spends.
     /// If the spell has any reference UTXOs, the transactions creating them must also be included.
@@ -131,17 +150,49 @@ pub struct SpellProveParams {
     #[arg(long, default_value = "bitcoin")]
     chain: String,
 
+    /// SNARK proof system of the spell proof: `groth16` or `plonk` (no circuit-specific trusted
+    /// setup, larger proof).
+    #[arg(long, default_value = "groth16")]
+    proof_system: ProofSystem,
+
     /// Is mock mode enabled?
     #[arg(long, default_value = "false", hide_env = true)]
     mock: bool,
 }
 
+/// Variables for `${...}` placeholders in the spell (see [`crate::template::render`]).
+#[derive(Args)]
+pub struct SpellTemplateParams {
+    /// Render the spell as a template. Implied by `--var` and `--vars`: otherwise, the spell is
+    /// read as is.
+    #[arg(long)]
+    template: bool,
+
+    /// Spell template variable: `name=value`. Can be repeated.
+    #[arg(long = "var", value_parser = template::parse_var)]
+    vars: Vec<(String, String)>,
+
+    /// YAML files with spell template variables (maps of names to values) separated by commas
+    /// (`,`). Variables set with `--var` take precedence.
+    #[arg(long = "vars", value_delimiter = ',')]
+    vars_files: Vec<PathBuf>,
+}
+
+impl SpellTemplateParams {
+    fn is_template(&self) -> bool {
+        self.template || !self.vars.is_empty() || !self.vars_files.is_empty()
+    }
+}
+
 #[derive(Args)]
 pub struct SpellCheckParams {
     /// Path to spell source file (YAML/JSON).
     #[arg(long, default_value = "/dev/stdin")]
     spell: PathBuf,
 
+    #[command(flatten)]
+    template: SpellTemplateParams,
+
     /// Paths to the apps' Wasm binaries.
     #[arg(long, value_delimiter = ',')]
     app_bins: Vec<PathBuf>,
@@ -157,6 +208,83 @@ pub struct SpellCheckParams {
     mock: bool,
 }
 
+#[derive(Args)]
+pub struct SpellExecuteParams {
+    /// Path to spell source file (YAML/JSON).
+    #[arg(long, default_value = "/dev/stdin")]
+    spell: PathBuf,
+
+    #[command(flatten)]
+    template: SpellTemplateParams,
+
+    /// Paths to the apps' Wasm binaries.
+    #[arg(long, value_delimiter = ',')]
+    app_bins: Vec<PathBuf>,
+
+    /// Pre-requisite transactions (hex-encoded) separated by commas (`,`).
+    /// These are the transactions that create the UTXOs that the `tx` (and the spell) spends.
+    /// If the spell has any reference UTXOs, the transactions creating them must also be included.
+    #[arg(long, value_delimiter = ',')]
+    prev_txs: Vec<String>,
+}
+
+#[derive(Args)]
+pub struct SpellLintParams {
+    /// Path to spell source file (YAML/JSON).
+    #[arg(long, default_value = "/dev/stdin")]
+    spell: PathBuf,
+
+    #[command(flatten)]
+    template: SpellTemplateParams,
+
+    /// Paths to the apps' Wasm binaries.
+    /// Charms of apps without binaries must only be transferred (e.g. token amounts must balance).
+    #[arg(long, value_delimiter = ',')]
+    app_bins: Vec<PathBuf>,
+
+    /// Pre-requisite transactions (hex-encoded) separated by commas (`,`).
+    /// These are the transactions that create the UTXOs that the `tx` (and the spell) spends.
+    /// If the spell has any reference UTXOs, the transactions creating them must also be included.
+    #[arg(long, value_delimiter = ',')]
+    prev_txs: Vec<String>,
+
+    /// Address to send the change to: output addresses must be on its network.
+    #[arg(long)]
+    change_address: Option<String>,
+
+    /// Output in JSON format (default is YAML).
+    #[arg(long)]
+    json: bool,
+
+    /// Is mock mode enabled?
+    #[arg(long, default_value = "false", hide_env = true)]
+    mock: bool,
+}
+
+#[derive(Args)]
+pub struct SpellExplainParams {
+    /// Path to spell source file (YAML/JSON), e.g. the output of `charms tx show-spell`.
+    #[arg(long, default_value = "/dev/stdin")]
+    spell: PathBuf,
+
+    #[command(flatten)]
+    template: SpellTemplateParams,
+
+    /// Pre-requisite transactions (hex-encoded) separated by commas (`,`).
+    /// These are the transactions that create the UTXOs that the `tx` (and the spell) spends.
+    /// If the spell has any reference UTXOs, the transactions creating them must also be included.
+    #[arg(long, value_delimiter = ',')]
+    prev_txs: Vec<String>,
+
+    /// Output in JSON format (default is human-readable text).
+    #[arg(long)]
+    json: bool,
+
+    /// Is mock mode enabled?
+    #[arg(long, default_value = "false", hide_env = true)]
+    mock: bool,
+}
+
 #[derive(Args)]
 pub struct SpellVkParams {
     /// Is mock mode enabled?
@@ -164,14 +292,112 @@ pub struct SpellVkParams {
     mock: bool,
 }
 
+#[derive(Args)]
+pub struct SpellUpgradeParams {
+    /// Path to spell source file (YAML/JSON).
+    #[arg(long, default_value = "/dev/stdin")]
+    spell: PathBuf,
+
+    /// Output in JSON format (default is YAML).
+    #[arg(long)]
+    json: bool,
+}
+
 #[derive(Subcommand)]
 pub enum SpellCommands {
     /// Check the spell is correct.
     Check(#[command(flatten)] SpellCheckParams),
     /// Prove the spell is correct.
     Prove(#[command(flatten)] SpellProveParams),
+    /// Run the spell checker in the SP1 executor (without proving): print the result and the
+    /// number of cycles spent in JSON.
+    Execute(#[command(flatten)] SpellExecuteParams),
+    /// Check the spell for common mistakes before proving (without running app contracts).
+    /// Prints all issues found and fails if any of them is an error.
+    Lint(#[command(flatten)] SpellLintParams),
+    /// Explain the spell's effects per app: tokens minted or burned, NFTs created or updated,
+    /// charms moved from input UTXOs to outputs, and beams.
+    Explain(#[command(flatten)] SpellExplainParams),
+    /// Estimate the transaction sizes, network fees, charms fee and change for proving the spell
+    /// (without proving it), and whether the funding UTXO is sufficient. Prints the estimate in
+    /// JSON. Bitcoin only. Assumes the spell is proven on its own, not in a batch.
+    Estimate(#[command(flatten)] SpellProveParams),
     /// Print the current protocol version and spell VK (verification key) in JSON.
     Vk(#[command(flatten)] SpellVkParams),
+    /// Upgrade the spell (version 6 or newer) to the current protocol version.
+    /// Prints the upgraded spell to stdout and the changes made to stderr.
+    Upgrade(#[command(flatten)] SpellUpgradeParams),
+}
+
+#[derive(Args)]
+pub struct BeamOutParams {
+    /// Path to spell source file (YAML/JSON).
+    #[arg(long, default_value = "/dev/stdin")]
+    spell: PathBuf,
+
+    /// Index of the spell output to beam.
+    #[arg(long)]
+    output: usize,
+
+    /// Destination UTXO (txid:vout), typically on another chain.
+    /// Its transaction must not carry a spell.
+    #[arg(long)]
+    dest_utxo: String,
+
+    /// Transaction (hex-encoded) creating the destination UTXO.
+    /// If provided, it is checked to be a valid beam destination.
+    #[arg(long)]
+    dest_tx: Option<String>,
+
+    /// Output in JSON format (default is YAML).
+    #[arg(long)]
+    json: bool,
+
+    /// Is mock mode enabled?
+    #[arg(long, default_value = "false", hide_env = true)]
+    mock: bool,
+}
+
+#[derive(Args)]
+pub struct BeamClaimParams {
+    /// Transaction (hex-encoded) that has beamed the charms out.
+    #[arg(long)]
+    source_tx: String,
+
+    /// Beamed-out UTXO (txid:vout) created by the source transaction.
+    #[arg(long)]
+    source_utxo: String,
+
+    /// Transaction (hex-encoded) creating the destination UTXO.
+    #[arg(long)]
+    dest_tx: String,
+
+    /// Destination UTXO (txid:vout) the charms have been beamed to.
+    #[arg(long)]
+    dest_utxo: String,
+
+    /// Address to send the claimed charms to.
+    #[arg(long)]
+    address: Option<String>,
+
+    /// Output in JSON format (default is YAML).
+    #[arg(long)]
+    json: bool,
+
+    /// Is mock mode enabled?
+    #[arg(long, default_value = "false", hide_env = true)]
+    mock: bool,
+}
+
+#[derive(Subcommand)]
+pub enum BeamCommands {
+    /// Beam charms of a spell output to a destination UTXO.
+    /// Prints the updated spell: prove it with `charms spell prove`.
+    Out(#[command(flatten)] BeamOutParams),
+    /// Create the spell claiming beamed charms at the destination UTXO.
+    /// Prove it with `charms spell prove`, supplying both source and destination transactions
+    /// as `--prev-txs`.
+    Claim(#[command(flatten)] BeamClaimParams),
 }
 
 #[derive(Args)]
@@ -196,6 +422,8 @@ pub struct ShowSpellParams {
 pub enum TxCommands {
     /// Show the spell in a transaction. If the transaction has a spell and its valid proof, it
     /// will be printed to stdout.
+    /// Apps named in the app alias registry (`~/.charms/apps.yaml` or `CHARMS_APPS_FILE`) are
+    /// shown with `$name` keys.
     ShowSpell(#[command(flatten)] ShowSpellParams),
 }
 
@@ -222,6 +450,19 @@ pub enum AppCommands {
         #[arg(long, default_value = "/dev/stdin")]
         spell: PathBuf,
 
+        #[command(flatten)]
+        template: SpellTemplateParams,
+
+        /// Pre-requisite transactions (hex-encoded) separated by commas (`,`).
+        /// If provided, charms of the spell's inputs and references are filled in (or checked)
+        /// from the spells of these transactions.
+        #[arg(long, value_delimiter = ',')]
+        prev_txs: Vec<String>,
+
+        /// Is mock mode enabled?
+        #[arg(long, default_value = "false", hide_env = true)]
+        mock: bool,
+
         /// Path to the app's Wasm binary.
         path: Option<PathBuf>,
     },
@@ -265,9 +506,18 @@ pub async fn run() -> anyhow::Result<()> {
             match command {
                 SpellCommands::Check(params) => spell_cli.check(params),
                 SpellCommands::Prove(params) => spell_cli.prove(params).await,
+                SpellCommands::Execute(params) => spell_cli.execute(params),
+                SpellCommands::Lint(params) => spell_cli.lint(params),
+                SpellCommands::Explain(params) => spell::explain(params),
+                SpellCommands::Estimate(params) => spell_cli.estimate(params),
                 SpellCommands::Vk(params) => spell_cli.print_vk(params.mock),
+                SpellCommands::Upgrade(params) => spell::upgrade(params),
             }
         }
+        Commands::Beam { command } => match command {
+            BeamCommands::Out(params) => beam::beam_out(params),
+            BeamCommands::Claim(params) => beam::beam_claim(params),
+        },
         Commands::Tx { command } => match command {
             TxCommands::ShowSpell(params) => tx::tx_show_spell(params),
         },
@@ -275,7 +525,13 @@ pub async fn run() -> anyhow::Result<()> {
             AppCommands::New { name } => app::new(&name),
             AppCommands::Vk { path } => app::vk(path),
             AppCommands::Build => app::build(),
-            AppCommands::Run { spell, path } => app::run(spell, path),
+            AppCommands::Run {
+                spell,
+                template,
+                prev_txs,
+                mock,
+                path,
+            } => app::run(spell, template, prev_txs, mock, path),
         },
         Commands::Wallet { command } => {
             let wallet_cli = wallet_cli();
@@ -287,6 +543,7 @@ pub async fn run() -> anyhow::Result<()> {
         Commands::Utils { command } => match command {
             UtilsCommands::InstallCircuitFiles => {
                 let _ = try_install_circuit_artifacts("groth16");
+                let _ = try_install_circuit_artifacts("plonk");
                 Ok(())
             }
         },
@@ -425,6 +682,25 @@ fn generate_completions(shell: Shell) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Read the spell from `path`. If it is a template (see [`SpellTemplateParams`]), render it with
+/// the variables from `template`.
+fn read_spell(path: &Path, template: SpellTemplateParams) -> anyhow::Result<Spell> {
+    if !template.is_template() {
+        let spell =
+            std::fs::read(path).map_err(|e| anyhow::anyhow!("error reading {:?}: {}", path, e))?;
+        return Ok(serde_yaml::from_slice(&spell)?);
+    }
+
+    let mut vars = Vars::new();
+    for vars_file in template.vars_files.iter() {
+        vars.extend(template::read_vars_file(vars_file)?);
+    }
+    vars.extend(template.vars);
+
+    let spell = template::render_file(path, &vars)?;
+    Ok(serde_yaml::from_str(&spell)?)
+}
+
 fn print_output<T: Serialize>(output: &T, json: bool) -> anyhow::Result<()> {
     match json {
         true => serde_json::to_writer_pretty(io::stdout(), &output)?,
@@ -435,6 +711,43 @@ fn print_output<T: Serialize>(output: &T, json: bool) -> anyhow::Result<()> {
 
 #[cfg(test)]
 mod test {
+    use super::*;
+    use charms_data::Data;
+
     #[test]
     fn dummy() {}
+
+    #[test]
+    fn spell_is_rendered_only_as_template() {
+        let path = std::env::temp_dir().join(format!("charms-spell-{}.yaml", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"
+version: 8
+apps:
+  $t: t/0101010101010101010101010101010101010101010101010101010101010101/0202020202020202020202020202020202020202020202020202020202020202
+public_args:
+  $t: "${arg}"
+ins: []
+outs: []
+"#,
+        )
+        .unwrap();
+        let params = |template, vars: &[(&str, &str)]| SpellTemplateParams {
+            template,
+            vars: (vars.iter())
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            vars_files: vec![],
+        };
+        let public_arg = |spell: Spell| spell.public_args.unwrap()["$t"].clone();
+
+        let spell = read_spell(&path, params(false, &[])).unwrap();
+        assert_eq!(public_arg(spell), Data::from(&"${arg}"));
+        assert!(read_spell(&path, params(true, &[])).is_err());
+        let spell = read_spell(&path, params(false, &[("arg", "x")])).unwrap();
+        assert_eq!(public_arg(spell), Data::from(&"x"));
+
+        std::fs::remove_file(&path).unwrap();
+    }
 }
@@ -1,5 +1,6 @@
 use crate::{
     cli::ServerConfig,
+    estimate::{SpellTxEstimate, estimate_spell_tx},
     spell::{ProveRequest, ProveSpellTx, ProveSpellTxImpl},
 };
 use anyhow::Result;
@@ -54,6 +55,8 @@ impl Server {
         let app = Router::new();
         let app = app
             .route("/spells/prove", post(prove_spell))
+            .route("/spells/prove-batch", post(prove_spells))
+            .route("/spells/estimate", post(estimate_spell))
             .with_state(self.prover.clone())
             .route("/ready", get(|| async { "OK" }))
             .layer(cors_layer());
@@ -80,3 +83,31 @@ async fn prove_spell(
         .map_err(|e| (StatusCode::BAD_REQUEST, Json(e.to_string())))?;
     Ok(Json(result))
 }
+
+/// Prove several spells with a single shared proof.
+#[tracing::instrument(level = "debug", skip_all)]
+async fn prove_spells(
+    State(prover): State<Arc<ProveSpellTxImpl>>,
+    Json(payload): Json<Vec<ProveRequest>>,
+) -> Result<Json<Vec<Vec<String>>>, (StatusCode, Json<String>)> {
+    let result = prover
+        .prove_spell_txs(payload)
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(e.to_string())))?;
+    Ok(Json(result))
+}
+
+/// Estimate the transaction sizes, fees and change for the prove request, without proving.
+/// Assumes the spell is proven on its own, not in a batch (see [`estimate_spell_tx`]).
+#[tracing::instrument(level = "debug", skip_all)]
+async fn estimate_spell(
+    State(prover): State<Arc<ProveSpellTxImpl>>,
+    Json(payload): Json<ProveRequest>,
+) -> Result<Json<SpellTxEstimate>, (StatusCode, Json<String>)> {
+    // running app contracts is CPU-bound: keep it off the async runtime threads
+    let result = tokio::task::spawn_blocking(move || estimate_spell_tx(&prover, &payload))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(e.to_string())))?
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(e.to_string())))?;
+    Ok(Json(result))
+}
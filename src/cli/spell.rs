@@ -1,15 +1,26 @@
 use crate::{
     cli,
-    cli::{BITCOIN, CARDANO, SpellCheckParams, SpellProveParams},
-    spell::{ProveRequest, ProveSpellTx, ProveSpellTxImpl, Spell, ensure_no_zero_amounts},
+    cli::{
+        BITCOIN, CARDANO, SpellCheckParams, SpellExecuteParams, SpellExplainParams,
+        SpellLintParams, SpellProveParams, SpellUpgradeParams,
+    },
+    estimate::estimate_spell_tx,
+    execute::execute_spell,
+    explain::explain_spell,
+    lint::{Severity, lint_spell},
+    spell::{
+        ProveRequest, ProveSpellTx, ProveSpellTxImpl, SpellToProve, ensure_no_zero_amounts,
+        from_hex_txs,
+    },
+    upgrade, utils,
 };
-use anyhow::{Result, ensure};
+use anyhow::{Result, bail, ensure};
 use charms_app_runner::AppRunner;
 use charms_client::{CURRENT_VERSION, tx::Tx};
-use charms_data::UtxoId;
+use charms_data::{Transaction, UtxoId};
 use charms_lib::SPELL_VK;
 use serde_json::json;
-use std::future::Future;
+use std::{collections::BTreeSet, future::Future};
 
 pub trait Check {
     fn check(&self, params: SpellCheckParams) -> Result<()>;
@@ -46,12 +57,74 @@ impl SpellCli {
         println!("{}", json);
         Ok(())
     }
-}
 
-impl Prove for SpellCli {
-    async fn prove(&self, params: SpellProveParams) -> Result<()> {
+    pub(crate) fn execute(&self, params: SpellExecuteParams) -> Result<()> {
+        let SpellExecuteParams {
+            spell,
+            template,
+            app_bins,
+            prev_txs,
+        } = params;
+
+        let spell = cli::read_spell(&spell, template)?;
+        let (norm_spell, app_private_inputs, tx_ins_beamed_source_utxos) = spell.normalized()?;
+        ensure_no_zero_amounts(&norm_spell)?;
+
+        let execution = execute_spell(SpellToProve {
+            norm_spell,
+            app_binaries: cli::app::binaries_by_vk(&self.app_runner, app_bins)?,
+            app_private_inputs,
+            prev_txs: from_hex_txs(&prev_txs)?,
+            tx_ins_beamed_source_utxos,
+        })?;
+
+        println!("{}", serde_json::to_string(&execution)?);
+        if let Some(error) = execution.error {
+            bail!("spell checker failed: {}", error);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn lint(&self, params: SpellLintParams) -> Result<()> {
+        let SpellLintParams {
+            spell,
+            template,
+            app_bins,
+            prev_txs,
+            change_address,
+            json,
+            mock,
+        } = params;
+
+        let spell = cli::read_spell(&spell, template)?;
+        let prev_txs = from_hex_txs(&prev_txs)?;
+        let prev_spells =
+            charms_client::prev_spells_cached(&prev_txs, SPELL_VK, mock, utils::spell_cache());
+        let app_vks = cli::app::binaries_by_vk(&self.app_runner, app_bins)?
+            .into_keys()
+            .collect();
+
+        let issues = lint_spell(
+            &spell,
+            &prev_spells,
+            &app_vks,
+            change_address.as_deref(),
+            mock,
+        );
+        cli::print_output(&issues, json)?;
+
+        let errors = (issues.iter())
+            .filter(|issue| issue.severity == Severity::Error)
+            .count();
+        ensure!(errors == 0, "spell has {} lint errors", errors);
+        Ok(())
+    }
+
+    /// Prove request (and mock mode flag) from the CLI parameters.
+    fn prove_request(&self, params: SpellProveParams) -> Result<(ProveRequest, bool)> {
         let SpellProveParams {
             spell,
+            template,
             prev_txs,
             app_bins,
             funding_utxo,
@@ -59,17 +132,16 @@ impl Prove for SpellCli {
             change_address,
             fee_rate,
             chain,
+            proof_system,
             mock,
         } = params;
 
-        let spell_prover = ProveSpellTxImpl::new(mock);
-
         // Parse funding UTXO early: to fail fast
         let funding_utxo = UtxoId::from_str(&funding_utxo)?;
 
         ensure!(fee_rate >= 1.0, "fee rate must be >= 1.0");
 
-        let spell: Spell = serde_yaml::from_slice(&std::fs::read(spell)?)?;
+        let spell = cli::read_spell(&spell, template)?;
 
         let binaries = cli::app::binaries_by_vk(&self.app_runner, app_bins)?;
 
@@ -81,8 +153,28 @@ impl Prove for SpellCli {
             funding_utxo_value,
             change_address,
             fee_rate,
-            chain: chain.clone(),
+            chain,
+            proof_system,
         };
+        Ok((prove_request, mock))
+    }
+
+    pub(crate) fn estimate(&self, params: SpellProveParams) -> Result<()> {
+        let (prove_request, mock) = self.prove_request(params)?;
+        let spell_prover = ProveSpellTxImpl::new(mock);
+
+        let estimate = estimate_spell_tx(&spell_prover, &prove_request)?;
+        println!("{}", serde_json::to_string(&estimate)?);
+        Ok(())
+    }
+}
+
+impl Prove for SpellCli {
+    async fn prove(&self, params: SpellProveParams) -> Result<()> {
+        let (prove_request, mock) = self.prove_request(params)?;
+        let chain = prove_request.chain.clone();
+
+        let spell_prover = ProveSpellTxImpl::new(mock);
         let transactions = spell_prover.prove_spell_tx(prove_request).await?;
 
         match chain.as_str() {
@@ -112,17 +204,18 @@ impl Prove for SpellCli {
 }
 
 impl Check for SpellCli {
-    #[tracing::instrument(level = "debug", skip(self, spell, app_bins))]
+    #[tracing::instrument(level = "debug", skip(self, spell, template, app_bins))]
     fn check(
         &self,
         SpellCheckParams {
             spell,
+            template,
             app_bins,
             prev_txs,
             mock,
         }: SpellCheckParams,
     ) -> Result<()> {
-        let mut spell: Spell = serde_yaml::from_slice(&std::fs::read(spell)?)?;
+        let mut spell = cli::read_spell(&spell, template)?;
         for u in spell.outs.iter_mut() {
             u.amount.get_or_insert(crate::cli::wallet::MIN_SATS);
         }
@@ -142,25 +235,33 @@ impl Check for SpellCli {
             .map(|tx_hex| Tx::from_hex(tx_hex))
             .collect::<Result<Vec<_>, _>>()?;
 
-        let prev_spells = charms_client::prev_spells(&prev_txs, &SPELL_VK, mock);
+        let prev_spells =
+            charms_client::prev_spells_cached(&prev_txs, &SPELL_VK, mock, utils::spell_cache());
+        spell.enrich_from_prev_txs(&prev_spells)?;
 
         let (norm_spell, app_private_inputs, tx_ins_beamed_source_utxos) = spell.normalized()?;
 
         ensure_no_zero_amounts(&norm_spell)?;
 
-        ensure!(
-            charms_client::well_formed(&norm_spell, &prev_spells, &tx_ins_beamed_source_utxos),
-            "spell is not well-formed"
-        );
-
         let binaries = cli::app::binaries_by_vk(&self.app_runner, app_bins)?;
 
-        let charms_tx = spell.to_tx()?;
-        let cycles_spent = self.app_runner.run_all(
-            &binaries,
-            &charms_tx,
-            &norm_spell.app_public_inputs,
-            &app_private_inputs,
+        let mut cycles_spent = vec![];
+        let mut run_app_contracts = |charms_tx: &Transaction| {
+            cycles_spent = self.app_runner.run_all(
+                &binaries,
+                charms_tx,
+                &norm_spell.app_public_inputs,
+                &app_private_inputs,
+            )?;
+            Ok(())
+        };
+        charms_client::is_correct_detailed(
+            &norm_spell,
+            &prev_spells,
+            &tx_ins_beamed_source_utxos,
+            Some(&mut run_app_contracts),
+            &BTreeSet::new(),
+            &|_, _, _| unreachable!("app contracts are run, not proven"),
         )?;
 
         eprintln!("cycles spent: {:?}", cycles_spent);
@@ -168,3 +269,49 @@ impl Check for SpellCli {
         Ok(())
     }
 }
+
+pub fn explain(params: SpellExplainParams) -> Result<()> {
+    let SpellExplainParams {
+        spell,
+        template,
+        prev_txs,
+        json,
+        mock,
+    } = params;
+
+    let spell = cli::read_spell(&spell, template)?;
+    let prev_txs = from_hex_txs(&prev_txs)?;
+    let prev_spells =
+        charms_client::prev_spells_cached(&prev_txs, SPELL_VK, mock, utils::spell_cache());
+    let explanation = explain_spell(&spell, &prev_spells)?;
+
+    match json {
+        true => println!("{}", serde_json::to_string_pretty(&explanation)?),
+        false => print!("{}", explanation),
+    }
+    Ok(())
+}
+
+pub fn upgrade(params: SpellUpgradeParams) -> Result<()> {
+    let SpellUpgradeParams { spell, json } = params;
+
+    let value: serde_yaml::Value = serde_yaml::from_slice(&std::fs::read(spell)?)?;
+    let upgraded = upgrade::upgrade_spell(value)?;
+
+    if upgraded.changes.is_empty() {
+        eprintln!(
+            "spell is at the current protocol version {}: no changes",
+            CURRENT_VERSION
+        );
+    } else {
+        eprintln!(
+            "upgraded spell from version {} to {}:",
+            upgraded.from_version, CURRENT_VERSION
+        );
+        for change in upgraded.changes.iter() {
+            eprintln!("  - {}", change);
+        }
+    }
+
+    cli::print_output(&upgraded.spell, json)
+}
@@ -1,4 +1,5 @@
 use crate::{
+    aliases::AppAliases,
     cli,
     cli::{BITCOIN, CARDANO, ShowSpellParams},
     tx,
@@ -19,7 +20,7 @@ pub fn tx_show_spell(params: ShowSpellParams) -> Result<()> {
         _ => unimplemented!(),
     };
 
-    match tx::spell(&tx, mock)? {
+    match tx::spell(&tx, mock, &AppAliases::load()?.app_keys())? {
         Some(spell) => cli::print_output(&spell, json)?,
         None => eprintln!("No spell found in the transaction"),
     }
@@ -1,13 +1,17 @@
 use crate::{
+    aliases::{AppAliases, TokenMetadata},
     cli,
     cli::WalletListParams,
     spell::{KeyedCharms, Spell},
     tx,
-    utils::str_index,
 };
 use anyhow::{Result, ensure};
 use bitcoin::{Transaction, hashes::Hash};
-use charms_client::{bitcoin_tx::BitcoinTx, tx::Tx};
+use charms_client::{
+    bitcoin_tx::BitcoinTx,
+    spell::{AppKeys, keys_of_apps},
+    tx::Tx,
+};
 use charms_data::{App, Data, TxId, UtxoId};
 use serde::{Deserialize, Serialize};
 use std::{
@@ -46,6 +50,9 @@ type ParsedCharms = BTreeMap<App, Data>;
 #[derive(Debug, Serialize)]
 struct AppsAndCharmsOutputs {
     apps: BTreeMap<String, App>,
+    /// Metadata of the named apps (from the app alias registry).
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    metadata: BTreeMap<String, TokenMetadata>,
     outputs: BTreeMap<UtxoId, OutputWithCharms>,
 }
 
@@ -76,10 +83,16 @@ fn outputs_with_charms(
     let spells = txs_with_spells(txid_set.into_iter(), mock)?;
     let utxos_with_charms: BTreeMap<UtxoId, (BListUnspentItem, ParsedCharms)> =
         utxos_with_charms(spells, b_list_unspent);
-    let apps = collect_apps(&utxos_with_charms);
+    let aliases = AppAliases::load()?;
+    let apps = collect_apps(&utxos_with_charms, &aliases.app_keys());
+    let metadata = apps
+        .iter()
+        .filter_map(|(app, key)| Some((key.clone(), aliases.metadata(app)?.clone())))
+        .collect();
 
     Ok(AppsAndCharmsOutputs {
         apps: enumerate_apps(&apps),
+        metadata,
         outputs: pretty_outputs(utxos_with_charms, &apps),
     })
 }
@@ -96,7 +109,7 @@ fn txs_with_spells(
         .map(|tx_result: Result<Transaction>| {
             let tx = tx_result?;
             let txid = tx.compute_txid();
-            let spell_opt = tx::spell(&Tx::Bitcoin(BitcoinTx(tx)), mock)?;
+            let spell_opt = tx::spell(&Tx::Bitcoin(BitcoinTx(tx)), mock, &AppKeys::new())?;
             Ok(spell_opt.map(|spell| (TxId(txid.to_byte_array()), spell)))
         })
         .filter_map(|tx_result| match tx_result {
@@ -140,16 +153,15 @@ fn parsed_charms(keyed_charms: &KeyedCharms, apps: &BTreeMap<String, App>) -> Pa
 
 fn collect_apps(
     strings_of_charms: &BTreeMap<UtxoId, (BListUnspentItem, ParsedCharms)>,
+    app_keys: &AppKeys,
 ) -> BTreeMap<App, String> {
     let apps: BTreeSet<App> = strings_of_charms
         .iter()
         .flat_map(|(_utxo, (_sats, charms))| charms.keys())
         .cloned()
         .collect();
-    apps.into_iter()
-        .zip(0..)
-        .map(|(app, i)| (app, str_index(&i)))
-        .collect()
+    let keys = keys_of_apps(&apps, app_keys);
+    apps.into_iter().zip(keys).collect()
 }
 
 fn enumerate_apps(apps: &BTreeMap<App, String>) -> BTreeMap<String, App> {
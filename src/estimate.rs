@@ -0,0 +1,207 @@
+use crate::{
+    cli::BITCOIN,
+    spell::{
+        ProveRequest, ProveSpellTxImpl, bitcoin_sats_in, bitcoin_sats_out, from_hex_txs,
+        get_charms_fee,
+    },
+    tx::{
+        bitcoin_tx::{self, DUST_LIMIT},
+        txs_by_txid,
+    },
+};
+use anyhow::{Result, bail, ensure};
+use bitcoin::{Amount, Transaction, Weight, hashes::Hash};
+use charms_client::{
+    Proof,
+    bitcoin_tx::BitcoinTx,
+    tx::{ProofSystem, Tx},
+};
+use charms_data::{TxId, util};
+use serde::Serialize;
+
+/// Size of SP1 Groth16 proofs (BN254): 4-byte prefix of the wrapper verifying key hash, then the
+/// `A`, `B` and `C` points (256 bytes, uncompressed).
+const GROTH16_PROOF_SIZE: usize = 260;
+/// Size of SP1 PLONK proofs (BN254), as checked by `sp1_verifier::PlonkVerifier`: 4-byte prefix
+/// of the wrapper verifying key hash, then 864 bytes: 9 G1 commitments (wires, quotient parts,
+/// permutation, custom gate), 2 opening proofs and 7 evaluations at `zeta`/`zeta·omega`.
+const PLONK_PROOF_SIZE: usize = 868;
+/// Size of mock proofs (compressed Groth16 proofs over BLS12-381).
+const MOCK_PROOF_SIZE: usize = 192;
+
+/// Witness weight of a Taproot key path spend: the signature and its length.
+const KEY_SPEND_WITNESS_WEIGHT: Weight = Weight::from_wu(66);
+
+/// Funding UTXO value the transactions are built with for estimation: large enough to always
+/// produce a change output.
+const PLACEHOLDER_FUNDING_VALUE: Amount = Amount::MAX_MONEY;
+
+/// Estimated sizes and costs of the transactions for a spell (see [`estimate_spell_tx`]).
+/// Amounts are in sats.
+#[derive(Debug, Serialize)]
+pub struct SpellTxEstimate {
+    /// Cycles spent running app contracts: the charms fee is based on these.
+    pub app_cycles: u64,
+    /// Virtual size (in vB) of the signed commit transaction.
+    pub commit_tx_vsize: u64,
+    /// Virtual size (in vB) of the signed spell transaction.
+    pub spell_tx_vsize: u64,
+    /// Network fee paid by the commit transaction.
+    pub commit_tx_fee: u64,
+    /// Network fee paid by the spell transaction.
+    pub spell_tx_fee: u64,
+    /// Charms fee for proving the spell.
+    pub charms_fee: u64,
+    /// Change returned to the change address. Zero if the funding UTXO is insufficient or the
+    /// change is below the dust limit (then it goes to the network fee).
+    pub change_amount: u64,
+    /// Minimum funding UTXO value covering the spell's outputs and all fees.
+    pub min_funding_utxo_value: u64,
+    /// Does the funding UTXO value cover the spell's outputs and all fees?
+    pub funding_sufficient: bool,
+}
+
+/// Estimate the transaction sizes, fees and change for the prove request without proving the
+/// spell: the request is validated (app contracts are run) and the transactions are built with a
+/// placeholder proof of the same size as the real one.
+///
+/// Inputs of the spell transaction (other than the spell input) are assumed to be Taproot key
+/// path spends. Only Bitcoin is supported.
+///
+/// The spell is assumed to be proven on its own (e.g. via `/spells/prove`). Spells proven in a
+/// batch (via `/spells/prove-batch`) carry a larger
+/// [`BatchProof`](charms_client::batch::BatchProof): the `"charms-batch"` prefix and 32 bytes per
+/// spell of the batch on top of the proof. Their transaction sizes and network fees are
+/// underestimated.
+pub fn estimate_spell_tx(
+    spell_prover: &ProveSpellTxImpl,
+    prove_request: &ProveRequest,
+) -> Result<SpellTxEstimate> {
+    ensure!(
+        prove_request.chain == BITCOIN,
+        "fee estimation is only supported for Bitcoin"
+    );
+    let app_cycles = spell_prover.check_prove_request(prove_request)?;
+
+    let (norm_spell, _, _) = prove_request.spell.normalized()?;
+    let proof = placeholder_proof(prove_request.proof_system, spell_prover.mock);
+    let spell_data = util::write(&(&norm_spell, &proof))?;
+
+    let mut prev_txs_by_id = txs_by_txid(&from_hex_txs(&prove_request.prev_txs)?);
+    let txs = bitcoin_tx::make_transactions(
+        &prove_request.spell,
+        prove_request.funding_utxo.clone(),
+        PLACEHOLDER_FUNDING_VALUE.to_sat(),
+        &prove_request.change_address,
+        &prev_txs_by_id,
+        &spell_data,
+        prove_request.fee_rate,
+        spell_prover.charms_fee_settings.clone(),
+        app_cycles,
+    )?;
+    let [
+        Tx::Bitcoin(BitcoinTx(commit_tx)),
+        Tx::Bitcoin(BitcoinTx(spell_tx)),
+    ] = txs.as_slice()
+    else {
+        bail!("expected commit and spell transactions");
+    };
+
+    let commit_tx_fee = PLACEHOLDER_FUNDING_VALUE - bitcoin_tx::tx_total_amount_out(commit_tx);
+    prev_txs_by_id.insert(
+        TxId(commit_tx.compute_txid().to_byte_array()),
+        Tx::Bitcoin(BitcoinTx(commit_tx.clone())),
+    );
+    let spell_tx_fee = bitcoin_tx::tx_total_amount_in(&prev_txs_by_id, spell_tx)
+        - bitcoin_tx::tx_total_amount_out(spell_tx);
+    let charms_fee = get_charms_fee(&spell_prover.charms_fee_settings, app_cycles);
+
+    let sats_in = bitcoin_sats_in(prove_request)?;
+    let sats_needed = bitcoin_sats_out(prove_request)
+        + charms_fee.to_sat()
+        + commit_tx_fee.to_sat()
+        + spell_tx_fee.to_sat();
+    let sats_available = sats_in + prove_request.funding_utxo_value;
+    let change_amount = match sats_available.checked_sub(sats_needed) {
+        Some(change) if change >= DUST_LIMIT.to_sat() => change,
+        _ => 0,
+    };
+
+    Ok(SpellTxEstimate {
+        app_cycles,
+        commit_tx_vsize: signed_vsize(commit_tx),
+        spell_tx_vsize: signed_vsize(spell_tx),
+        commit_tx_fee: commit_tx_fee.to_sat(),
+        spell_tx_fee: spell_tx_fee.to_sat(),
+        charms_fee: charms_fee.to_sat(),
+        change_amount,
+        min_funding_utxo_value: sats_needed.saturating_sub(sats_in),
+        funding_sufficient: sats_available >= sats_needed,
+    })
+}
+
+/// Placeholder of the same size as the spell proof (tagged with the proof system).
+fn placeholder_proof(proof_system: ProofSystem, mock: bool) -> Proof {
+    let size = match (mock, proof_system) {
+        (true, _) => MOCK_PROOF_SIZE,
+        (false, ProofSystem::Groth16) => GROTH16_PROOF_SIZE,
+        (false, ProofSystem::Plonk) => PLONK_PROOF_SIZE,
+    };
+    proof_system.tag(vec![0; size])
+}
+
+/// Virtual size of the transaction once signed: inputs without witnesses are assumed to be
+/// Taproot key path spends.
+fn signed_vsize(tx: &Transaction) -> u64 {
+    let unsigned_inputs = tx.input.iter().filter(|i| i.witness.is_empty()).count();
+    let segwit_marker_weight = match unsigned_inputs == tx.input.len() {
+        true => Weight::from_wu(2),
+        false => Weight::ZERO,
+    };
+    (tx.weight() + segwit_marker_weight + KEY_SPEND_WITNESS_WEIGHT * unsigned_inputs as u64)
+        .to_vbytes_ceil()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bitcoin::{
+        OutPoint, ScriptBuf, TxIn, TxOut, absolute::LockTime, key::TweakedPublicKey,
+        secp256k1::XOnlyPublicKey, transaction::Version,
+    };
+    use std::str::FromStr;
+
+    #[test]
+    fn signed_vsize_of_commit_tx() {
+        let public_key = XOnlyPublicKey::from_str(
+            "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+        )
+        .unwrap();
+        let commit_tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::null(),
+                ..Default::default()
+            }],
+            output: vec![TxOut {
+                value: Amount::from_sat(10000),
+                script_pubkey: ScriptBuf::new_p2tr_tweaked(
+                    TweakedPublicKey::dangerous_assume_tweaked(public_key),
+                ),
+            }],
+        };
+        // the commit transaction builder pays the fee for 111 vB
+        assert_eq!(signed_vsize(&commit_tx), 111);
+    }
+
+    #[test]
+    fn placeholder_proof_sizes() {
+        assert_eq!(placeholder_proof(ProofSystem::Groth16, false).len(), 260);
+        assert_eq!(
+            placeholder_proof(ProofSystem::Plonk, false).len(),
+            charms_client::tx::PLONK_PROOF_PREFIX.len() + 868
+        );
+        assert_eq!(placeholder_proof(ProofSystem::Groth16, true).len(), 192);
+    }
+}
@@ -0,0 +1,100 @@
+use crate::{
+    SPELL_CHECKER_BINARY,
+    spell::{SpellToProve, spell_checker_input, write_compressed_proof},
+};
+use anyhow::{anyhow, ensure};
+use charms_data::util;
+use serde::Serialize;
+use sp1_core_executor::{Executor, Program, SP1Context, SP1CoreOpts};
+use sp1_core_machine::io::SP1Stdin;
+use sp1_sdk::{Prover, ProverClient};
+
+/// Result of running the spell checker in the SP1 executor.
+#[derive(Debug, Serialize)]
+pub struct SpellExecution {
+    /// Did the spell checker accept the spell?
+    pub success: bool,
+    /// Panic message of the spell checker (if it rejected the spell).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// RISC-V cycles spent by the spell checker (including running the app contracts).
+    pub cycles: u64,
+}
+
+/// Run the spell checker (`SPELL_CHECKER_BINARY`) for the spell in the SP1 executor on CPU: no
+/// proof is generated and no network access is needed.
+///
+/// The spell checker gets the same input as when proving (see
+/// [`spell_checker_input`]), so it takes the same code path and spends the same number of
+/// cycles. Proofs of parent spells verified recursively are supplied, but not checked: the
+/// executor runs with deferred proof verification disabled.
+pub fn execute_spell(spell_to_prove: SpellToProve) -> anyhow::Result<SpellExecution> {
+    ensure!(
+        !spell_to_prove.norm_spell.mock,
+        "mock spells are not checked by the spell checker"
+    );
+
+    let (prover_input, parent_proofs) = spell_checker_input(spell_to_prove)?;
+
+    let mut stdin = SP1Stdin::new();
+    stdin.write_vec(util::write(&prover_input)?);
+    if !parent_proofs.is_empty() {
+        // not verified by the executor, but consumed by the spell checker's `verify_sp1_proof`
+        let (_, spell_checker_vk) = ProverClient::builder()
+            .cpu()
+            .build()
+            .setup(SPELL_CHECKER_BINARY);
+        for proof in parent_proofs {
+            write_compressed_proof(&mut stdin, proof, &spell_checker_vk);
+        }
+    }
+
+    let program = Program::from(SPELL_CHECKER_BINARY)
+        .map_err(|e| anyhow!("failed to load the spell checker program: {}", e))?;
+
+    let mut stderr = Vec::new();
+    let context = SP1Context::builder()
+        .stderr(&mut stderr)
+        .set_deferred_proof_verification(false)
+        .build();
+    let mut executor = Executor::with_context(program, SP1CoreOpts::default(), context);
+    executor.write_vecs(&stdin.buffer);
+    for (proof, vk) in stdin.proofs {
+        executor.write_proof(proof, vk);
+    }
+    let result = executor.run_fast();
+    let cycles = executor.state.global_clk;
+    drop(executor);
+
+    let error = result
+        .err()
+        .map(|e| panic_message(&String::from_utf8_lossy(&stderr)).unwrap_or(e.to_string()));
+    tracing::info!("spell checker executed: {} cycles", cycles);
+
+    Ok(SpellExecution {
+        success: error.is_none(),
+        error,
+        cycles,
+    })
+}
+
+/// Extract the panic message (and location) from the program's stderr output.
+fn panic_message(stderr: &str) -> Option<String> {
+    let start = stderr.find("panicked at")?;
+    Some(stderr[start..].trim().to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn extracts_panic_message() {
+        let stderr = "about to check\npanicked at charms-spell-checker/src/bin.rs:35:5:\nassertion failed: is_correct(...)\n";
+        assert_eq!(
+            panic_message(stderr).unwrap(),
+            "panicked at charms-spell-checker/src/bin.rs:35:5:\nassertion failed: is_correct(...)"
+        );
+        assert_eq!(panic_message("Spell is correct!\n"), None);
+    }
+}
@@ -0,0 +1,338 @@
+use crate::spell::Spell;
+use anyhow::{Result, anyhow};
+use charms_client::NormalizedSpell;
+use charms_data::{App, B32, Data, NFT, TOKEN, TxId, UtxoId};
+use serde::Serialize;
+use std::{collections::BTreeMap, fmt};
+
+/// Effects of a spell, per app (see [`explain_spell`]).
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SpellExplanation {
+    pub apps: Vec<AppEffects>,
+    /// Outputs beamed to other chains.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub beamed_outs: Vec<BeamedOut>,
+    /// Inputs with charms beamed from other chains.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub beamed_ins: Vec<BeamedIn>,
+}
+
+/// Charms of an app in the spell's inputs and outputs, and their overall effect.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AppEffects {
+    /// Key of the app in the spell.
+    pub key: String,
+    pub app: App,
+    pub effect: Effect,
+    pub inputs: Vec<InputCharm>,
+    pub outputs: Vec<OutputCharm>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Effect {
+    /// Charms are moved without changes (token amounts are balanced, NFT states preserved).
+    Transferred,
+    /// More tokens in the outputs than in the inputs.
+    TokensMinted(u64),
+    /// Fewer tokens in the outputs than in the inputs.
+    TokensBurned(u64),
+    /// NFT created: it is only in the outputs.
+    NftMinted,
+    /// NFT destroyed: it is only in the inputs.
+    NftBurned,
+    /// NFT state in the outputs differs from the inputs.
+    NftStateChanged,
+    /// Charms of the app (neither a token nor an NFT) are changed: what it means is up to the app.
+    Changed,
+}
+
+/// Charm data in an input UTXO.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct InputCharm {
+    pub utxo_id: UtxoId,
+    pub data: Data,
+}
+
+/// Charm data in a transaction output.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct OutputCharm {
+    /// Index of the output.
+    pub output: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    pub data: Data,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BeamedOut {
+    /// Index of the output.
+    pub output: usize,
+    /// Hash of the destination UTXO ID.
+    pub beam_to: B32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BeamedIn {
+    pub utxo_id: UtxoId,
+    /// Source UTXO (beamed out to `utxo_id`).
+    pub beamed_from: UtxoId,
+}
+
+/// Explain the effects of the spell: for each app, its charms in the inputs (resolved from
+/// `prev_spells`: see [`Spell::enrich_from_prev_txs`]) and outputs, and whether tokens are
+/// minted or burned, NFTs created, destroyed or updated. Beamed outputs and inputs are listed
+/// separately.
+pub fn explain_spell(
+    spell: &Spell,
+    prev_spells: &BTreeMap<TxId, (Option<NormalizedSpell>, usize)>,
+) -> Result<SpellExplanation> {
+    let mut spell = spell.clone();
+    spell.enrich_from_prev_txs(prev_spells)?;
+
+    let apps = spell
+        .apps
+        .iter()
+        .map(|(key, app)| {
+            let inputs: Vec<InputCharm> = spell
+                .ins
+                .iter()
+                .filter_map(|input| {
+                    let data = input.charms.as_ref()?.get(key)?;
+                    Some(InputCharm {
+                        utxo_id: input.utxo_id.clone()?,
+                        data: data.clone(),
+                    })
+                })
+                .collect();
+            let outputs: Vec<OutputCharm> = (spell.outs.iter().enumerate())
+                .filter_map(|(i, output)| {
+                    let data = output.charms.as_ref()?.get(key)?;
+                    Some(OutputCharm {
+                        output: i,
+                        address: output.address.clone(),
+                        data: data.clone(),
+                    })
+                })
+                .collect();
+            let effect =
+                effect(app, &inputs, &outputs).map_err(|e| anyhow!("app {}: {}", key, e))?;
+            Ok(AppEffects {
+                key: key.clone(),
+                app: app.clone(),
+                effect,
+                inputs,
+                outputs,
+            })
+        })
+        .collect::<Result<_>>()?;
+
+    let beamed_outs = (spell.outs.iter().enumerate())
+        .filter_map(|(i, output)| {
+            Some(BeamedOut {
+                output: i,
+                beam_to: output.beam_to.clone()?,
+            })
+        })
+        .collect();
+    let beamed_ins = (spell.ins.iter())
+        .filter_map(|input| {
+            Some(BeamedIn {
+                utxo_id: input.utxo_id.clone()?,
+                beamed_from: input.beamed_from.clone()?,
+            })
+        })
+        .collect();
+
+    Ok(SpellExplanation {
+        apps,
+        beamed_outs,
+        beamed_ins,
+    })
+}
+
+fn effect(app: &App, inputs: &[InputCharm], outputs: &[OutputCharm]) -> Result<Effect> {
+    let mut data_in: Vec<&Data> = inputs.iter().map(|c| &c.data).collect();
+    let mut data_out: Vec<&Data> = outputs.iter().map(|c| &c.data).collect();
+    data_in.sort();
+    data_out.sort();
+
+    let effect = match app.tag {
+        TOKEN => {
+            let amount_in = token_amount(&data_in)?;
+            let amount_out = token_amount(&data_out)?;
+            match amount_out.checked_sub(amount_in) {
+                Some(0) => Effect::Transferred,
+                Some(minted) => Effect::TokensMinted(minted),
+                None => Effect::TokensBurned(amount_in - amount_out),
+            }
+        }
+        _ if data_in == data_out => Effect::Transferred,
+        NFT if data_in.is_empty() => Effect::NftMinted,
+        NFT if data_out.is_empty() => Effect::NftBurned,
+        NFT => Effect::NftStateChanged,
+        _ => Effect::Changed,
+    };
+    Ok(effect)
+}
+
+fn token_amount(data: &[&Data]) -> Result<u64> {
+    data.iter().try_fold(0u64, |total, data| {
+        let amount = data.value::<u64>()?;
+        total
+            .checked_add(amount)
+            .ok_or(anyhow!("token amount overflow"))
+    })
+}
+
+impl fmt::Display for SpellExplanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for app_effects in &self.apps {
+            write!(f, "{}", app_effects)?;
+        }
+        for beamed_out in &self.beamed_outs {
+            writeln!(
+                f,
+                "output {} is beamed to UTXO with ID hash {}",
+                beamed_out.output, beamed_out.beam_to
+            )?;
+        }
+        for beamed_in in &self.beamed_ins {
+            writeln!(
+                f,
+                "input {} is beamed from {}",
+                beamed_in.utxo_id, beamed_in.beamed_from
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for AppEffects {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} {}: {}", self.key, self.app, self.effect)?;
+        for input in &self.inputs {
+            writeln!(f, "  from {}: {}", input.utxo_id, data_str(&input.data))?;
+        }
+        for output in &self.outputs {
+            write!(f, "  to output {}", output.output)?;
+            if let Some(address) = &output.address {
+                write!(f, " ({})", address)?;
+            }
+            writeln!(f, ": {}", data_str(&output.data))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Effect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Effect::Transferred => write!(f, "transferred"),
+            Effect::TokensMinted(amount) => write!(f, "{} tokens minted", amount),
+            Effect::TokensBurned(amount) => write!(f, "{} tokens burned", amount),
+            Effect::NftMinted => write!(f, "NFT minted"),
+            Effect::NftBurned => write!(f, "NFT burned"),
+            Effect::NftStateChanged => write!(f, "NFT state changed"),
+            Effect::Changed => write!(f, "charms changed"),
+        }
+    }
+}
+
+/// Data as (single line) JSON.
+fn data_str(data: &Data) -> String {
+    serde_json::to_string(data).unwrap_or_else(|_| format!("{:?}", data))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::spell::{Input, Output};
+    use charms_client::test_utils::{app, charms, input, normalized, output, spell, utxo_id};
+
+    fn nft_and_token_spell(ins: Vec<Input>, outs: Vec<Output>) -> Spell {
+        spell(&[("$n", app(NFT)), ("$t", app(TOKEN))], ins, outs)
+    }
+
+    /// Tx `[1; 32]` created an NFT in output 0 and 100 tokens in output 1.
+    fn prev_spells() -> BTreeMap<TxId, (Option<NormalizedSpell>, usize)> {
+        let prev_spell = nft_and_token_spell(
+            vec![input(9, 0)],
+            vec![
+                output("addr0", 1000, charms(&[("$n", Data::from(&"v1"))])),
+                output("addr1", 1000, charms(&[("$t", Data::from(&100u64))])),
+            ],
+        );
+        BTreeMap::from([(TxId([1; 32]), (Some(normalized(&prev_spell)), 2))])
+    }
+
+    #[test]
+    fn explains_app_effects() {
+        let spell = nft_and_token_spell(
+            vec![input(1, 0), input(1, 1)],
+            vec![
+                output("addr2", 1000, charms(&[("$n", Data::from(&"v2"))])),
+                output("addr3", 1000, charms(&[("$t", Data::from(&70u64))])),
+            ],
+        );
+        let explanation = explain_spell(&spell, &prev_spells()).unwrap();
+
+        assert_eq!(explanation.apps.len(), 2);
+        let nft = &explanation.apps[0];
+        assert_eq!(nft.effect, Effect::NftStateChanged);
+        assert_eq!(
+            nft.inputs,
+            vec![InputCharm {
+                utxo_id: utxo_id(1, 0),
+                data: Data::from(&"v1"),
+            }]
+        );
+        let token = &explanation.apps[1];
+        assert_eq!(token.effect, Effect::TokensBurned(30));
+        assert_eq!(
+            token.outputs,
+            vec![OutputCharm {
+                output: 1,
+                address: Some("addr3".to_string()),
+                data: Data::from(&70u64),
+            }]
+        );
+        assert_eq!(
+            format!("{}", token),
+            format!(
+                "$t {}: 30 tokens burned\n  from {}: 100\n  to output 1 (addr3): 70\n",
+                app(TOKEN),
+                utxo_id(1, 1)
+            )
+        );
+    }
+
+    #[test]
+    fn explains_transfers_mints_and_beams() {
+        let mut spell = nft_and_token_spell(
+            vec![input(1, 0)],
+            vec![
+                output("addr2", 1000, charms(&[("$n", Data::from(&"v1"))])),
+                output("addr3", 1000, charms(&[("$t", Data::from(&5u64))])),
+            ],
+        );
+        spell.outs[1].beam_to = Some(B32([7; 32]));
+        let explanation = explain_spell(&spell, &prev_spells()).unwrap();
+
+        assert_eq!(explanation.apps[0].effect, Effect::Transferred);
+        assert_eq!(explanation.apps[1].effect, Effect::TokensMinted(5));
+        assert_eq!(
+            explanation.beamed_outs,
+            vec![BeamedOut {
+                output: 1,
+                beam_to: B32([7; 32]),
+            }]
+        );
+    }
+
+    #[test]
+    fn requires_prev_txs() {
+        let spell = nft_and_token_spell(vec![input(3, 0)], vec![output("addr2", 1000, None)]);
+        assert!(explain_spell(&spell, &prev_spells()).is_err());
+    }
+}
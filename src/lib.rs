@@ -1,8 +1,17 @@
+pub mod aliases;
 pub mod app;
+pub mod beam;
+pub mod builder;
 pub mod cli;
+pub mod estimate;
+pub mod execute;
+pub mod explain;
+pub mod lint;
 pub mod script;
 pub mod spell;
+pub mod template;
 pub mod tx;
+pub mod upgrade;
 pub mod utils;
 
 pub use charms_proof_wrapper::SPELL_CHECKER_VK;
@@ -0,0 +1,350 @@
+use crate::{
+    spell::{Spell, bitcoin_network, zero_amounts},
+    tx::bitcoin_tx::DUST_LIMIT,
+};
+use charms_client::{NormalizedSpell, to_tx, well_formed_detailed};
+use charms_data::{App, B32, NFT, TOKEN, TxId, is_simple_transfer};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    str::FromStr,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Likely a mistake, but the spell can still be proven.
+    Warning,
+    /// The spell will fail to prove (or the transaction will not be accepted).
+    Error,
+}
+
+/// Problem found in a spell by [`lint_spell`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct LintIssue {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{}: {}", severity, self.message)
+    }
+}
+
+/// Check the spell for mistakes that would make proving it fail (or produce an unintended
+/// transaction), without running app contracts or proving anything. All issues found are
+/// reported.
+///
+/// - `prev_spells`: spells of the transactions creating the spell's inputs and references (see
+///   [`charms_client::prev_spells`]),
+/// - `app_vks`: verification keys of the apps whose binaries are supplied: the other apps' charms
+///   must only be transferred (e.g. token amounts must balance),
+/// - `change_address`: if supplied, output addresses must be on the same network.
+pub fn lint_spell(
+    spell: &Spell,
+    prev_spells: &BTreeMap<TxId, (Option<NormalizedSpell>, usize)>,
+    app_vks: &BTreeSet<B32>,
+    change_address: Option<&str>,
+    mock: bool,
+) -> Vec<LintIssue> {
+    let mut issues = vec![];
+    let mut error = |message: String| {
+        issues.push(LintIssue {
+            severity: Severity::Error,
+            message,
+        })
+    };
+
+    let mut keys_by_app: BTreeMap<&App, Vec<&String>> = BTreeMap::new();
+    for (key, app) in &spell.apps {
+        keys_by_app.entry(app).or_default().push(key);
+    }
+    for (app, keys) in keys_by_app.iter().filter(|(_, keys)| keys.len() > 1) {
+        error(format!("duplicate app {}: {}", join(keys), app));
+    }
+
+    let network = change_address.and_then(|change_address| {
+        bitcoin_network(change_address, mock)
+            .map_err(|e| error(format!("change address {}: {}", change_address, e)))
+            .ok()
+    });
+    for (i, output) in spell.outs.iter().enumerate() {
+        if let Some(amount) = output.amount
+            && amount < DUST_LIMIT.to_sat()
+        {
+            error(format!(
+                "output {}: amount {} is below the dust limit {}",
+                i,
+                amount,
+                DUST_LIMIT.to_sat()
+            ));
+        }
+        match (&output.address, network) {
+            (None, _) => error(format!("output {}: no address", i)),
+            (Some(address), Some(network)) => match bitcoin::Address::from_str(address) {
+                Ok(a) if a.is_valid_for_network(network) => {}
+                Ok(_) => error(format!(
+                    "output {}: address {} is not on the change address network ({})",
+                    i, address, network
+                )),
+                Err(e) => error(format!("output {}: invalid address {}: {}", i, address, e)),
+            },
+            (Some(_), None) => {}
+        }
+    }
+
+    let mut prev_txs_missing = false;
+    let inputs = (spell
+        .ins
+        .iter()
+        .enumerate()
+        .map(|(i, input)| ("input", i, input)))
+    .chain((spell.refs.iter().flatten().enumerate()).map(|(i, input)| ("reference", i, input)));
+    for (kind, i, input) in inputs {
+        let Some(utxo_id) = &input.utxo_id else {
+            error(format!("{} {}: no utxo_id", kind, i));
+            prev_txs_missing = true;
+            continue;
+        };
+        for utxo_id in [Some(utxo_id), input.beamed_from.as_ref()]
+            .into_iter()
+            .flatten()
+        {
+            if !prev_spells.contains_key(&utxo_id.0) {
+                error(format!(
+                    "{} {}: transaction creating UTXO {} is not supplied",
+                    kind, i, utxo_id
+                ));
+                prev_txs_missing = true;
+            }
+        }
+    }
+
+    let (norm_spell, _, _) = match spell.normalized() {
+        Ok(normalized) => normalized,
+        Err(e) => {
+            error(format!("spell cannot be normalized: {}", e));
+            return issues;
+        }
+    };
+    match zero_amounts(&norm_spell) {
+        Ok(zero_amounts) => {
+            for (i, app) in zero_amounts {
+                error(format!("output {}: zero amount of token {}", i, app));
+            }
+        }
+        Err(e) => error(e.to_string()),
+    }
+
+    // the rest of the checks need the charms of the inputs
+    let mut enriched_spell = None;
+    if !prev_txs_missing {
+        let mut spell = spell.clone();
+        match spell
+            .enrich_from_prev_txs(prev_spells)
+            .and_then(|()| spell.normalized())
+        {
+            Ok((norm_spell, _, tx_ins_beamed_source_utxos)) => {
+                if let Err(e) =
+                    well_formed_detailed(&norm_spell, prev_spells, &tx_ins_beamed_source_utxos)
+                {
+                    error(format!("spell is not well-formed: {}", e));
+                }
+                let tx = to_tx(&norm_spell, prev_spells, &tx_ins_beamed_source_utxos);
+                for app in norm_spell.app_public_inputs.keys() {
+                    if app_vks.contains(&app.vk) || is_simple_transfer(app, &tx) {
+                        continue;
+                    }
+                    let what = match app.tag {
+                        TOKEN => "token amounts are not balanced",
+                        NFT => "NFT state is not preserved",
+                        _ => "charms are not simply transferred",
+                    };
+                    error(format!(
+                        "app {}: {} and its binary is not supplied",
+                        app, what
+                    ));
+                }
+                enriched_spell = Some(spell);
+            }
+            Err(e) => error(format!("spell inputs: {}", e)),
+        }
+    }
+
+    let spell = enriched_spell.as_ref().unwrap_or(spell);
+    let used_keys: BTreeSet<&String> = (spell.ins.iter())
+        .chain(spell.refs.iter().flatten())
+        .filter_map(|input| input.charms.as_ref())
+        .chain(
+            spell
+                .outs
+                .iter()
+                .filter_map(|output| output.charms.as_ref()),
+        )
+        .flat_map(|charms| charms.keys())
+        .collect();
+    for key in spell.apps.keys().filter(|key| !used_keys.contains(key)) {
+        issues.push(LintIssue {
+            severity: Severity::Warning,
+            message: format!(
+                "app {} is declared but has no charms in the spell's inputs, references or outputs",
+                key
+            ),
+        });
+    }
+
+    issues
+}
+
+fn join(keys: &[&String]) -> String {
+    keys.iter()
+        .map(|key| key.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::spell::{Input, KeyedCharms, Output};
+    use charms_client::test_utils::{app, charms, input, normalized, output, spell, utxo_id};
+    use charms_data::Data;
+
+    const TESTNET_ADDRESS: &str = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx";
+    const MAINNET_ADDRESS: &str = "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0";
+
+    fn tokens(amount: u64) -> Option<KeyedCharms> {
+        charms(&[("$t", Data::from(&amount))])
+    }
+
+    fn token_spell(ins: Vec<Input>, outs: Vec<Output>) -> Spell {
+        spell(&[("$t", app(TOKEN))], ins, outs)
+    }
+
+    /// Previous spells: tx `[1; 32]` created 100 tokens in output 0, tx `[2; 32]` has no spell.
+    fn prev_spells() -> BTreeMap<TxId, (Option<NormalizedSpell>, usize)> {
+        let prev_spell = token_spell(
+            vec![input(9, 0)],
+            vec![output(TESTNET_ADDRESS, 1000, tokens(100))],
+        );
+        BTreeMap::from([
+            (TxId([1; 32]), (Some(normalized(&prev_spell)), 1)),
+            (TxId([2; 32]), (None, 1)),
+        ])
+    }
+
+    fn messages(issues: &[LintIssue], severity: Severity) -> Vec<String> {
+        issues
+            .iter()
+            .filter(|issue| issue.severity == severity)
+            .map(|issue| issue.message.clone())
+            .collect()
+    }
+
+    #[test]
+    fn clean_spell() {
+        let spell = token_spell(
+            vec![input(1, 0)],
+            vec![output(TESTNET_ADDRESS, 1000, tokens(100))],
+        );
+        let issues = lint_spell(
+            &spell,
+            &prev_spells(),
+            &BTreeSet::new(),
+            Some(TESTNET_ADDRESS),
+            false,
+        );
+        assert_eq!(issues, vec![]);
+    }
+
+    #[test]
+    fn reports_all_issues() {
+        let mut spell = token_spell(
+            vec![input(1, 0), input(3, 0)],
+            vec![
+                output(TESTNET_ADDRESS, 1000, tokens(90)),
+                output(MAINNET_ADDRESS, 100, tokens(0)),
+            ],
+        );
+        spell.apps.insert(
+            "$u".to_string(),
+            App {
+                tag: NFT,
+                ..app(TOKEN)
+            },
+        );
+        let issues = lint_spell(
+            &spell,
+            &prev_spells(),
+            &BTreeSet::new(),
+            Some(TESTNET_ADDRESS),
+            false,
+        );
+        assert_eq!(
+            messages(&issues, Severity::Error),
+            vec![
+                "output 1: amount 100 is below the dust limit 546".to_string(),
+                format!(
+                    "output 1: address {} is not on the change address network (testnet4)",
+                    MAINNET_ADDRESS
+                ),
+                format!(
+                    "input 1: transaction creating UTXO {} is not supplied",
+                    utxo_id(3, 0)
+                ),
+                format!("output 1: zero amount of token {}", app(TOKEN)),
+            ]
+        );
+        assert_eq!(
+            messages(&issues, Severity::Warning),
+            vec![
+                "app $u is declared but has no charms in the spell's inputs, references or outputs"
+            ]
+        );
+    }
+
+    #[test]
+    fn unbalanced_tokens_need_binaries() {
+        let spell = token_spell(
+            vec![input(1, 0)],
+            vec![output(TESTNET_ADDRESS, 1000, tokens(90))],
+        );
+        let issues = lint_spell(&spell, &prev_spells(), &BTreeSet::new(), None, false);
+        assert_eq!(
+            messages(&issues, Severity::Error),
+            vec![format!(
+                "app {}: token amounts are not balanced and its binary is not supplied",
+                app(TOKEN)
+            )]
+        );
+
+        let issues = lint_spell(
+            &spell,
+            &prev_spells(),
+            &BTreeSet::from([app(TOKEN).vk]),
+            None,
+            false,
+        );
+        assert_eq!(issues, vec![]);
+    }
+
+    #[test]
+    fn duplicate_apps() {
+        let mut spell = token_spell(vec![input(2, 0)], vec![output(TESTNET_ADDRESS, 1000, None)]);
+        spell.apps.insert("$v".to_string(), app(TOKEN));
+        let issues = lint_spell(&spell, &prev_spells(), &BTreeSet::new(), None, false);
+        assert_eq!(
+            messages(&issues, Severity::Error),
+            vec![
+                format!("duplicate app $t, $v: {}", app(TOKEN)),
+                "spell cannot be normalized: duplicate apps".to_string(),
+            ]
+        );
+    }
+}
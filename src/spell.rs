@@ -3,7 +3,7 @@ use crate::{
     cli::{BITCOIN, CARDANO, charms_fee_settings, prove_impl},
     tx::{bitcoin_tx, cardano_tx, txs_by_txid},
     utils,
-    utils::{BoxedSP1Prover, Shared},
+    utils::{BoxedSP1Prover, Shared, proof_cache::committed_spell_hash},
 };
 use anyhow::{anyhow, bail, ensure};
 use ark_bls12_381::Bls12_381;
@@ -26,9 +26,15 @@ pub use charms_client::{
     CURRENT_VERSION, NormalizedCharms, NormalizedSpell, NormalizedTransaction, Proof,
     SpellProverInput, to_tx,
 };
-use charms_client::{MOCK_SPELL_VK, bitcoin_tx::BitcoinTx, tx::Tx, well_formed};
+use charms_client::{
+    MOCK_SPELL_VK,
+    batch::{BATCH_PROOF_PREFIX, BatchProof, batch_public_values, committed_data_hash, spell_hash},
+    bitcoin_tx::BitcoinTx,
+    tx::{EnchantedTx, ProofSystem, Tx},
+    well_formed_detailed,
+};
 use charms_data::{
-    App, AppInput, B32, Charms, Data, TOKEN, Transaction, TxId, UtxoId, is_simple_transfer, util,
+    App, AppInput, B32, Data, TOKEN, Transaction, TxId, UtxoId, is_simple_transfer, util,
 };
 use charms_lib::SPELL_VK;
 use const_format::formatcp;
@@ -38,7 +44,7 @@ use serde::{Deserialize, Serialize};
 use serde_with::{IfIsHumanReadable, base64::Base64, serde_as};
 use sha2::{Digest, Sha256};
 use sp1_prover::{HashableKey, SP1ProvingKey, SP1VerifyingKey};
-use sp1_sdk::{SP1Proof, SP1ProofMode, SP1Stdin};
+use sp1_sdk::{SP1Proof, SP1ProofMode, SP1ProofWithPublicValues, SP1Stdin};
 use std::{
     collections::{BTreeMap, BTreeSet},
     str::FromStr,
@@ -47,315 +53,7 @@ use std::{
 #[cfg(not(feature = "prover"))]
 use utils::retry;
 
-/// Charm as represented in a spell.
-/// Map of `$KEY: data`.
-pub type KeyedCharms = BTreeMap<String, Data>;
-
-/// UTXO as represented in a spell.
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
-pub struct Input {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub utxo_id: Option<UtxoId>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub charms: Option<KeyedCharms>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub beamed_from: Option<UtxoId>,
-}
-
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
-pub struct Output {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub address: Option<String>,
-    #[serde(alias = "sats", skip_serializing_if = "Option::is_none")]
-    pub amount: Option<u64>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub charms: Option<KeyedCharms>,
-    #[serde(alias = "beamed_to", skip_serializing_if = "Option::is_none")]
-    pub beam_to: Option<B32>,
-}
-
-/// Defines how spells are represented in their source form and in CLI outputs,
-/// in both human-friendly (JSON/YAML) and machine-friendly (CBOR) formats.
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct Spell {
-    /// Version of the protocol.
-    pub version: u32,
-
-    /// Apps used in the spell. Map of `$KEY: App`.
-    /// Keys are arbitrary strings. They just need to be unique (inside the spell).
-    pub apps: BTreeMap<String, App>,
-
-    /// Public inputs to the apps for this spell. Map of `$KEY: Data`.
-    #[serde(alias = "public_inputs", skip_serializing_if = "Option::is_none")]
-    pub public_args: Option<BTreeMap<String, Data>>,
-
-    /// Private inputs to the apps for this spell. Map of `$KEY: Data`.
-    #[serde(alias = "private_inputs", skip_serializing_if = "Option::is_none")]
-    pub private_args: Option<BTreeMap<String, Data>>,
-
-    /// Transaction inputs.
-    pub ins: Vec<Input>,
-    /// Reference inputs.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub refs: Option<Vec<Input>>,
-    /// Transaction outputs.
-    pub outs: Vec<Output>,
-}
-
-impl Spell {
-    /// New empty spell.
-    pub fn new() -> Self {
-        Self {
-            version: CURRENT_VERSION,
-            apps: BTreeMap::new(),
-            public_args: None,
-            private_args: None,
-            ins: vec![],
-            refs: None,
-            outs: vec![],
-        }
-    }
-
-    /// Get a [`Transaction`] for the spell.
-    pub fn to_tx(&self) -> anyhow::Result<Transaction> {
-        let ins = self.strings_of_charms(&self.ins)?;
-        let empty_vec = vec![];
-        let refs = self.strings_of_charms(self.refs.as_ref().unwrap_or(&empty_vec))?;
-        let outs = self
-            .outs
-            .iter()
-            .map(|output| self.charms(&output.charms))
-            .collect::<Result<_, _>>()?;
-
-        Ok(Transaction { ins, refs, outs })
-    }
-
-    fn strings_of_charms(&self, inputs: &Vec<Input>) -> anyhow::Result<Vec<(UtxoId, Charms)>> {
-        inputs
-            .iter()
-            .map(|input| {
-                let utxo_id = input
-                    .utxo_id
-                    .as_ref()
-                    .ok_or(anyhow!("missing input utxo_id"))?;
-                let charms = self.charms(&input.charms)?;
-                Ok((utxo_id.clone(), charms))
-            })
-            .collect::<Result<_, _>>()
-    }
-
-    fn charms(&self, charms_opt: &Option<KeyedCharms>) -> anyhow::Result<Charms> {
-        charms_opt
-            .as_ref()
-            .ok_or(anyhow!("missing charms field"))?
-            .iter()
-            .map(|(k, v)| {
-                let app = self.apps.get(k).ok_or(anyhow!("missing app {}", k))?;
-                Ok((app.clone(), Data::from(v)))
-            })
-            .collect::<Result<Charms, _>>()
-    }
-
-    /// Get a [`NormalizedSpell`] and apps' private inputs for the spell.
-    pub fn normalized(
-        &self,
-    ) -> anyhow::Result<(
-        NormalizedSpell,
-        BTreeMap<App, Data>,
-        BTreeMap<UtxoId, UtxoId>,
-    )> {
-        ensure!(self.version == CURRENT_VERSION);
-
-        let empty_map = BTreeMap::new();
-        let keyed_public_inputs = self.public_args.as_ref().unwrap_or(&empty_map);
-
-        let keyed_apps = &self.apps;
-        let apps: BTreeSet<App> = keyed_apps.values().cloned().collect();
-        let app_to_index: BTreeMap<App, u32> = apps.iter().cloned().zip(0..).collect();
-        ensure!(apps.len() == keyed_apps.len(), "duplicate apps");
-
-        let app_public_inputs: BTreeMap<App, Data> = app_inputs(keyed_apps, keyed_public_inputs);
-
-        let ins: Vec<UtxoId> = self
-            .ins
-            .iter()
-            .map(|utxo| utxo.utxo_id.clone().ok_or(anyhow!("missing input utxo_id")))
-            .collect::<Result<_, _>>()?;
-        ensure!(
-            ins.iter().collect::<BTreeSet<_>>().len() == ins.len(),
-            "duplicate inputs"
-        );
-        let ins = Some(ins);
-
-        let refs = self
-            .refs
-            .as_ref()
-            .map(|refs| {
-                refs.iter()
-                    .map(|utxo| utxo.utxo_id.clone().ok_or(anyhow!("missing input utxo_id")))
-                    .collect::<Result<Vec<_>, _>>()
-            })
-            .transpose()?;
-
-        let empty_charm = KeyedCharms::new();
-
-        let outs: Vec<NormalizedCharms> = self
-            .outs
-            .iter()
-            .map(|utxo| {
-                let n_charms = utxo
-                    .charms
-                    .as_ref()
-                    .unwrap_or(&empty_charm)
-                    .iter()
-                    .map(|(k, v)| {
-                        let app = keyed_apps.get(k).ok_or(anyhow!("missing app key"))?;
-                        let i = *app_to_index
-                            .get(app)
-                            .ok_or(anyhow!("app is expected to be in app_to_index"))?;
-                        Ok((i, v.clone()))
-                    })
-                    .collect::<anyhow::Result<NormalizedCharms>>()?;
-                Ok(n_charms)
-            })
-            .collect::<anyhow::Result<_>>()?;
-
-        let beamed_outs: BTreeMap<_, _> = self
-            .outs
-            .iter()
-            .zip(0u32..)
-            .filter_map(|(o, i)| o.beam_to.as_ref().map(|b32| (i, b32.clone())))
-            .collect();
-        let beamed_outs = Some(beamed_outs).filter(|m| !m.is_empty());
-
-        let norm_spell = NormalizedSpell {
-            version: self.version,
-            tx: NormalizedTransaction {
-                ins,
-                refs,
-                outs,
-                beamed_outs,
-            },
-            app_public_inputs,
-            mock: false,
-        };
-
-        let keyed_private_inputs = self.private_args.as_ref().unwrap_or(&empty_map);
-        let app_private_inputs = app_inputs(keyed_apps, keyed_private_inputs);
-
-        let tx_ins_beamed_source_utxos = self
-            .ins
-            .iter()
-            .filter_map(|input| {
-                let tx_in = input
-                    .utxo_id
-                    .as_ref()
-                    .expect("inputs are expected to have utxo_id set")
-                    .clone();
-                input
-                    .beamed_from
-                    .as_ref()
-                    .map(|beam_source_utxo_id| (tx_in, beam_source_utxo_id.clone()))
-            })
-            .collect();
-
-        Ok((norm_spell, app_private_inputs, tx_ins_beamed_source_utxos))
-    }
-
-    /// De-normalize a normalized spell.
-    #[tracing::instrument(level = "debug", skip_all)]
-    pub fn denormalized(norm_spell: &NormalizedSpell) -> anyhow::Result<Self> {
-        let apps = (0..)
-            .zip(norm_spell.app_public_inputs.keys())
-            .map(|(i, app)| (utils::str_index(&i), app.clone()))
-            .collect();
-
-        let public_inputs = match norm_spell
-            .app_public_inputs
-            .values()
-            .enumerate()
-            .filter_map(|(i, data)| match data {
-                data if data.is_empty() => None,
-                data => Some((utils::str_index(&(i as u32)), data.clone())),
-            })
-            .collect::<BTreeMap<_, _>>()
-        {
-            map if map.is_empty() => None,
-            map => Some(map),
-        };
-
-        let Some(norm_spell_ins) = &norm_spell.tx.ins else {
-            bail!("spell must have inputs");
-        };
-        let ins = norm_spell_ins
-            .iter()
-            .map(|utxo_id| Input {
-                utxo_id: Some(utxo_id.clone()),
-                charms: None,
-                beamed_from: None,
-            })
-            .collect();
-
-        let refs = norm_spell.tx.refs.as_ref().map(|refs| {
-            refs.iter()
-                .map(|utxo_id| Input {
-                    utxo_id: Some(utxo_id.clone()),
-                    charms: None,
-                    beamed_from: None,
-                })
-                .collect::<Vec<_>>()
-        });
-
-        let outs = norm_spell
-            .tx
-            .outs
-            .iter()
-            .zip(0u32..)
-            .map(|(n_charms, i)| Output {
-                address: None,
-                amount: None,
-                charms: match n_charms
-                    .iter()
-                    .map(|(i, data)| (utils::str_index(i), data.clone()))
-                    .collect::<KeyedCharms>()
-                {
-                    charms if charms.is_empty() => None,
-                    charms => Some(charms),
-                },
-                beam_to: norm_spell
-                    .tx
-                    .beamed_outs
-                    .as_ref()
-                    .and_then(|beamed_to| beamed_to.get(&i).cloned()),
-            })
-            .collect();
-
-        Ok(Self {
-            version: norm_spell.version,
-            apps,
-            public_args: public_inputs,
-            private_args: None,
-            ins,
-            refs,
-            outs,
-        })
-    }
-}
-
-fn app_inputs(
-    keyed_apps: &BTreeMap<String, App>,
-    keyed_inputs: &BTreeMap<String, Data>,
-) -> BTreeMap<App, Data> {
-    keyed_apps
-        .iter()
-        .map(|(k, app)| {
-            (
-                app.clone(),
-                keyed_inputs.get(k).cloned().unwrap_or_default(),
-            )
-        })
-        .collect()
-}
+pub use charms_client::spell::{Input, KeyedCharms, Output, Spell};
 
 pub trait Prove: Send + Sync {
     /// Prove the correctness of a spell, generate the proof.
@@ -377,6 +75,7 @@ pub trait Prove: Send + Sync {
     ///   by the spell.
     /// - `tx_ins_beamed_source_utxos`: A mapping of input UTXOs to their beaming source UTXOs (if
     ///   the input UTXO has been beamed from another chain).
+    /// - `proof_system`: The SNARK proof system of the final (onchain) proof.
     /// - `expected_cycles`: An optional vector of cycles (`u64`) that represents the desired
     ///   execution cycles or constraints for the proof. If `None`, no specific cycle limit is
     ///   applied.
@@ -404,7 +103,32 @@ pub trait Prove: Send + Sync {
         app_private_inputs: BTreeMap<App, Data>,
         prev_txs: Vec<Tx>,
         tx_ins_beamed_source_utxos: BTreeMap<UtxoId, UtxoId>,
+        proof_system: ProofSystem,
     ) -> anyhow::Result<(NormalizedSpell, Proof, u64)>;
+
+    /// Prove the correctness of several spells with a single proof.
+    ///
+    /// Each spell is checked as in [`Prove::prove`], and the resulting proof (a
+    /// [`BatchProof`][charms_client::batch::BatchProof]) is shared by all transactions of the
+    /// batch.
+    ///
+    /// # Returns
+    /// - `Ok((Vec<(NormalizedSpell, u64)>, Proof))`: the spells in their onchain form (in the same
+    ///   order as `batch`), each with the number of cycles attributed to it, and the shared proof.
+    fn prove_batch(
+        &self,
+        batch: Vec<SpellToProve>,
+        proof_system: ProofSystem,
+    ) -> anyhow::Result<(Vec<(NormalizedSpell, u64)>, Proof)>;
+}
+
+/// Spell to prove as part of a batch, with the data needed to prove it (see [`Prove::prove`]).
+pub struct SpellToProve {
+    pub norm_spell: NormalizedSpell,
+    pub app_binaries: BTreeMap<B32, Vec<u8>>,
+    pub app_private_inputs: BTreeMap<App, Data>,
+    pub prev_txs: Vec<Tx>,
+    pub tx_ins_beamed_source_utxos: BTreeMap<UtxoId, UtxoId>,
 }
 
 impl Prove for Prover {
@@ -415,64 +139,210 @@ impl Prove for Prover {
         app_private_inputs: BTreeMap<App, Data>,
         prev_txs: Vec<Tx>,
         tx_ins_beamed_source_utxos: BTreeMap<UtxoId, UtxoId>,
+        proof_system: ProofSystem,
     ) -> anyhow::Result<(NormalizedSpell, Proof, u64)> {
-        ensure!(
-            !norm_spell.mock,
-            "trying to prove a mock spell with a real prover"
-        );
+        let (proof, _) = self.prove_spell_checker(SpellToProve {
+            norm_spell: norm_spell.clone(),
+            app_binaries,
+            app_private_inputs,
+            prev_txs,
+            tx_ins_beamed_source_utxos,
+        })?;
 
-        let prev_spells = charms_client::prev_spells(&prev_txs, SPELL_VK, false);
-        let tx = to_tx(&norm_spell, &prev_spells, &tx_ins_beamed_source_utxos);
+        let mut stdin = SP1Stdin::new();
+        stdin.write_vec(proof.public_values.to_vec());
+        self.write_spell_checker_proof(&mut stdin, proof);
 
-        let app_binaries = filter_app_binaries(&norm_spell, app_binaries, &tx)?;
+        let (proof, spell_cycles) = self.wrapper_prover_client.get().prove(
+            &self.proof_wrapper_pk,
+            &stdin,
+            proof_mode(proof_system),
+        )?;
+        let norm_spell = clear_inputs(norm_spell);
+        let proof = proof_system.tag(proof.bytes());
 
-        let app_input = match app_binaries.is_empty() {
-            true => None,
-            false => Some(AppInput {
-                app_binaries,
-                app_public_inputs: norm_spell.app_public_inputs.clone(),
-                app_private_inputs,
-            }),
-        };
+        // TODO app_cycles might turn out to be much more expensive than spell_cycles
+        Ok((norm_spell, proof, spell_cycles))
+    }
 
-        let prover_input = SpellProverInput {
-            self_spell_vk: SPELL_VK.to_string(),
-            prev_txs,
-            spell: norm_spell.clone(),
-            tx_ins_beamed_source_utxos,
-            app_input,
-        };
+    fn prove_batch(
+        &self,
+        batch: Vec<SpellToProve>,
+        proof_system: ProofSystem,
+    ) -> anyhow::Result<(Vec<(NormalizedSpell, u64)>, Proof)> {
+        ensure!(!batch.is_empty(), "empty batch");
+        let batch_len = batch.len() as u64;
+
+        let mut norm_spells = vec![];
+        let mut proofs = vec![];
+        let mut spell_hashes = vec![];
+        for spell_to_prove in batch {
+            norm_spells.push(clear_inputs(spell_to_prove.norm_spell.clone()));
+            let (proof, spell_hash) = self.prove_spell_checker(spell_to_prove)?;
+            proofs.push(proof);
+            spell_hashes.push(spell_hash);
+        }
+
+        let public_values_list: Vec<Vec<u8>> = proofs
+            .iter()
+            .map(|proof| proof.public_values.to_vec())
+            .collect();
+
+        let mut stdin = SP1Stdin::new();
+        stdin.write_vec(BATCH_PROOF_PREFIX.to_vec());
+        stdin.write(&public_values_list);
+        for proof in proofs {
+            self.write_spell_checker_proof(&mut stdin, proof);
+        }
+
+        let (proof, spell_cycles) = self.wrapper_prover_client.get().prove(
+            &self.proof_wrapper_pk,
+            &stdin,
+            proof_mode(proof_system),
+        )?;
+        tracing::info!("batch proof generated for {} spells", batch_len);
+        let proof = BatchProof {
+            spell_hashes,
+            proof: proof_system.tag(proof.bytes()),
+        }
+        .to_proof();
+
+        // the wrapper proof is shared: split its cost evenly
+        let spell_cycles = spell_cycles / batch_len;
+        let norm_spells = norm_spells
+            .into_iter()
+            .map(|norm_spell| (norm_spell, spell_cycles))
+            .collect();
+        Ok((norm_spells, proof))
+    }
+}
+
+impl Prover {
+    /// Prove the spell checker run for the spell: the result is to be wrapped by the proof
+    /// wrapper. Returns the compressed proof and the spell's hash (see
+    /// [`spell_hash`][charms_client::batch::spell_hash]).
+    fn prove_spell_checker(
+        &self,
+        spell_to_prove: SpellToProve,
+    ) -> anyhow::Result<(SP1ProofWithPublicValues, B32)> {
+        ensure!(
+            !spell_to_prove.norm_spell.mock,
+            "trying to prove a mock spell with a real prover"
+        );
+
+        let (prover_input, parent_proofs) = spell_checker_input(spell_to_prove)?;
 
         let mut stdin = SP1Stdin::new();
         stdin.write_vec(util::write(&prover_input)?);
+        for proof in parent_proofs {
+            self.write_spell_checker_proof(&mut stdin, proof);
+        }
 
         let (proof, _) = self.spell_prover_client.get().prove(
             &self.spell_checker_pk,
             &stdin,
             SP1ProofMode::Compressed,
         )?;
-        let SP1Proof::Compressed(compressed_proof) = proof.proof else {
-            unreachable!()
-        };
         tracing::info!("spell proof generated");
 
-        let mut stdin = SP1Stdin::new();
-        stdin.write_vec(proof.public_values.to_vec());
-        stdin.write_proof(*compressed_proof, self.spell_checker_vk.vk.clone());
+        let spell_hash =
+            committed_spell_hash(&proof).ok_or(anyhow!("malformed spell checker public values"))?;
+        utils::proof_cache().put(spell_hash.clone(), proof.clone());
+        Ok((proof, spell_hash))
+    }
 
-        let (proof, spell_cycles) = self.wrapper_prover_client.get().prove(
-            &self.proof_wrapper_pk,
-            &stdin,
-            SP1ProofMode::Groth16,
-        )?;
-        let norm_spell = clear_inputs(norm_spell);
-        let proof = proof.bytes();
+    fn write_spell_checker_proof(&self, stdin: &mut SP1Stdin, proof: SP1ProofWithPublicValues) {
+        write_compressed_proof(stdin, proof, &self.spell_checker_vk);
+    }
+}
 
-        // TODO app_cycles might turn out to be much more expensive than spell_cycles
-        Ok((norm_spell, proof, spell_cycles))
+/// Input of the spell checker for the spell, as [`Prover`] supplies it: the [`SpellProverInput`]
+/// and the compressed proofs of the parent spells it verifies recursively (in `prev_txs` order).
+///
+/// [`crate::execute::execute_spell`] runs the spell checker with the same input.
+pub(crate) fn spell_checker_input(
+    spell_to_prove: SpellToProve,
+) -> anyhow::Result<(SpellProverInput, Vec<SP1ProofWithPublicValues>)> {
+    let SpellToProve {
+        norm_spell,
+        app_binaries,
+        app_private_inputs,
+        prev_txs,
+        tx_ins_beamed_source_utxos,
+    } = spell_to_prove;
+
+    let prev_spells =
+        charms_client::prev_spells_cached(&prev_txs, SPELL_VK, false, utils::spell_cache());
+    let tx = to_tx(&norm_spell, &prev_spells, &tx_ins_beamed_source_utxos);
+
+    let app_binaries = filter_app_binaries(&norm_spell, app_binaries, &tx)?;
+
+    // app contracts are run by the spell checker: proving them separately needs the
+    // `charms-app-checker` binary (see `charms_client::APP_CHECKER_VK`)
+    let app_input = match app_binaries.is_empty() {
+        true => None,
+        false => Some(AppInput {
+            app_binaries,
+            app_public_inputs: norm_spell.app_public_inputs.clone(),
+            app_private_inputs,
+        }),
+    };
+
+    // parent spells proven by us are verified recursively: much cheaper than verifying their
+    // Groth16 proofs in the spell checker
+    let parent_proofs: Vec<(TxId, SP1ProofWithPublicValues)> = prev_txs
+        .iter()
+        .filter_map(|tx| {
+            let tx_id = tx.tx_id();
+            let (Some(parent_spell), _) = prev_spells.get(&tx_id)? else {
+                return None;
+            };
+            if parent_spell.version != CURRENT_VERSION || parent_spell.mock {
+                return None;
+            }
+            let proof = utils::proof_cache().get(&spell_hash(SPELL_VK, parent_spell))?;
+            Some((tx_id, proof))
+        })
+        .collect();
+    tracing::info!(
+        "parent spells verified recursively: {}",
+        parent_proofs.len()
+    );
+
+    let prover_input = SpellProverInput {
+        self_spell_vk: SPELL_VK.to_string(),
+        prev_txs,
+        spell: norm_spell,
+        tx_ins_beamed_source_utxos,
+        app_input,
+        spell_checker_vk: SPELL_CHECKER_VK,
+        recursive_prev_txids: parent_proofs.iter().map(|(tx_id, _)| *tx_id).collect(),
+        proven_apps: BTreeSet::new(),
+    };
+    let parent_proofs = parent_proofs.into_iter().map(|(_, proof)| proof).collect();
+    Ok((prover_input, parent_proofs))
+}
+
+/// SP1 proof mode of the proof wrapper proof for the SNARK proof system.
+fn proof_mode(proof_system: ProofSystem) -> SP1ProofMode {
+    match proof_system {
+        ProofSystem::Groth16 => SP1ProofMode::Groth16,
+        ProofSystem::Plonk => SP1ProofMode::Plonk,
     }
 }
 
+/// Supply the compressed proof to the zkVM program for (deferred) recursive verification.
+pub(crate) fn write_compressed_proof(
+    stdin: &mut SP1Stdin,
+    proof: SP1ProofWithPublicValues,
+    vk: &SP1VerifyingKey,
+) {
+    let SP1Proof::Compressed(compressed_proof) = proof.proof else {
+        unreachable!()
+    };
+    stdin.write_proof(*compressed_proof, vk.vk.clone());
+}
+
 fn make_mock(mut norm_spell: NormalizedSpell) -> NormalizedSpell {
     norm_spell.mock = true;
     norm_spell
@@ -491,56 +361,116 @@ impl Prove for MockProver {
         app_private_inputs: BTreeMap<App, Data>,
         prev_txs: Vec<Tx>,
         tx_ins_beamed_source_utxos: BTreeMap<UtxoId, UtxoId>,
+        proof_system: ProofSystem,
     ) -> anyhow::Result<(NormalizedSpell, Proof, u64)> {
-        let norm_spell = make_mock(norm_spell);
+        let (norm_spell, app_cycles) = self.check_spell(SpellToProve {
+            norm_spell,
+            app_binaries,
+            app_private_inputs,
+            prev_txs,
+            tx_ins_beamed_source_utxos,
+        })?;
 
-        let prev_spells = charms_client::prev_spells(&prev_txs, SPELL_VK, true);
-        let tx = to_tx(&norm_spell, &prev_spells, &tx_ins_beamed_source_utxos);
+        let committed_data = util::write(&(MOCK_SPELL_VK, norm_spell.clone()))?;
 
-        let app_binaries = filter_app_binaries(&norm_spell, app_binaries, &tx)?;
+        let (proof, spell_cycles) = (proof_system.tag(mock_proof(&committed_data)?), 0);
 
-        let cycles = self.app_runner.run_all(
-            &app_binaries,
-            &tx,
-            &norm_spell.app_public_inputs,
-            &app_private_inputs,
-        )?;
+        let norm_spell = clear_inputs(norm_spell);
 
-        let app_cycles: u64 = cycles.iter().sum();
+        Ok((norm_spell, proof, app_cycles + spell_cycles))
+    }
 
-        // prove charms-spell-checker run
-        ensure!(
-            charms_client::well_formed(&norm_spell, &prev_spells, &tx_ins_beamed_source_utxos),
-            "spell is not well-formed"
-        );
+    fn prove_batch(
+        &self,
+        batch: Vec<SpellToProve>,
+        proof_system: ProofSystem,
+    ) -> anyhow::Result<(Vec<(NormalizedSpell, u64)>, Proof)> {
+        ensure!(!batch.is_empty(), "empty batch");
+
+        let mut norm_spells = vec![];
+        let mut spell_hashes = vec![];
+        for spell_to_prove in batch {
+            let (norm_spell, app_cycles) = self.check_spell(spell_to_prove)?;
+            let committed_data = util::write(&(MOCK_SPELL_VK, &norm_spell))?;
+            spell_hashes.push(committed_data_hash(&committed_data));
+            norm_spells.push((clear_inputs(norm_spell), app_cycles));
+        }
 
-        // replace with good randomness in non-mock mode
-        let mut rng = ark_std::rand::rngs::StdRng::seed_from_u64(test_rng().next_u64());
+        let proof = BatchProof {
+            proof: proof_system.tag(mock_proof(&batch_public_values(&spell_hashes))?),
+            spell_hashes,
+        }
+        .to_proof();
 
-        // Create parameters for our circuit
-        let pk = load_pk()?;
+        Ok((norm_spells, proof))
+    }
+}
 
-        let committed_data = util::write(&(MOCK_SPELL_VK, norm_spell.clone()))?;
+impl MockProver {
+    /// Run the apps and check the spell is well-formed: everything the spell checker would do.
+    /// Returns the mock spell and the app cycles.
+    fn check_spell(&self, spell_to_prove: SpellToProve) -> anyhow::Result<(NormalizedSpell, u64)> {
+        let SpellToProve {
+            norm_spell,
+            mut app_binaries,
+            app_private_inputs,
+            prev_txs,
+            tx_ins_beamed_source_utxos,
+        } = spell_to_prove;
+        let norm_spell = make_mock(norm_spell);
 
-        let field_elements = Sha256::digest(&committed_data)
-            .to_field_elements()
-            .expect("non-empty vector is expected");
-        let circuit = DummyCircuit {
-            a: Some(field_elements[0]),
+        let prev_spells =
+            charms_client::prev_spells_cached(&prev_txs, SPELL_VK, true, utils::spell_cache());
+
+        // same checks as the spell checker's (with app contracts run by it)
+        let mut app_cycles = 0;
+        let mut run_app_contracts = |tx: &Transaction| {
+            let app_binaries =
+                filter_app_binaries(&norm_spell, std::mem::take(&mut app_binaries), tx)?;
+            let cycles = self.app_runner.run_all(
+                &app_binaries,
+                tx,
+                &norm_spell.app_public_inputs,
+                &app_private_inputs,
+            )?;
+            app_cycles = cycles.iter().sum();
+            Ok(())
         };
+        charms_client::is_correct_detailed(
+            &norm_spell,
+            &prev_spells,
+            &tx_ins_beamed_source_utxos,
+            Some(&mut run_app_contracts),
+            &BTreeSet::new(),
+            &|_, _, _| unreachable!("no app contract proofs in mock mode"),
+        )?;
 
-        let proof = Groth16::<Bls12_381>::prove(&pk, circuit, &mut rng)?;
-        let mut proof_bytes = vec![];
-        proof.serialize_compressed(&mut proof_bytes)?;
-
-        let (proof, spell_cycles) = (proof_bytes, 0);
-
-        let norm_spell = clear_inputs(norm_spell);
-
-        Ok((norm_spell, proof, app_cycles + spell_cycles))
+        Ok((norm_spell, app_cycles))
     }
 }
 
+/// Mock Groth16 proof committing to `committed_data`. Mock proofs of both proof systems are
+/// Groth16 proofs (see [`charms_client::tx::verify_snark_proof`]).
+fn mock_proof(committed_data: &[u8]) -> anyhow::Result<Proof> {
+    // replace with good randomness in non-mock mode
+    let mut rng = ark_std::rand::rngs::StdRng::seed_from_u64(test_rng().next_u64());
+
+    // Create parameters for our circuit
+    let pk = load_pk()?;
+
+    let field_elements = Sha256::digest(committed_data)
+        .to_field_elements()
+        .expect("non-empty vector is expected");
+    let circuit = DummyCircuit {
+        a: Some(field_elements[0]),
+    };
+
+    let proof = Groth16::<Bls12_381>::prove(&pk, circuit, &mut rng)?;
+    let mut proof_bytes = vec![];
+    proof.serialize_compressed(&mut proof_bytes)?;
+    Ok(proof_bytes)
+}
+
 fn load_pk<E: Pairing>() -> anyhow::Result<ProvingKey<E>> {
     ProvingKey::deserialize_compressed(MOCK_GROTH16_PK)
         .map_err(|e| anyhow!("Failed to deserialize proving key: {}", e))
@@ -600,6 +530,13 @@ pub trait ProveSpellTx: Send + Sync {
         &self,
         prove_request: ProveRequest,
     ) -> impl Future<Output = anyhow::Result<Vec<String>>>;
+
+    /// Prove several spells with a single shared proof: returns the transactions for each of the
+    /// requests (in the same order).
+    fn prove_spell_txs(
+        &self,
+        prove_requests: Vec<ProveRequest>,
+    ) -> impl Future<Output = anyhow::Result<Vec<Vec<String>>>>;
 }
 
 pub struct ProveSpellTxImpl {
@@ -607,6 +544,7 @@ pub struct ProveSpellTxImpl {
 
     pub charms_fee_settings: Option<CharmsFee>,
     pub charms_prove_api_url: String,
+    pub charms_prove_batch_api_url: String,
 
     pub prover: Box<dyn Prove>,
     #[cfg(not(feature = "prover"))]
@@ -648,9 +586,13 @@ pub struct ProveRequest {
     pub change_address: String,
     pub fee_rate: f64,
     pub chain: String,
+    /// SNARK proof system of the spell proof.
+    #[serde(default)]
+    pub proof_system: ProofSystem,
 }
 
 pub struct Prover {
+    pub app_prover: Arc<app::Prover>,
     pub spell_prover_client: Arc<Shared<BoxedSP1Prover>>,
     pub wrapper_prover_client: Arc<Shared<BoxedSP1Prover>>,
     pub spell_checker_pk: SP1ProvingKey,
@@ -666,6 +608,7 @@ impl Prover {
         assert_eq!(SPELL_VK, vk.bytes32().as_str());
         Self {
             spell_prover_client: app_prover.sp1_client.clone(),
+            app_prover,
             wrapper_prover_client: prover_client,
             spell_checker_pk,
             spell_checker_vk,
@@ -679,30 +622,24 @@ pub struct MockProver {
 }
 
 impl ProveSpellTxImpl {
-    async fn do_prove_spell_tx(&self, prove_request: ProveRequest) -> anyhow::Result<Vec<String>> {
+    async fn do_prove_spell_tx(
+        &self,
+        mut prove_request: ProveRequest,
+    ) -> anyhow::Result<Vec<String>> {
         let total_app_cycles = self.validate_prove_request(&prove_request)?;
-        let ProveRequest {
-            spell,
-            binaries,
-            prev_txs,
-            funding_utxo,
-            funding_utxo_value,
-            change_address,
-            fee_rate,
-            chain,
-        } = prove_request;
 
-        let prev_txs = from_hex_txs(&prev_txs)?;
-        let prev_txs_by_id = txs_by_txid(&prev_txs);
+        let prev_txs = from_hex_txs(&prove_request.prev_txs)?;
 
-        let (norm_spell, app_private_inputs, tx_ins_beamed_source_utxos) = spell.normalized()?;
+        let (norm_spell, app_private_inputs, tx_ins_beamed_source_utxos) =
+            prove_request.spell.normalized()?;
 
         let (norm_spell, proof, proof_app_cycles) = self.prover.prove(
             norm_spell,
-            binaries,
+            std::mem::take(&mut prove_request.binaries),
             app_private_inputs,
             prev_txs,
             tx_ins_beamed_source_utxos,
+            prove_request.proof_system,
         )?;
 
         let total_cycles = if !self.mock {
@@ -713,21 +650,95 @@ impl ProveSpellTxImpl {
 
         tracing::info!("proof generated. total app cycles: {}", total_cycles);
 
+        self.make_transactions(&prove_request, &norm_spell, &proof, total_cycles)
+    }
+
+    /// Prove several spells with a single (batch) proof, and build the transactions for each of
+    /// them. All transactions reference the same proof.
+    async fn do_prove_spell_txs(
+        &self,
+        mut prove_requests: Vec<ProveRequest>,
+    ) -> anyhow::Result<Vec<Vec<String>>> {
+        let total_app_cycles = prove_requests
+            .iter()
+            .map(|prove_request| self.validate_prove_request(prove_request))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let mut batch = vec![];
+        for prove_request in &mut prove_requests {
+            let (norm_spell, app_private_inputs, tx_ins_beamed_source_utxos) =
+                prove_request.spell.normalized()?;
+            batch.push(SpellToProve {
+                norm_spell,
+                app_binaries: std::mem::take(&mut prove_request.binaries),
+                app_private_inputs,
+                prev_txs: from_hex_txs(&prove_request.prev_txs)?,
+                tx_ins_beamed_source_utxos,
+            });
+        }
+
+        let proof_system = prove_requests[0].proof_system;
+        ensure!(
+            prove_requests
+                .iter()
+                .all(|prove_request| prove_request.proof_system == proof_system),
+            "all spells of a batch must use the same proof system"
+        );
+        let (norm_spells, proof) = self.prover.prove_batch(batch, proof_system)?;
+
+        prove_requests
+            .iter()
+            .zip(norm_spells)
+            .zip(total_app_cycles)
+            .map(
+                |((prove_request, (norm_spell, proof_app_cycles)), total_app_cycles)| {
+                    let total_cycles = if !self.mock {
+                        total_app_cycles
+                    } else {
+                        proof_app_cycles // mock prover computes app run cycles
+                    };
+                    self.make_transactions(prove_request, &norm_spell, &proof, total_cycles)
+                },
+            )
+            .collect()
+    }
+
+    fn make_transactions(
+        &self,
+        prove_request: &ProveRequest,
+        norm_spell: &NormalizedSpell,
+        proof: &Proof,
+        total_cycles: u64,
+    ) -> anyhow::Result<Vec<String>> {
+        let ProveRequest {
+            spell,
+            prev_txs,
+            funding_utxo,
+            funding_utxo_value,
+            change_address,
+            fee_rate,
+            chain,
+            ..
+        } = prove_request;
+
+        let prev_txs = from_hex_txs(prev_txs)?;
+        let prev_txs_by_id = txs_by_txid(&prev_txs);
+
         // Serialize spell into CBOR
-        let spell_data = util::write(&(&norm_spell, &proof))?;
+        let spell_data = util::write(&(norm_spell, proof))?;
 
         let charms_fee = self.charms_fee_settings.clone();
 
         match chain.as_str() {
             BITCOIN => {
                 let txs = bitcoin_tx::make_transactions(
-                    &spell,
-                    funding_utxo,
-                    funding_utxo_value,
-                    &change_address,
+                    spell,
+                    funding_utxo.clone(),
+                    *funding_utxo_value,
+                    change_address,
                     &prev_txs_by_id,
                     &spell_data,
-                    fee_rate,
+                    *fee_rate,
                     charms_fee,
                     total_cycles,
                 )?;
@@ -735,10 +746,10 @@ impl ProveSpellTxImpl {
             }
             CARDANO => {
                 let txs = cardano_tx::make_transactions(
-                    &spell,
-                    funding_utxo,
-                    funding_utxo_value,
-                    &change_address,
+                    spell,
+                    funding_utxo.clone(),
+                    *funding_utxo_value,
+                    change_address,
                     &spell_data,
                     &prev_txs_by_id,
                     charms_fee,
@@ -751,7 +762,7 @@ impl ProveSpellTxImpl {
     }
 }
 
-fn filter_app_binaries(
+pub(crate) fn filter_app_binaries(
     norm_spell: &NormalizedSpell,
     app_binaries: BTreeMap<B32, Vec<u8>>,
     tx: &Transaction,
@@ -778,6 +789,8 @@ fn filter_app_binaries(
 
 const CHARMS_PROVE_API_URL: &'static str =
     formatcp!("https://v{CURRENT_VERSION}.charms.dev/spells/prove");
+const CHARMS_PROVE_BATCH_API_URL: &'static str =
+    formatcp!("https://v{CURRENT_VERSION}.charms.dev/spells/prove-batch");
 
 impl ProveSpellTx for ProveSpellTxImpl {
     #[tracing::instrument(level = "debug")]
@@ -789,6 +802,10 @@ impl ProveSpellTx for ProveSpellTxImpl {
             .unwrap_or(CHARMS_PROVE_API_URL.to_string());
         tracing::info!(charms_prove_api_url);
 
+        let charms_prove_batch_api_url = std::env::var("CHARMS_PROVE_BATCH_API_URL")
+            .ok()
+            .unwrap_or(CHARMS_PROVE_BATCH_API_URL.to_string());
+
         let prover = prove_impl(mock);
 
         #[cfg(not(feature = "prover"))]
@@ -804,6 +821,7 @@ impl ProveSpellTx for ProveSpellTxImpl {
             mock,
             charms_fee_settings,
             charms_prove_api_url,
+            charms_prove_batch_api_url,
             prover,
             #[cfg(not(feature = "prover"))]
             client,
@@ -815,6 +833,14 @@ impl ProveSpellTx for ProveSpellTxImpl {
         self.do_prove_spell_tx(prove_request).await
     }
 
+    #[cfg(feature = "prover")]
+    async fn prove_spell_txs(
+        &self,
+        prove_requests: Vec<ProveRequest>,
+    ) -> anyhow::Result<Vec<Vec<String>>> {
+        self.do_prove_spell_txs(prove_requests).await
+    }
+
     #[cfg(not(feature = "prover"))]
     #[tracing::instrument(level = "info", skip_all)]
     async fn prove_spell_tx(&self, prove_request: ProveRequest) -> anyhow::Result<Vec<String>> {
@@ -823,13 +849,36 @@ impl ProveSpellTx for ProveSpellTxImpl {
         }
 
         self.validate_prove_request(&prove_request)?;
+        self.post(&self.charms_prove_api_url, &prove_request).await
+    }
+
+    #[cfg(not(feature = "prover"))]
+    #[tracing::instrument(level = "info", skip_all)]
+    async fn prove_spell_txs(
+        &self,
+        prove_requests: Vec<ProveRequest>,
+    ) -> anyhow::Result<Vec<Vec<String>>> {
+        if self.mock {
+            return Self::do_prove_spell_txs(self, prove_requests).await;
+        }
+
+        for prove_request in &prove_requests {
+            self.validate_prove_request(prove_request)?;
+        }
+        self.post(&self.charms_prove_batch_api_url, &prove_requests)
+            .await
+    }
+}
+
+#[cfg(not(feature = "prover"))]
+impl ProveSpellTxImpl {
+    async fn post<Req: Serialize, Resp: serde::de::DeserializeOwned>(
+        &self,
+        url: &str,
+        request: &Req,
+    ) -> anyhow::Result<Resp> {
         let response = retry(0, || async {
-            let response = self
-                .client
-                .post(&self.charms_prove_api_url)
-                .json(&prove_request)
-                .send()
-                .await?;
+            let response = self.client.post(url).json(request).send().await?;
             if response.status().is_server_error() {
                 bail!("server error: {}", response.status());
             }
@@ -841,32 +890,51 @@ impl ProveSpellTx for ProveSpellTxImpl {
             let body = response.text().await?;
             bail!("client error: {}: {}", status, body);
         }
-        let txs: Vec<String> = response.json().await?;
-        Ok(txs)
+        Ok(response.json().await?)
     }
 }
 
 pub fn ensure_no_zero_amounts(norm_spell: &NormalizedSpell) -> anyhow::Result<()> {
+    if let Some((_, app)) = zero_amounts(norm_spell)?.first() {
+        bail!("zero output amount for app {}", app);
+    }
+    Ok(())
+}
+
+/// Zero token amounts in the spell's outputs: `(output index, app)` pairs.
+pub fn zero_amounts(norm_spell: &NormalizedSpell) -> anyhow::Result<Vec<(usize, &App)>> {
     let apps: Vec<_> = norm_spell
         .app_public_inputs
         .iter()
         .map(|(app, _)| app)
         .collect();
-    for out in &norm_spell.tx.outs {
+    let mut zero_amounts = vec![];
+    for (out_index, out) in norm_spell.tx.outs.iter().enumerate() {
         for (i, data) in out {
             let app = apps
                 .get(*i as usize)
                 .ok_or(anyhow!("no app for index {}", i))?;
-            if app.tag == TOKEN {
-                ensure!(
-                    data.value::<u64>()? != 0,
-                    "zero output amount for app {}",
-                    app
-                );
-            };
+            if app.tag == TOKEN && data.value::<u64>()? == 0 {
+                zero_amounts.push((out_index, *app));
+            }
         }
     }
-    Ok(())
+    Ok(zero_amounts)
+}
+
+/// Bitcoin network of the change address: mainnet or testnet4 (or regtest, in mock mode).
+pub fn bitcoin_network(change_address: &str, mock: bool) -> anyhow::Result<Network> {
+    let change_address = bitcoin::Address::from_str(change_address)?;
+    let network = match &change_address {
+        a if a.is_valid_for_network(Network::Bitcoin) => Network::Bitcoin,
+        a if a.is_valid_for_network(Network::Testnet4) => Network::Testnet4,
+        a if a.is_valid_for_network(Network::Regtest) && mock => Network::Regtest,
+        _ => bail!(
+            "Unsupported network of change address: {:?}",
+            change_address
+        ),
+    };
+    Ok(network)
 }
 
 fn ensure_all_prev_txs_are_present(
@@ -895,6 +963,27 @@ fn ensure_all_prev_txs_are_present(
 
 impl ProveSpellTxImpl {
     pub fn validate_prove_request(&self, prove_request: &ProveRequest) -> anyhow::Result<u64> {
+        let total_cycles = self.check_prove_request(prove_request)?;
+
+        if prove_request.chain == BITCOIN {
+            let charms_fee = get_charms_fee(&self.charms_fee_settings, total_cycles).to_sat();
+            let total_sats_in = bitcoin_sats_in(prove_request)?;
+            let total_sats_out = bitcoin_sats_out(prove_request);
+            let funding_utxo_sats = prove_request.funding_utxo_value;
+
+            ensure!(
+                total_sats_in + funding_utxo_sats > total_sats_out + charms_fee,
+                "total input value must be greater than total output value plus charms fee"
+            );
+
+            tracing::info!(total_sats_in, funding_utxo_sats, total_sats_out, charms_fee);
+        }
+        Ok(total_cycles)
+    }
+
+    /// Everything [`Self::validate_prove_request`] checks, except that the inputs and funding
+    /// cover the outputs and the charms fee. Returns the total cycles spent running app contracts.
+    pub(crate) fn check_prove_request(&self, prove_request: &ProveRequest) -> anyhow::Result<u64> {
         let prev_txs = &prove_request.prev_txs;
         let prev_txs = from_hex_txs(&prev_txs)?;
         let prev_txs_by_id = txs_by_txid(&prev_txs);
@@ -904,7 +993,8 @@ impl ProveSpellTxImpl {
         ensure_no_zero_amounts(&norm_spell)?;
         ensure_all_prev_txs_are_present(&norm_spell, &tx_ins_beamed_source_utxos, &prev_txs_by_id)?;
 
-        let prev_spells = charms_client::prev_spells(&prev_txs, SPELL_VK, self.mock);
+        let prev_spells =
+            charms_client::prev_spells_cached(&prev_txs, SPELL_VK, self.mock, utils::spell_cache());
 
         let tx = to_tx(&norm_spell, &prev_spells, &tx_ins_beamed_source_utxos);
         // prove charms-app-checker run
@@ -915,67 +1005,17 @@ impl ProveSpellTxImpl {
             &app_private_inputs,
         )?;
         let total_cycles = cycles.iter().sum();
-        ensure!(well_formed(
-            &norm_spell,
-            &prev_spells,
-            &tx_ins_beamed_source_utxos
-        ));
+        well_formed_detailed(&norm_spell, &prev_spells, &tx_ins_beamed_source_utxos)
+            .map_err(|e| anyhow!("spell is not well-formed: {}", e))?;
 
         match prove_request.chain.as_str() {
             BITCOIN => {
-                let change_address = bitcoin::Address::from_str(&prove_request.change_address)?;
-
-                let network = match &change_address {
-                    a if a.is_valid_for_network(Network::Bitcoin) => Network::Bitcoin,
-                    a if a.is_valid_for_network(Network::Testnet4) => Network::Testnet4,
-                    a if a.is_valid_for_network(Network::Regtest) && self.mock => Network::Regtest,
-                    _ => bail!(
-                        "Unsupported network of change address: {:?}",
-                        change_address
-                    ),
-                };
+                let network = bitcoin_network(&prove_request.change_address, self.mock)?;
                 ensure!(prove_request.spell.outs.iter().all(|o| {
                     o.address.as_ref().is_some_and(|a| {
                         bitcoin::Address::from_str(a).is_ok_and(|a| a.is_valid_for_network(network))
                     })
                 }));
-
-                let charms_fee = get_charms_fee(&self.charms_fee_settings, total_cycles).to_sat();
-
-                let total_sats_in: u64 = (&prove_request.spell.ins)
-                    .iter()
-                    .map(|i| {
-                        let utxo_id = i.utxo_id.as_ref().expect("utxo_id is expected to be Some");
-                        prev_txs_by_id
-                            .get(&utxo_id.0)
-                            .and_then(|prev_tx| {
-                                if let Tx::Bitcoin(BitcoinTx(prev_tx)) = prev_tx {
-                                    prev_tx
-                                        .output
-                                        .get(utxo_id.1 as usize)
-                                        .map(|o| o.value.to_sat())
-                                } else {
-                                    None
-                                }
-                            })
-                            .ok_or(anyhow!("utxo not found in prev_txs: {}", utxo_id))
-                    })
-                    .collect::<anyhow::Result<Vec<_>>>()?
-                    .iter()
-                    .sum();
-                let total_sats_out: u64 = (&prove_request.spell.outs)
-                    .iter()
-                    .map(|o| o.amount.unwrap_or_default())
-                    .sum();
-
-                let funding_utxo_sats = prove_request.funding_utxo_value;
-
-                ensure!(
-                    total_sats_in + funding_utxo_sats > total_sats_out + charms_fee,
-                    "total input value must be greater than total output value plus charms fee"
-                );
-
-                tracing::info!(total_sats_in, funding_utxo_sats, total_sats_out, charms_fee);
             }
             CARDANO => {
                 // TODO
@@ -987,12 +1027,47 @@ impl ProveSpellTxImpl {
     }
 }
 
+/// Total value (in sats) of the spell's inputs: from the Bitcoin transactions in `prev_txs`.
+pub(crate) fn bitcoin_sats_in(prove_request: &ProveRequest) -> anyhow::Result<u64> {
+    let prev_txs_by_id = txs_by_txid(&from_hex_txs(&prove_request.prev_txs)?);
+    let sats_in = (&prove_request.spell.ins)
+        .iter()
+        .map(|i| {
+            let utxo_id = i.utxo_id.as_ref().expect("utxo_id is expected to be Some");
+            prev_txs_by_id
+                .get(&utxo_id.0)
+                .and_then(|prev_tx| {
+                    if let Tx::Bitcoin(BitcoinTx(prev_tx)) = prev_tx {
+                        prev_tx
+                            .output
+                            .get(utxo_id.1 as usize)
+                            .map(|o| o.value.to_sat())
+                    } else {
+                        None
+                    }
+                })
+                .ok_or(anyhow!("utxo not found in prev_txs: {}", utxo_id))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?
+        .iter()
+        .sum();
+    Ok(sats_in)
+}
+
+/// Total value (in sats) of the spell's outputs.
+pub(crate) fn bitcoin_sats_out(prove_request: &ProveRequest) -> u64 {
+    (&prove_request.spell.outs)
+        .iter()
+        .map(|o| o.amount.unwrap_or_default())
+        .sum()
+}
+
 pub fn from_hex_txs(prev_txs: &[String]) -> anyhow::Result<Vec<Tx>> {
     prev_txs
         .iter()
         .map(|s| s.trim())
         .filter(|s| !s.is_empty())
-        .map(|tx_hex| Tx::from_hex(tx_hex))
+        .map(|tx_hex| Ok(Tx::from_hex(tx_hex)?))
         .collect()
 }
 
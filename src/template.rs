@@ -0,0 +1,192 @@
+use anyhow::{Result, anyhow, bail, ensure};
+use charms_client::app_vk;
+use sha2::{Digest, Sha256};
+use std::{collections::BTreeMap, path::Path};
+
+/// Values of template variables, by name.
+pub type Vars = BTreeMap<String, String>;
+
+/// Render the spell template: replace `${...}` placeholders with their values.
+///
+/// Supported placeholders:
+/// - `${var}`: value of variable `var`,
+/// - `${sha256(arg)}`: hex-encoded SHA-256 hash of `arg` (e.g. of a UTXO ID, for app identities),
+/// - `${app_vk(arg)}`: verification key of the app with Wasm binary at path `arg`.
+///
+/// `arg` is either a variable name or a string literal in double quotes. `$${` is rendered as a
+/// literal `${`. All missing variables are reported in the error.
+pub fn render(template: &str, vars: &Vars) -> Result<String> {
+    let mut output = String::with_capacity(template.len());
+    let mut missing = vec![];
+    let mut rest = template;
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            // escaped: `$${`
+            output.push_str(&rest[..start - 1]);
+            output.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        output.push_str(&rest[..start]);
+        let Some(len) = rest[start + 2..].find('}') else {
+            bail!("unterminated placeholder: {}", &rest[start..]);
+        };
+        let expr = rest[start + 2..start + 2 + len].trim();
+        match eval(expr, vars) {
+            Ok(value) => output.push_str(&value),
+            Err(EvalError::MissingVar(name)) => missing.push(name),
+            Err(EvalError::Other(e)) => bail!("placeholder ${{{}}}: {}", expr, e),
+        }
+        rest = &rest[start + 2 + len + 1..];
+    }
+    output.push_str(rest);
+
+    ensure!(
+        missing.is_empty(),
+        "missing template variables: {}",
+        missing.join(", ")
+    );
+    Ok(output)
+}
+
+/// Read the spell template file and render it with `vars`.
+pub fn render_file(path: &Path, vars: &Vars) -> Result<String> {
+    let template =
+        std::fs::read_to_string(path).map_err(|e| anyhow!("error reading {:?}: {}", path, e))?;
+    render(&template, vars).map_err(|e| anyhow!("spell template {:?}: {}", path, e))
+}
+
+/// Parse a `key=value` variable definition.
+pub fn parse_var(s: &str) -> Result<(String, String)> {
+    let Some((name, value)) = s.split_once('=') else {
+        bail!("expected key=value, got: {}", s);
+    };
+    ensure!(is_var_name(name), "invalid variable name: {}", name);
+    Ok((name.to_string(), value.to_string()))
+}
+
+/// Read variables from a YAML (or JSON) map of names to (scalar) values.
+pub fn read_vars_file(path: &Path) -> Result<Vars> {
+    let map: BTreeMap<String, serde_yaml::Value> = serde_yaml::from_slice(
+        &std::fs::read(path).map_err(|e| anyhow!("error reading {:?}: {}", path, e))?,
+    )?;
+    map.into_iter()
+        .map(|(name, value)| {
+            ensure!(is_var_name(&name), "invalid variable name: {}", name);
+            let value = match value {
+                serde_yaml::Value::String(s) => s,
+                serde_yaml::Value::Number(n) => n.to_string(),
+                serde_yaml::Value::Bool(b) => b.to_string(),
+                _ => bail!("variable {} must have a scalar value", name),
+            };
+            Ok((name, value))
+        })
+        .collect()
+}
+
+enum EvalError {
+    MissingVar(String),
+    Other(anyhow::Error),
+}
+
+fn eval(expr: &str, vars: &Vars) -> Result<String, EvalError> {
+    if is_var_name(expr) {
+        return var(expr, vars);
+    }
+    let Some((func, arg)) = expr.strip_suffix(')').and_then(|call| call.split_once('(')) else {
+        return Err(EvalError::Other(anyhow!("invalid expression")));
+    };
+    let arg = arg.trim();
+    let arg = match arg.strip_prefix('"').and_then(|a| a.strip_suffix('"')) {
+        Some(literal) => literal.to_string(),
+        None if is_var_name(arg) => var(arg, vars)?,
+        None => return Err(EvalError::Other(anyhow!("invalid argument: {}", arg))),
+    };
+    match func.trim() {
+        "sha256" => Ok(hex::encode(Sha256::digest(arg.as_bytes()))),
+        "app_vk" => {
+            let binary = std::fs::read(&arg)
+                .map_err(|e| EvalError::Other(anyhow!("error reading {:?}: {}", arg, e)))?;
+            Ok(app_vk(&binary).to_string())
+        }
+        func => Err(EvalError::Other(anyhow!("unknown function: {}", func))),
+    }
+}
+
+fn var(name: &str, vars: &Vars) -> Result<String, EvalError> {
+    vars.get(name)
+        .cloned()
+        .ok_or_else(|| EvalError::MissingVar(name.to_string()))
+}
+
+fn is_var_name(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn vars(entries: &[(&str, &str)]) -> Vars {
+        entries
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn renders_vars_and_functions() {
+        let vars = vars(&[("utxo", "abcd:0"), ("addr", "tb1q"), ("vk", "ff")]);
+        let template = "apps:\n  $00: n/${sha256(utxo)}/${ vk }\nouts:\n  - address: ${addr}\n";
+        assert_eq!(
+            render(template, &vars).unwrap(),
+            format!(
+                "apps:\n  $00: n/{}/ff\nouts:\n  - address: tb1q\n",
+                hex::encode(Sha256::digest(b"abcd:0"))
+            )
+        );
+        assert_eq!(
+            render(r#"${sha256("abcd:0")}"#, &Vars::new()).unwrap(),
+            hex::encode(Sha256::digest(b"abcd:0"))
+        );
+    }
+
+    #[test]
+    fn escapes_and_plain_dollars() {
+        assert_eq!(
+            render("$00: $${x} costs $5", &Vars::new()).unwrap(),
+            "$00: ${x} costs $5"
+        );
+    }
+
+    #[test]
+    fn reports_all_missing_vars() {
+        let err = render("${a} ${b} ${sha256(c)}", &vars(&[("b", "1")])).unwrap_err();
+        assert_eq!(err.to_string(), "missing template variables: a, c");
+    }
+
+    #[test]
+    fn rejects_malformed_placeholders() {
+        assert!(render("${a", &Vars::new()).is_err());
+        assert!(render("${md5(a)}", &vars(&[("a", "1")])).is_err());
+        assert!(render("${a b}", &Vars::new()).is_err());
+    }
+
+    #[test]
+    fn parses_vars() {
+        assert_eq!(
+            parse_var("utxo=abcd:0").unwrap(),
+            ("utxo".to_string(), "abcd:0".to_string())
+        );
+        assert_eq!(
+            parse_var("x=a=b").unwrap(),
+            ("x".to_string(), "a=b".to_string())
+        );
+        assert!(parse_var("x").is_err());
+        assert!(parse_var("1x=a").is_err());
+    }
+}
@@ -21,6 +21,9 @@ use charms_client::{bitcoin_tx::BitcoinTx, tx::Tx};
 use charms_data::{TxId, UtxoId};
 use std::{collections::BTreeMap, str::FromStr};
 
+/// Outputs with lower amounts are not relayed by Bitcoin nodes (dust).
+pub const DUST_LIMIT: Amount = Amount::from_sat(546);
+
 /// Adds spell data to a Bitcoin transaction by creating a committed spell output and spending it.
 ///
 /// # Arguments
@@ -182,8 +185,7 @@ fn modify_tx(
         witness: Witness::new(),
     });
 
-    // dust limit // TODO make a constant
-    if change_amount >= Amount::from_sat(546) {
+    if change_amount >= DUST_LIMIT {
         tx.output.push(TxOut {
             value: change_amount,
             script_pubkey: change_script_pubkey,
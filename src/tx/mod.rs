@@ -1,6 +1,7 @@
 use crate::spell::Spell;
 use charms_client::{
     NormalizedSpell,
+    spell::AppKeys,
     tx::{EnchantedTx, Tx},
 };
 use charms_data::TxId;
@@ -20,10 +21,12 @@ pub fn norm_spell(tx: &Tx, mock: bool) -> Option<NormalizedSpell> {
         .ok()
 }
 
+/// Spell of the transaction (if it has one with a valid proof), using `app_keys` for the keys of
+/// the apps that have them (see [`charms_client::spell::keys_of_apps`]).
 #[tracing::instrument(level = "debug", skip_all)]
-pub fn spell(tx: &Tx, mock: bool) -> anyhow::Result<Option<Spell>> {
+pub fn spell(tx: &Tx, mock: bool, app_keys: &AppKeys) -> anyhow::Result<Option<Spell>> {
     match norm_spell(tx, mock) {
-        Some(norm_spell) => Ok(Some(Spell::denormalized(&norm_spell)?)),
+        Some(norm_spell) => Ok(Some(Spell::denormalized_with_keys(&norm_spell, app_keys)?)),
         None => Ok(None),
     }
 }
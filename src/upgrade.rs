@@ -0,0 +1,238 @@
+use crate::spell::Spell;
+use anyhow::{anyhow, bail, ensure};
+use charms_client::{CURRENT_VERSION, V6, V7};
+use serde_yaml::{Mapping, Value};
+
+/// Spell upgraded to [`CURRENT_VERSION`].
+#[derive(Clone, Debug)]
+pub struct UpgradedSpell {
+    /// Version of the source spell.
+    pub from_version: u32,
+    /// The spell at [`CURRENT_VERSION`].
+    pub spell: Spell,
+    /// Human-readable descriptions of the changes made, in the order they were applied.
+    pub changes: Vec<String>,
+}
+
+/// Migration of spells from protocol version `from` to `from + 1`.
+struct Migration {
+    from: u32,
+    migrate: fn(&mut Mapping, &mut Vec<String>) -> anyhow::Result<()>,
+}
+
+/// Migrations between consecutive protocol versions, in version order.
+///
+/// [`V6`] is the oldest version source spells can be upgraded from. This tree only knows how
+/// spells of [`V0`](charms_client::V0)..=[`V5`](charms_client::V5) are encoded on-chain and
+/// verified (see [`charms_client::tx::VERSION_PARAMS`]), not what their source spells looked
+/// like or how the rules of the spell checker changed since. A migration for them would be a
+/// guess. Such spells don't need upgrading to stay valid: they are still verified at their own
+/// version. To spend their outputs, write the new spell at [`CURRENT_VERSION`].
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: V6,
+        migrate: v6_to_v7,
+    },
+    Migration {
+        from: V7,
+        migrate: v7_to_v8,
+    },
+];
+
+/// Upgrade a spell source (parsed from YAML or JSON) from any supported older version to
+/// [`CURRENT_VERSION`].
+///
+/// Migrations are applied one version at a time (see [`MIGRATIONS`]). Versions without a
+/// migration path to [`CURRENT_VERSION`] are rejected.
+pub fn upgrade_spell(value: Value) -> anyhow::Result<UpgradedSpell> {
+    upgrade_spell_to(value, CURRENT_VERSION)
+}
+
+fn upgrade_spell_to(mut value: Value, to_version: u32) -> anyhow::Result<UpgradedSpell> {
+    let spell = value
+        .as_mapping_mut()
+        .ok_or(anyhow!("spell must be a map"))?;
+    let from_version = spell
+        .get("version")
+        .ok_or(anyhow!("spell has no version"))?
+        .as_u64()
+        .and_then(|v| u32::try_from(v).ok())
+        .ok_or(anyhow!("spell version must be a non-negative integer"))?;
+    ensure!(
+        from_version <= to_version,
+        "spell version {} is newer than the current protocol version {}: upgrade charms",
+        from_version,
+        to_version
+    );
+
+    let mut changes = vec![];
+    for version in from_version..to_version {
+        let Some(migration) = MIGRATIONS.iter().find(|m| m.from == version) else {
+            bail!(
+                "spell version {} can't be upgraded automatically: the oldest supported version is {}",
+                from_version,
+                MIGRATIONS.first().map_or(to_version, |m| m.from)
+            );
+        };
+        let mut step_changes = vec![];
+        (migration.migrate)(spell, &mut step_changes)?;
+        spell.insert("version".into(), (version + 1).into());
+        step_changes.push(format!("version: {} -> {}", version, version + 1));
+        changes.extend(step_changes);
+    }
+
+    let spell: Spell = serde_yaml::from_value(value)?;
+    Ok(UpgradedSpell {
+        from_version,
+        spell,
+        changes,
+    })
+}
+
+/// V7 renamed spell fields: `public_inputs` to `public_args`, `private_inputs` to
+/// `private_args`, and output fields `sats` to `amount`, `beamed_to` to `beam_to`.
+///
+/// The renames are cosmetic: [`Spell`] still accepts the old names as aliases, and the spell
+/// means the same with either name.
+fn v6_to_v7(spell: &mut Mapping, changes: &mut Vec<String>) -> anyhow::Result<()> {
+    const SPELL_RENAMES: &[(&str, &str)] = &[
+        ("public_inputs", "public_args"),
+        ("private_inputs", "private_args"),
+    ];
+    const OUTPUT_RENAMES: &[(&str, &str)] = &[("sats", "amount"), ("beamed_to", "beam_to")];
+
+    rename_fields(spell, SPELL_RENAMES, "", changes)?;
+    if let Some(outs) = spell.get_mut("outs") {
+        let Some(outs) = outs.as_sequence_mut() else {
+            bail!("spell `outs` must be a list");
+        };
+        for (i, output) in outs.iter_mut().enumerate() {
+            let Some(output) = output.as_mapping_mut() else {
+                bail!("spell output {} must be a map", i);
+            };
+            rename_fields(output, OUTPUT_RENAMES, &format!("outs[{}].", i), changes)?;
+        }
+    }
+    Ok(())
+}
+
+/// V8 spells have the same source format as V7 ones: only the version changes.
+fn v7_to_v8(_spell: &mut Mapping, _changes: &mut Vec<String>) -> anyhow::Result<()> {
+    Ok(())
+}
+
+fn rename_fields(
+    map: &mut Mapping,
+    renames: &[(&str, &str)],
+    path: &str,
+    changes: &mut Vec<String>,
+) -> anyhow::Result<()> {
+    for (old_name, new_name) in renames {
+        let Some(field) = map.remove(*old_name) else {
+            continue;
+        };
+        ensure!(
+            !map.contains_key(*new_name),
+            "spell has both `{}{}` and `{}{}`",
+            path,
+            old_name,
+            path,
+            new_name
+        );
+        map.insert((*new_name).into(), field);
+        changes.push(format!(
+            "renamed `{}{}` to `{}{}`",
+            path, old_name, path, new_name
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use charms_client::{V5, V8};
+
+    const V6_SPELL: &str = r#"
+version: 6
+apps:
+  $TOAD: t/0000000000000000000000000000000000000000000000000000000000000001/0000000000000000000000000000000000000000000000000000000000000002
+public_inputs:
+  $TOAD: 1
+ins:
+  - utxo_id: f72700ac56bd4dd61f2ccb4acdf21d0b11bb294fc3efa9012b77903932197d2f:2
+    charms:
+      $TOAD: 10
+outs:
+  - address: tb1p0000
+    sats: 1000
+    charms:
+      $TOAD: 10
+"#;
+
+    #[test]
+    fn upgrades_v6_to_v7() {
+        let upgraded = upgrade_spell_to(serde_yaml::from_str(V6_SPELL).unwrap(), V7).unwrap();
+        assert_eq!(upgraded.from_version, V6);
+        assert_eq!(upgraded.spell.version, V7);
+        assert!(upgraded.spell.public_args.is_some());
+        assert_eq!(upgraded.spell.outs[0].amount, Some(1000));
+        assert_eq!(
+            upgraded.changes,
+            vec![
+                "renamed `public_inputs` to `public_args`".to_string(),
+                "renamed `outs[0].sats` to `outs[0].amount`".to_string(),
+                "version: 6 -> 7".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn upgrades_v7_to_v8() {
+        let y = "version: 7\napps: {}\nins: []\nouts: []\n";
+        let upgraded = upgrade_spell_to(serde_yaml::from_str(y).unwrap(), V8).unwrap();
+        assert_eq!(upgraded.from_version, V7);
+        assert_eq!(upgraded.spell.version, V8);
+        assert_eq!(upgraded.changes, vec!["version: 7 -> 8".to_string()]);
+    }
+
+    #[test]
+    fn upgrades_to_current_version() {
+        let upgraded = upgrade_spell(serde_yaml::from_str(V6_SPELL).unwrap()).unwrap();
+        assert_eq!(upgraded.spell.version, CURRENT_VERSION);
+        assert_eq!(
+            upgraded.changes.last().unwrap(),
+            &format!("version: {} -> {}", CURRENT_VERSION - 1, CURRENT_VERSION)
+        );
+        assert!(upgraded.spell.normalized().is_ok());
+    }
+
+    #[test]
+    fn current_spell_is_unchanged() {
+        let y = format!(
+            "version: {}\napps: {{}}\nins: []\nouts: []\n",
+            CURRENT_VERSION
+        );
+        let upgraded = upgrade_spell(serde_yaml::from_str(&y).unwrap()).unwrap();
+        assert!(upgraded.changes.is_empty());
+    }
+
+    #[test]
+    fn rejects_unsupported_and_conflicting_spells() {
+        let y = format!(
+            "version: {}\napps: {{}}\nins: []\nouts: []\n",
+            CURRENT_VERSION + 1
+        );
+        assert!(upgrade_spell(serde_yaml::from_str(&y).unwrap()).is_err());
+
+        let y = format!("version: {}\napps: {{}}\nins: []\nouts: []\n", V5);
+        let e = upgrade_spell(serde_yaml::from_str(&y).unwrap()).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "spell version 5 can't be upgraded automatically: the oldest supported version is 6"
+        );
+
+        let y = "version: 6\napps: {}\nins: []\nouts:\n  - sats: 1\n    amount: 2\n";
+        assert!(upgrade_spell(serde_yaml::from_str(y).unwrap()).is_err());
+    }
+}
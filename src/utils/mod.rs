@@ -1,6 +1,11 @@
+use charms_client::cache::MemorySpellCache;
+pub use charms_client::spell::str_index;
+use proof_cache::ProofCache;
 use prover::CharmsSP1Prover;
 use std::{
     fmt::Debug,
+    io::Write,
+    path::Path,
     sync::OnceLock,
     time::{Duration, Instant},
 };
@@ -8,15 +13,86 @@ use tokio::sync::OnceCell;
 
 pub(crate) mod logger;
 pub mod pool;
+pub mod proof_cache;
 pub mod prover;
 #[cfg(feature = "prover")]
 pub(crate) mod sp1;
 
 pub type BoxedSP1Prover = Box<dyn CharmsSP1Prover>;
 
-/// Create a string representation of the index `i` in the format `$xxxx`.
-pub fn str_index(i: &u32) -> String {
-    format!("${:04}", i)
+/// Number of verified spells [`spell_cache`] keeps in memory.
+const SPELL_CACHE_CAPACITY: usize = 1000;
+
+/// Process-wide cache of verified spells of previous transactions.
+///
+/// If `CHARMS_SPELL_CACHE_DIR` environment variable is set, verified spells are also stored in
+/// that directory, so that other processes don't have to verify them again. Entries are
+/// authenticated with the secret in `~/.charms/spell-cache.key` (created if missing).
+pub fn spell_cache() -> &'static MemorySpellCache {
+    static SPELL_CACHE: OnceLock<MemorySpellCache> = OnceLock::new();
+    SPELL_CACHE.get_or_init(|| {
+        let cache = MemorySpellCache::new(SPELL_CACHE_CAPACITY);
+        let Ok(dir) = std::env::var("CHARMS_SPELL_CACHE_DIR") else {
+            return cache;
+        };
+        match spell_cache_secret() {
+            Ok(secret) => cache.with_authenticated_disk_store(dir, secret),
+            Err(e) => {
+                tracing::warn!("not using spell cache directory {}: {}", dir, e);
+                cache
+            }
+        }
+    })
+}
+
+/// Location of the spell cache secret, relative to the home directory.
+const SPELL_CACHE_SECRET_FILE: &str = ".charms/spell-cache.key";
+
+/// Read the spell cache secret, creating it (readable only by the user) if it doesn't exist.
+fn spell_cache_secret() -> anyhow::Result<[u8; 32]> {
+    let home = std::env::var("HOME")?;
+    let path = Path::new(&home).join(SPELL_CACHE_SECRET_FILE);
+    if !path.exists() {
+        std::fs::create_dir_all(path.parent().expect("path has a parent"))?;
+        let secret: [u8; 32] = bitcoin::secp256k1::rand::random();
+        // write the secret to a temporary file and link it into place: readers never see a
+        // partially written secret, and if another process created the secret first, we use theirs
+        let tmp_path = path.with_extension(format!("{}.tmp", std::process::id()));
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        options.open(&tmp_path)?.write_all(&secret)?;
+        let linked = std::fs::hard_link(&tmp_path, &path);
+        std::fs::remove_file(&tmp_path)?;
+        match linked {
+            Ok(()) => return Ok(secret),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    let secret = std::fs::read(&path)?;
+    secret
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("invalid spell cache secret in {:?}", path))
+}
+
+/// Number of compressed spell checker proofs [`proof_cache`] keeps in memory.
+const PROOF_CACHE_CAPACITY: usize = 100;
+
+/// Process-wide cache of compressed spell checker proofs of spells proven by this process.
+///
+/// If `CHARMS_PROOF_CACHE_DIR` environment variable is set, proofs are also written to that
+/// directory, e.g. for proving descendants of the spells in another process.
+pub fn proof_cache() -> &'static ProofCache {
+    static PROOF_CACHE: OnceLock<ProofCache> = OnceLock::new();
+    PROOF_CACHE.get_or_init(|| {
+        let cache = ProofCache::new(PROOF_CACHE_CAPACITY);
+        match std::env::var("CHARMS_PROOF_CACHE_DIR") {
+            Ok(dir) => cache.with_disk_store(dir),
+            Err(_) => cache,
+        }
+    })
 }
 
 pub struct AsyncShared<T> {
@@ -0,0 +1,84 @@
+use charms_client::{
+    batch::committed_data_hash,
+    cache::{DiskStore, LruCache},
+    split_spell_checker_pv,
+};
+use charms_data::B32;
+use sp1_sdk::SP1ProofWithPublicValues;
+use std::path::PathBuf;
+
+/// Store of compressed spell checker proofs, keyed by the hash of the data the spell checker
+/// committed to: `(spell_vk, spell)` (see [`charms_client::batch::spell_hash`]).
+///
+/// Spells proven earlier can then be verified recursively (and cheaply) by the spell checker when
+/// proving their descendants.
+pub struct ProofCache(LruCache<B32, SP1ProofWithPublicValues>);
+
+impl ProofCache {
+    /// New in-memory cache holding up to `capacity` proofs.
+    pub fn new(capacity: usize) -> Self {
+        Self(LruCache::new(capacity))
+    }
+
+    /// Also persist proofs to (and load them from) directory `dir`. Proofs loaded from there are
+    /// only used if they commit to the spell they are stored for.
+    pub fn with_disk_store(self, dir: impl Into<PathBuf>) -> Self {
+        Self(self.0.with_disk_store(DiskStore::new(dir)))
+    }
+
+    pub fn get(&self, spell_hash: &B32) -> Option<SP1ProofWithPublicValues> {
+        self.0.get(spell_hash, |proof| {
+            committed_spell_hash(proof).as_ref() == Some(spell_hash)
+        })
+    }
+
+    pub fn put(&self, spell_hash: B32, proof: SP1ProofWithPublicValues) {
+        self.0.put(spell_hash, proof)
+    }
+}
+
+/// Hash of the data `(spell_vk, spell)` committed to by the spell checker proof.
+pub fn committed_spell_hash(proof: &SP1ProofWithPublicValues) -> Option<B32> {
+    let (committed_data, _) = split_spell_checker_pv(proof.public_values.as_slice())?;
+    Some(committed_data_hash(committed_data))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use charms_client::SPELL_CHECKER_VK_LEN;
+    use sp1_sdk::{SP1Proof, SP1PublicValues};
+
+    fn proof(committed_data: &[u8]) -> SP1ProofWithPublicValues {
+        let public_values = [committed_data, &[0; SPELL_CHECKER_VK_LEN]].concat();
+        SP1ProofWithPublicValues {
+            proof: SP1Proof::Core(vec![]),
+            public_values: SP1PublicValues::from(public_values.as_slice()),
+            sp1_version: String::new(),
+            tee_proof: None,
+        }
+    }
+
+    #[test]
+    fn disk_store_entries_must_match_key() {
+        let dir = std::env::temp_dir().join(format!("charms-proof-cache-{}", std::process::id()));
+        let proof_1 = proof(b"spell 1");
+        let proof_2 = proof(b"spell 2");
+        let hash_1 = committed_spell_hash(&proof_1).unwrap();
+        let hash_2 = committed_spell_hash(&proof_2).unwrap();
+
+        let cache = ProofCache::new(1).with_disk_store(&dir);
+        cache.put(hash_1.clone(), proof_1.clone());
+        // stored under the wrong key
+        cache.put(hash_2.clone(), proof_1.clone());
+
+        let other_cache = ProofCache::new(1).with_disk_store(&dir);
+        assert_eq!(
+            other_cache.get(&hash_1).map(|p| p.public_values.to_vec()),
+            Some(proof_1.public_values.to_vec())
+        );
+        assert!(other_cache.get(&hash_2).is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}